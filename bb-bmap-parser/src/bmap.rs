@@ -6,18 +6,24 @@ use thiserror::Error;
 #[non_exhaustive]
 pub enum HashValue {
     Sha256([u8; 32]),
+    Sha1([u8; 20]),
+    Md5([u8; 16]),
 }
 
 impl HashValue {
     pub fn to_type(&self) -> HashType {
         match self {
             HashValue::Sha256(_) => HashType::Sha256,
+            HashValue::Sha1(_) => HashType::Sha1,
+            HashValue::Md5(_) => HashType::Md5,
         }
     }
 
     pub fn as_slice(&self) -> &[u8] {
         match self {
             HashValue::Sha256(v) => v,
+            HashValue::Sha1(v) => v,
+            HashValue::Md5(v) => v,
         }
     }
 }
@@ -43,7 +49,7 @@ impl BlockRange {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Bmap {
     image_size: u64,
     block_size: u64,
@@ -63,6 +69,11 @@ impl Bmap {
         crate::xml::from_xml(xml)
     }
 
+    /// Serialize to a bmaptool compatible v2.0 .bmap xml file, including `BmapFileChecksum`
+    pub fn to_xml(&self) -> String {
+        crate::xml::to_xml(self)
+    }
+
     /// Image size in bytes
     pub fn image_size(&self) -> u64 {
         self.image_size
@@ -0,0 +1,213 @@
+//! Generate a bmap for a raw or sparse image.
+//!
+//! For files, holes are found using `SEEK_DATA`/`SEEK_HOLE` where available. Like bmaptool, every
+//! block of a data extent is mapped, even if it only contains zeros, and holes are skipped without
+//! being read. Otherwise, blocks which only contain zeros are left unmapped.
+//!
+//! Unmapped blocks are not written while flashing, so they keep whatever the destination
+//! previously contained.
+
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Range;
+
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::bmap::{Bmap, BmapBuilderError, HashValue};
+use crate::xml::HashType;
+
+/// Block size used by bmaptool.
+pub const DEFAULT_BLOCK_SIZE: u64 = 4096;
+
+#[derive(Debug, Error)]
+pub enum GenerateError {
+    #[error("Failed to read image: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Invalid block size: {0}")]
+    InvalidBlockSize(u64),
+    #[error("Invalid bmap: {0}")]
+    BuilderError(#[from] BmapBuilderError),
+}
+
+#[derive(Clone, Debug)]
+pub struct BmapGenerator {
+    block_size: u64,
+}
+
+impl Default for BmapGenerator {
+    fn default() -> Self {
+        Self {
+            block_size: DEFAULT_BLOCK_SIZE,
+        }
+    }
+}
+
+impl BmapGenerator {
+    pub fn block_size(&mut self, block_size: u64) -> &mut Self {
+        self.block_size = block_size;
+        self
+    }
+
+    /// Generate bmap for an image file. Holes in sparse files are skipped without reading them.
+    ///
+    /// If holes cannot be detected, zero blocks are left unmapped instead, like [`Self::generate`].
+    pub fn generate_file(&self, image: &std::fs::File) -> Result<Bmap, GenerateError> {
+        let mut image = image;
+        let size = image.seek(SeekFrom::End(0))?;
+        match data_extents(image, size)? {
+            Some(extents) => self.generate_internal(image, size, extents, false),
+            None => self.generate_internal(image, size, std::iter::once(0..size).collect(), true),
+        }
+    }
+
+    /// Generate bmap for an image by reading all of it. Zero blocks are left unmapped.
+    pub fn generate<R: Read + Seek>(&self, mut image: R) -> Result<Bmap, GenerateError> {
+        let size = image.seek(SeekFrom::End(0))?;
+        self.generate_internal(image, size, std::iter::once(0..size).collect(), true)
+    }
+
+    fn generate_internal<R: Read + Seek>(
+        &self,
+        mut image: R,
+        size: u64,
+        extents: Vec<Range<u64>>,
+        skip_zeros: bool,
+    ) -> Result<Bmap, GenerateError> {
+        let bs = self.block_size;
+        let mut buf = match usize::try_from(bs) {
+            Ok(x) if x > 0 => vec![0; x],
+            _ => return Err(GenerateError::InvalidBlockSize(bs)),
+        };
+
+        let mut builder = Bmap::builder();
+        builder
+            .image_size(size)
+            .block_size(bs)
+            .blocks(size.div_ceil(bs))
+            .checksum_type(HashType::Sha256);
+
+        let mut mapped_blocks = 0;
+        // Start, end and hasher of the range currently being built.
+        let mut current: Option<(u64, u64, Sha256)> = None;
+        let mut pos = None;
+
+        for block in block_ranges(extents, bs).into_iter().flatten() {
+            let offset = block * bs;
+            let len = bs.min(size - offset) as usize;
+
+            if pos != Some(offset) {
+                image.seek(SeekFrom::Start(offset))?;
+            }
+            image.read_exact(&mut buf[..len])?;
+            pos = Some(offset + len as u64);
+
+            let data = &buf[..len];
+            if skip_zeros && data.iter().all(|x| *x == 0) {
+                finish_range(&mut builder, current.take());
+                continue;
+            }
+
+            mapped_blocks += 1;
+            match &mut current {
+                Some((_, end, hasher)) if *end == offset => {
+                    *end += len as u64;
+                    hasher.update(data);
+                }
+                _ => {
+                    finish_range(&mut builder, current.take());
+                    current = Some((offset, offset + len as u64, Sha256::new_with_prefix(data)));
+                }
+            }
+        }
+        finish_range(&mut builder, current);
+
+        builder.mapped_blocks(mapped_blocks);
+        builder.build().map_err(Into::into)
+    }
+}
+
+fn finish_range(builder: &mut crate::bmap::BmapBuilder, range: Option<(u64, u64, Sha256)>) {
+    if let Some((start, end, hasher)) = range {
+        builder.add_byte_range(
+            start,
+            end - start,
+            HashValue::Sha256(hasher.finalize().into()),
+        );
+    }
+}
+
+/// Convert sorted byte extents to non-overlapping block ranges.
+fn block_ranges(extents: Vec<Range<u64>>, block_size: u64) -> Vec<Range<u64>> {
+    let mut ans: Vec<Range<u64>> = Vec::with_capacity(extents.len());
+
+    for extent in extents.into_iter().filter(|x| !x.is_empty()) {
+        let start = extent.start / block_size;
+        let end = extent.end.div_ceil(block_size);
+
+        match ans.last_mut() {
+            Some(last) if last.end >= start => last.end = last.end.max(end),
+            _ => ans.push(start..end),
+        }
+    }
+
+    ans
+}
+
+/// Data extents of a sparse file. Returns [`None`] if holes cannot be detected.
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+fn data_extents(file: &std::fs::File, size: u64) -> std::io::Result<Option<Vec<Range<u64>>>> {
+    use std::os::fd::AsRawFd;
+
+    let seek = |offset: u64, whence| -> std::io::Result<Option<u64>> {
+        let offset = libc::off_t::try_from(offset).map_err(std::io::Error::other)?;
+        // SAFETY: The file descriptor is valid for the lifetime of `file`.
+        match unsafe { libc::lseek(file.as_raw_fd(), offset, whence) } {
+            -1 => {
+                let e = std::io::Error::last_os_error();
+                // No data or hole after the offset.
+                if e.raw_os_error() == Some(libc::ENXIO) {
+                    Ok(None)
+                } else {
+                    Err(e)
+                }
+            }
+            x => Ok(Some(x as u64)),
+        }
+    };
+
+    let mut ans = Vec::new();
+    let mut pos = 0;
+    while pos < size {
+        let start = match seek(pos, libc::SEEK_DATA) {
+            Ok(Some(x)) => x,
+            Ok(None) => break,
+            // Not supported by the filesystem.
+            Err(e) if e.raw_os_error() == Some(libc::EINVAL) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let end = seek(start, libc::SEEK_HOLE)?.unwrap_or(size).min(size);
+
+        ans.push(start..end);
+        pos = end;
+    }
+
+    Ok(Some(ans))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+fn data_extents(_: &std::fs::File, _: u64) -> std::io::Result<Option<Vec<Range<u64>>>> {
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_block_ranges() {
+        let ranges = block_ranges(vec![0..10, 100..4096, 4097..5000, 9000..9000], 4096);
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0], 0..2);
+        assert_eq!(block_ranges(vec![0..4096, 8192..8193], 4096), [0..1, 2..3]);
+    }
+}
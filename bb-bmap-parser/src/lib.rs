@@ -1,5 +1,8 @@
 mod bmap;
 pub use crate::bmap::*;
 
+mod generate;
+pub use generate::{BmapGenerator, DEFAULT_BLOCK_SIZE, GenerateError};
+
 mod xml;
 pub use xml::{HashType, XmlError};
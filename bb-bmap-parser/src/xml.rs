@@ -1,6 +1,8 @@
 use crate::bmap::{BmapBuilder, BmapBuilderError, HashValue};
 use quick_xml::de::{DeError, from_str};
 use serde::Deserialize;
+use sha2::Digest;
+use std::fmt::Write;
 use thiserror::Error;
 
 /// Custom deserializer to first trim whitespace around text elements before converting.
@@ -20,6 +22,44 @@ where
 #[non_exhaustive]
 pub enum HashType {
     Sha256,
+    Sha1,
+    Md5,
+}
+
+impl HashType {
+    /// Size of the digest in bytes
+    pub const fn size(&self) -> usize {
+        match self {
+            Self::Sha256 => 32,
+            Self::Sha1 => 20,
+            Self::Md5 => 16,
+        }
+    }
+
+    /// Digest of `data` concatenated
+    pub(crate) fn digest(&self, data: &[&[u8]]) -> HashValue {
+        fn hash<D: Digest>(data: &[&[u8]]) -> sha2::digest::Output<D> {
+            data.iter()
+                .fold(D::new(), |h, x| h.chain_update(x))
+                .finalize()
+        }
+
+        match self {
+            Self::Sha256 => HashValue::Sha256(hash::<sha2::Sha256>(data).into()),
+            Self::Sha1 => HashValue::Sha1(hash::<sha1::Sha1>(data).into()),
+            Self::Md5 => HashValue::Md5(hash::<md5::Md5>(data).into()),
+        }
+    }
+}
+
+impl std::fmt::Display for HashType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Sha256 => write!(f, "sha256"),
+            Self::Sha1 => write!(f, "sha1"),
+            Self::Md5 => write!(f, "md5"),
+        }
+    }
 }
 
 impl std::str::FromStr for HashType {
@@ -28,6 +68,8 @@ impl std::str::FromStr for HashType {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "sha256" => Ok(Self::Sha256),
+            "sha1" => Ok(Self::Sha1),
+            "md5" => Ok(Self::Md5),
             _ => Err("Unsupported"),
         }
     }
@@ -35,19 +77,19 @@ impl std::str::FromStr for HashType {
 
 #[derive(Debug, Deserialize)]
 struct Range {
-    #[serde(rename = "@chksum", deserialize_with = "deserialize_trimmed")]
-    chksum: String,
+    /// Bmap 1.3 and older use `sha1` attribute.
+    #[serde(rename = "@chksum", alias = "@sha1")]
+    chksum: Option<String>,
     #[serde(rename = "$value", deserialize_with = "deserialize_trimmed")]
     range: String,
 }
 
 #[derive(Debug, Deserialize)]
 struct BlockMap {
-    #[serde(rename = "Range")]
+    #[serde(rename = "Range", default)]
     ranges: Vec<Range>,
 }
 
-#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 struct Bmap {
     #[serde(rename = "@version", deserialize_with = "deserialize_trimmed")]
@@ -60,10 +102,15 @@ struct Bmap {
     blocks_count: u64,
     #[serde(rename = "MappedBlocksCount", deserialize_with = "deserialize_trimmed")]
     mapped_blocks_count: u64,
-    #[serde(rename = "ChecksumType", deserialize_with = "deserialize_trimmed")]
-    checksum_type: HashType,
-    #[serde(rename = "BmapFileChecksum", deserialize_with = "deserialize_trimmed")]
-    bmap_file_checksum: String,
+    /// Added in bmap 1.4. Older versions always use SHA-1.
+    #[serde(rename = "ChecksumType")]
+    checksum_type: Option<String>,
+    /// Bmap 1.4 and newer.
+    #[serde(rename = "BmapFileChecksum")]
+    bmap_file_checksum: Option<String>,
+    /// Bmap 1.3.
+    #[serde(rename = "BmapFileSHA1")]
+    bmap_file_sha1: Option<String>,
     #[serde(rename = "BlockMap")]
     block_map: BlockMap,
 }
@@ -78,6 +125,14 @@ pub enum XmlError {
     UnknownChecksumType(String),
     #[error("Invalid checksum: {0}")]
     InvalidChecksum(String),
+    #[error("Unsupported bmap version: {0}")]
+    UnsupportedVersion(String),
+    #[error("Missing {0}")]
+    MissingElement(&'static str),
+    #[error("Invalid block range: {0}")]
+    InvalidRange(String),
+    #[error("Bmap file checksum mismatch. Expected {expected}, got {actual}")]
+    BmapChecksumMismatch { expected: String, actual: String },
 }
 
 const fn hexdigit_to_u8(c: u8) -> Option<u8> {
@@ -114,10 +169,88 @@ fn str_to_digest(s: String, digest: &mut [u8]) -> Result<(), XmlError> {
     Ok(())
 }
 
+fn parse_version(version: &str) -> Option<(u32, u32)> {
+    let (major, minor) = version.split_once('.')?;
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+fn parse_checksum(hash_type: HashType, s: String) -> Result<HashValue, XmlError> {
+    let checksum = match hash_type {
+        HashType::Sha256 => {
+            let mut v = [0; 32];
+            str_to_digest(s, &mut v)?;
+            HashValue::Sha256(v)
+        }
+        HashType::Sha1 => {
+            let mut v = [0; 20];
+            str_to_digest(s, &mut v)?;
+            HashValue::Sha1(v)
+        }
+        HashType::Md5 => {
+            let mut v = [0; 16];
+            str_to_digest(s, &mut v)?;
+            HashValue::Md5(v)
+        }
+    };
+
+    Ok(checksum)
+}
+
+/// Verify checksum of the bmap file itself. Like bmaptool, the first occurrence of the checksum in
+/// the file is replaced with zeros before calculating the checksum.
+fn verify_bmap_checksum(xml: &str, hash_type: HashType, expected: &str) -> Result<(), XmlError> {
+    let pos = xml
+        .find(expected)
+        .ok_or_else(|| XmlError::InvalidChecksum(expected.to_string()))?;
+    let zeros = "0".repeat(expected.len());
+    let xml = xml.as_bytes();
+    let actual = hash_type.digest(&[&xml[..pos], zeros.as_bytes(), &xml[pos + expected.len()..]]);
+    let actual = digest_to_str(actual.as_slice());
+
+    if actual.eq_ignore_ascii_case(expected) {
+        Ok(())
+    } else {
+        Err(XmlError::BmapChecksumMismatch {
+            expected: expected.to_string(),
+            actual,
+        })
+    }
+}
+
 pub(crate) fn from_xml(xml: &str) -> Result<crate::bmap::Bmap, XmlError> {
     let b: Bmap = from_str(xml)?;
+    let version = parse_version(&b.version)
+        .filter(|(major, _)| (1..=2).contains(major))
+        .ok_or_else(|| XmlError::UnsupportedVersion(b.version.clone()))?;
+
+    let hash_type = if version >= (1, 4) {
+        let t = b
+            .checksum_type
+            .ok_or(XmlError::MissingElement("ChecksumType"))?;
+        t.trim()
+            .parse()
+            .map_err(|_| XmlError::UnknownChecksumType(t))?
+    } else {
+        HashType::Sha1
+    };
+
+    // Bmap 1.2 and older do not contain a checksum of the file.
+    let bmap_checksum = match version {
+        (1, 3) => Some(
+            b.bmap_file_sha1
+                .ok_or(XmlError::MissingElement("BmapFileSHA1"))?,
+        ),
+        x if x >= (1, 4) => Some(
+            b.bmap_file_checksum
+                .ok_or(XmlError::MissingElement("BmapFileChecksum"))?,
+        ),
+        _ => None,
+    };
+    if let Some(x) = bmap_checksum {
+        verify_bmap_checksum(xml, hash_type, x.trim())?;
+    }
+
     let mut builder = BmapBuilder::default();
-    let hash_type = b.checksum_type;
     builder
         .image_size(b.image_size)
         .block_size(b.block_size)
@@ -126,25 +259,105 @@ pub(crate) fn from_xml(xml: &str) -> Result<crate::bmap::Bmap, XmlError> {
         .mapped_blocks(b.mapped_blocks_count);
 
     for range in b.block_map.ranges {
-        let mut split = range.range.trim().splitn(2, '-');
-        let start = match split.next() {
-            Some(s) => s.parse().unwrap(),
-            None => unimplemented!("woops"),
+        let parse = |x: &str| {
+            x.trim()
+                .parse::<u64>()
+                .map_err(|_| XmlError::InvalidRange(range.range.clone()))
         };
-        let end = match split.next() {
-            Some(s) => s.parse().unwrap(),
-            None => start,
+        let (start, end) = match range.range.split_once('-') {
+            Some((start, end)) => (parse(start)?, parse(end)?),
+            None => (parse(&range.range)?, parse(&range.range)?),
         };
+        if start > end || end >= b.blocks_count {
+            return Err(XmlError::InvalidRange(range.range));
+        }
 
-        let checksum = match hash_type {
-            HashType::Sha256 => {
-                let mut v = [0; 32];
-                str_to_digest(range.chksum, &mut v)?;
-                HashValue::Sha256(v)
-            }
-        };
+        let chksum = range
+            .chksum
+            .ok_or(XmlError::MissingElement("Range checksum"))?;
+        let checksum = parse_checksum(hash_type, chksum.trim().to_string())?;
         builder.add_block_range(start, end, checksum);
     }
 
     builder.build().map_err(std::convert::Into::into)
 }
+
+fn digest_to_str(digest: &[u8]) -> String {
+    digest
+        .iter()
+        .fold(String::with_capacity(digest.len() * 2), |mut s, x| {
+            write!(s, "{x:02x}").unwrap();
+            s
+        })
+}
+
+pub(crate) fn to_xml(bmap: &crate::bmap::Bmap) -> String {
+    let bs = bmap.block_size();
+    let mut head = String::new();
+    let mut tail = String::new();
+
+    writeln!(head, r#"<?xml version="1.0" ?>"#).unwrap();
+    writeln!(head, r#"<bmap version="2.0">"#).unwrap();
+    writeln!(head, "    <!-- Image size in bytes -->").unwrap();
+    writeln!(head, "    <ImageSize> {} </ImageSize>", bmap.image_size()).unwrap();
+    writeln!(head, "    <!-- Size of a block in bytes -->").unwrap();
+    writeln!(head, "    <BlockSize> {bs} </BlockSize>").unwrap();
+    writeln!(head, "    <!-- Count of blocks in the image file -->").unwrap();
+    writeln!(head, "    <BlocksCount> {} </BlocksCount>", bmap.blocks()).unwrap();
+    writeln!(head, "    <!-- Count of mapped blocks -->").unwrap();
+    writeln!(
+        head,
+        "    <MappedBlocksCount> {} </MappedBlocksCount>",
+        bmap.mapped_blocks()
+    )
+    .unwrap();
+    writeln!(head, "    <!-- Type of checksum used in this file -->").unwrap();
+    writeln!(
+        head,
+        "    <ChecksumType> {} </ChecksumType>",
+        bmap.checksum_type()
+    )
+    .unwrap();
+    writeln!(
+        head,
+        "    <!-- The checksum of this bmap file. When it is calculated, the value of"
+    )
+    .unwrap();
+    writeln!(
+        head,
+        r#"         the checksum has to be zero (all ASCII "0" symbols). -->"#
+    )
+    .unwrap();
+    write!(head, "    <BmapFileChecksum> ").unwrap();
+
+    writeln!(tail, " </BmapFileChecksum>").unwrap();
+    writeln!(tail, "    <BlockMap>").unwrap();
+    for range in bmap.block_map() {
+        let start = range.offset() / bs;
+        let end = (range.offset() + range.length()).div_ceil(bs) - 1;
+        let chksum = digest_to_str(range.checksum().as_slice());
+
+        if start == end {
+            writeln!(
+                tail,
+                r#"        <Range chksum="{chksum}"> {start} </Range>"#
+            )
+            .unwrap();
+        } else {
+            writeln!(
+                tail,
+                r#"        <Range chksum="{chksum}"> {start}-{end} </Range>"#
+            )
+            .unwrap();
+        }
+    }
+    writeln!(tail, "    </BlockMap>").unwrap();
+    writeln!(tail, "</bmap>").unwrap();
+
+    // The checksum is calculated with the checksum field set to all zeros.
+    let hash_type = bmap.checksum_type();
+    let zeros = "0".repeat(hash_type.size() * 2);
+    let checksum = hash_type.digest(&[head.as_bytes(), zeros.as_bytes(), tail.as_bytes()]);
+
+    head + &digest_to_str(checksum.as_slice()) + &tail
+}
@@ -5,6 +5,7 @@
 //! the `BmapBuilderError` matrix, and the `XmlError` failure branches.
 
 use bb_bmap_parser::{Bmap, BmapBuilderError, HashType, HashValue, XmlError};
+use sha2::{Digest, Sha256};
 
 /// Build a fully-specified `Bmap`, exercising every getter, the byte-range
 /// clamping in `add_block_range`, and `total_mapped_size`.
@@ -123,8 +124,9 @@ fn builder_missing_field_matrix() {
     // cannot be exercised through the public API.
 }
 
+/// Minimal bmap 2.0 file. `BmapFileChecksum` is always calculated using SHA-256.
 fn minimal_xml(checksum_type: &str, chksum: &str, range: &str) -> String {
-    format!(
+    let xml = format!(
         r#"<?xml version="1.0" ?>
 <bmap version="2.0">
   <ImageSize>8192</ImageSize>
@@ -137,7 +139,13 @@ fn minimal_xml(checksum_type: &str, chksum: &str, range: &str) -> String {
     <Range chksum="{chksum}">{range}</Range>
   </BlockMap>
 </bmap>"#
-    )
+    );
+    let zeros = "0".repeat(64);
+    let checksum: String = Sha256::digest(xml.as_bytes())
+        .iter()
+        .map(|x| format!("{x:02x}"))
+        .collect();
+    xml.replacen(&zeros, &checksum, 1)
 }
 
 const VALID_SHA: &str = "5feceb66ffc86f38d952786c6d696c79c2dbc239dd4e91b46729d73a27fb57e9";
@@ -178,9 +186,10 @@ fn from_xml_rejects_non_hex_checksum() {
 
 #[test]
 fn from_xml_rejects_unknown_checksum_type() {
-    let xml = minimal_xml("md5", VALID_SHA, "0");
+    let xml = minimal_xml("crc32", VALID_SHA, "0");
     let err = Bmap::from_xml(&xml).expect_err("unknown checksum type");
-    // The unknown type fails during deserialization, so it surfaces as a parse
-    // error rather than `XmlError::UnknownChecksumType` (which is unused).
-    assert!(matches!(err, XmlError::XmlParsError(_)), "got {err:?}");
+    assert!(
+        matches!(err, XmlError::UnknownChecksumType(ref x) if x == "crc32"),
+        "got {err:?}"
+    );
 }
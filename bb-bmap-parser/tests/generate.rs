@@ -0,0 +1,131 @@
+//! Integration tests for `BmapGenerator`.
+
+use std::io::{Cursor, Seek, SeekFrom, Write};
+
+use bb_bmap_parser::{BmapGenerator, GenerateError, HashType, HashValue};
+use sha2::{Digest, Sha256};
+
+fn sha256(data: &[u8]) -> HashValue {
+    HashValue::Sha256(Sha256::digest(data).into())
+}
+
+/// Zero blocks are unmapped, consecutive data blocks are merged into a single
+/// range and the final partial block is clamped to the image size.
+#[test]
+fn generate_detects_zero_blocks() {
+    let mut image = vec![0u8; 4096 * 5 + 100];
+    image[..4096].fill(1);
+    image[4096] = 2;
+    image[4096 * 3 + 10] = 3;
+    image[4096 * 5 + 99] = 4;
+
+    let bmap = BmapGenerator::default()
+        .generate(Cursor::new(&image))
+        .unwrap();
+
+    assert_eq!(bmap.image_size(), image.len() as u64);
+    assert_eq!(bmap.block_size(), 4096);
+    assert_eq!(bmap.blocks(), 6);
+    assert_eq!(bmap.mapped_blocks(), 4);
+    assert_eq!(bmap.checksum_type(), HashType::Sha256);
+
+    let ranges: Vec<_> = bmap
+        .block_map()
+        .map(|x| (x.offset(), x.length(), x.checksum()))
+        .collect();
+    assert_eq!(
+        ranges,
+        [
+            (0, 8192, sha256(&image[..8192])),
+            (4096 * 3, 4096, sha256(&image[4096 * 3..4096 * 4])),
+            (4096 * 5, 100, sha256(&image[4096 * 5..])),
+        ]
+    );
+}
+
+#[test]
+fn generate_custom_block_size() {
+    let mut image = vec![0u8; 2048];
+    image[600] = 1;
+
+    let bmap = BmapGenerator::default()
+        .block_size(512)
+        .generate(Cursor::new(&image))
+        .unwrap();
+
+    assert_eq!(bmap.blocks(), 4);
+    assert_eq!(bmap.mapped_blocks(), 1);
+    let range = bmap.block_map().next().unwrap();
+    assert_eq!((range.offset(), range.length()), (512, 512));
+}
+
+#[test]
+fn generate_empty_image() {
+    let bmap = BmapGenerator::default()
+        .generate(Cursor::new(Vec::new()))
+        .unwrap();
+
+    assert_eq!(bmap.blocks(), 0);
+    assert_eq!(bmap.mapped_blocks(), 0);
+    assert_eq!(bmap.block_map().len(), 0);
+}
+
+#[test]
+fn generate_rejects_zero_block_size() {
+    let err = BmapGenerator::default()
+        .block_size(0)
+        .generate(Cursor::new(vec![1u8; 16]))
+        .unwrap_err();
+    assert!(matches!(err, GenerateError::InvalidBlockSize(0)), "{err:?}");
+}
+
+/// Holes are unmapped, whether or not they can be detected.
+#[test]
+fn generate_sparse_file() {
+    let mut file = tempfile::tempfile().unwrap();
+    file.set_len(16 * 1024 * 1024).unwrap();
+    file.seek(SeekFrom::Start(8 * 1024 * 1024 + 10)).unwrap();
+    file.write_all(b"hello").unwrap();
+    file.flush().unwrap();
+
+    let bmap = BmapGenerator::default().generate_file(&file).unwrap();
+
+    assert_eq!(bmap.image_size(), 16 * 1024 * 1024);
+    assert_eq!(bmap.mapped_blocks(), 1);
+
+    let mut block = vec![0u8; 4096];
+    block[10..15].copy_from_slice(b"hello");
+    let ranges: Vec<_> = bmap
+        .block_map()
+        .map(|x| (x.offset(), x.length(), x.checksum()))
+        .collect();
+    assert_eq!(ranges, [(8 * 1024 * 1024, 4096, sha256(&block))]);
+}
+
+/// Zeros written to a file are part of a data extent, so they are mapped like
+/// bmaptool does. Only holes are skipped.
+#[cfg(target_os = "linux")]
+#[test]
+fn generate_file_maps_zeros_in_data_extents() {
+    let mut file = tempfile::tempfile().unwrap();
+    file.set_len(16 * 1024 * 1024).unwrap();
+    file.seek(SeekFrom::Start(8 * 1024 * 1024)).unwrap();
+    let mut data = vec![0u8; 8192];
+    data[4096] = 1;
+    file.write_all(&data).unwrap();
+    file.flush().unwrap();
+
+    let bmap = BmapGenerator::default().generate_file(&file).unwrap();
+    assert_eq!(bmap.mapped_blocks(), 2);
+
+    let ranges: Vec<_> = bmap
+        .block_map()
+        .map(|x| (x.offset(), x.length(), x.checksum()))
+        .collect();
+    assert_eq!(ranges, [(8 * 1024 * 1024, 8192, sha256(&data))]);
+
+    // The same image read as a stream leaves the zero block unmapped.
+    file.rewind().unwrap();
+    let bmap = BmapGenerator::default().generate(&file).unwrap();
+    assert_eq!(bmap.mapped_blocks(), 1);
+}
@@ -1,5 +1,7 @@
-use bb_bmap_parser::Bmap;
-use sha2::{Sha256, Digest};
+use bb_bmap_parser::{Bmap, BmapGenerator, HashType, HashValue, XmlError};
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
 
 #[test]
 fn parse() {
@@ -23,3 +25,153 @@ fn parse() {
     }
     assert_eq!(2048, block);
 }
+
+/// Checksum of a bmap file, calculated with `BmapFileChecksum` set to zeros.
+fn bmap_file_checksum(xml: &str) -> String {
+    let start = xml.find("<BmapFileChecksum>").unwrap() + "<BmapFileChecksum>".len();
+    let end = xml.find("</BmapFileChecksum>").unwrap();
+    let expected = xml[start..end].trim();
+
+    let zeroed = xml.replacen(expected, &"0".repeat(expected.len()), 1);
+    let digest = Sha256::digest(zeroed.as_bytes());
+    assert_eq!(
+        digest
+            .iter()
+            .map(|x| format!("{x:02x}"))
+            .collect::<String>(),
+        expected
+    );
+
+    expected.to_string()
+}
+
+#[test]
+fn round_trip() {
+    let xml = include_str!("data/simple.bmap");
+    let bmap = Bmap::from_xml(xml).unwrap();
+
+    let out = bmap.to_xml();
+    assert!(out.contains(r#"<bmap version="2.0">"#));
+    assert!(out.contains(r#"<Range chksum="5feceb66ffc86f38d952786c6d696c79c2dbc239dd4e91b46729d73a27fb57e9"> 0 </Range>"#));
+    assert!(out.contains(r#"<Range chksum="94f8607915dff25f013e45fc0642fb9830b0fb25ab0ab46d477eaf1061def379"> 512-1024 </Range>"#));
+    assert_ne!(bmap_file_checksum(&out), "0".repeat(64));
+
+    let parsed = Bmap::from_xml(&out).unwrap();
+    assert_eq!(bmap, parsed);
+    assert_eq!(out, parsed.to_xml());
+}
+
+#[test]
+fn round_trip_generated() {
+    let mut image = vec![0u8; 4096 * 4 + 10];
+    image[4096 * 2] = 1;
+    image[4096 * 4 + 9] = 1;
+
+    for image in [image, Vec::new()] {
+        let bmap = BmapGenerator::default()
+            .generate(std::io::Cursor::new(image))
+            .unwrap();
+        let out = bmap.to_xml();
+        bmap_file_checksum(&out);
+
+        assert_eq!(bmap, Bmap::from_xml(&out).unwrap());
+    }
+}
+
+fn ranges(bmap: &Bmap) -> Vec<(u64, u64, HashValue)> {
+    bmap.block_map()
+        .map(|x| (x.offset(), x.length(), x.checksum()))
+        .collect()
+}
+
+#[test]
+fn parse_v1() {
+    let sha1 = |x: &str| HashValue::Sha1(Sha1::digest(x.as_bytes()).into());
+    let md5 = |x: &str| HashValue::Md5(Md5::digest(x.as_bytes()).into());
+
+    let cases = [
+        (
+            include_str!("data/v1.2.bmap"),
+            HashType::Sha1,
+            [sha1("0"), sha1("2")],
+        ),
+        (
+            include_str!("data/v1.3.bmap"),
+            HashType::Sha1,
+            [sha1("0"), sha1("2")],
+        ),
+        (
+            include_str!("data/v1.4.bmap"),
+            HashType::Md5,
+            [md5("0"), md5("2")],
+        ),
+    ];
+
+    for (xml, hash_type, [first, second]) in cases {
+        let bmap = Bmap::from_xml(xml).unwrap();
+
+        assert_eq!(bmap.checksum_type(), hash_type);
+        assert_eq!(bmap.image_size(), 4 * 4096);
+        assert_eq!(bmap.mapped_blocks(), 3);
+        assert_eq!(
+            ranges(&bmap),
+            [(0, 4096, first), (2 * 4096, 2 * 4096, second)]
+        );
+
+        // Written back as 2.0, keeping the checksum type.
+        let out = bmap.to_xml();
+        assert!(out.contains(&format!("<ChecksumType> {hash_type} </ChecksumType>")));
+        assert_eq!(bmap, Bmap::from_xml(&out).unwrap());
+    }
+}
+
+#[test]
+fn bmap_checksum_mismatch() {
+    for xml in [
+        include_str!("data/simple.bmap"),
+        include_str!("data/v1.3.bmap"),
+        include_str!("data/v1.4.bmap"),
+    ] {
+        let edited = xml.replacen("<MappedBlocksCount> ", "<MappedBlocksCount> 1", 1);
+        let err = Bmap::from_xml(&edited).unwrap_err();
+        assert!(
+            matches!(err, XmlError::BmapChecksumMismatch { .. }),
+            "got {err:?}"
+        );
+    }
+
+    // Bmap 1.2 does not contain a checksum of the file.
+    let edited = include_str!("data/v1.2.bmap").replacen("> 3 <", "> 2 <", 1);
+    assert_eq!(Bmap::from_xml(&edited).unwrap().mapped_blocks(), 2);
+}
+
+#[test]
+fn bmap_truncated() {
+    let xml = include_str!("data/simple.bmap");
+    let truncated = &xml[..xml.find("<Range chksum=\"e29c").unwrap()];
+    assert!(Bmap::from_xml(truncated).is_err());
+}
+
+#[test]
+fn bmap_missing_checksum() {
+    let xml = include_str!("data/v1.3.bmap");
+    let start = xml.find("    <BmapFileSHA1>").unwrap();
+    let end = xml.find("</BmapFileSHA1>\n").unwrap() + "</BmapFileSHA1>\n".len();
+    let edited = format!("{}{}", &xml[..start], &xml[end..]);
+
+    let err = Bmap::from_xml(&edited).unwrap_err();
+    assert!(
+        matches!(err, XmlError::MissingElement("BmapFileSHA1")),
+        "got {err:?}"
+    );
+}
+
+#[test]
+fn unsupported_version() {
+    let xml = include_str!("data/simple.bmap").replacen(r#"version="2.0""#, r#"version="3.0""#, 1);
+    let err = Bmap::from_xml(&xml).unwrap_err();
+    assert!(
+        matches!(err, XmlError::UnsupportedVersion(ref x) if x == "3.0"),
+        "got {err:?}"
+    );
+}
@@ -1,5 +1,8 @@
 //! Abstractions to parse and generate distros.json file.
 
+use std::collections::BTreeMap;
+
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_with::{Map, VecSkipError, serde_as};
 use url::Url;
@@ -8,7 +11,7 @@ use url::Url;
 ///
 /// [BeagleBoard.org]: https://www.beagleboard.org/
 #[serde_as]
-#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, Default, PartialEq, Eq)]
 pub struct Config {
     #[serde(default)]
     pub imager: Imager,
@@ -17,16 +20,40 @@ pub struct Config {
     pub os_list: Vec<OsListItem>,
 }
 
+impl Config {
+    /// All [`OsImage`]s in the config, including the ones in nested [`OsSubList`]s.
+    ///
+    /// Images in [`OsRemoteSubList`]s are not included, since they need to be downloaded first.
+    pub fn images(&self) -> impl Iterator<Item = &OsImage> {
+        let mut stack: Vec<&OsListItem> = self.os_list.iter().rev().collect();
+
+        std::iter::from_fn(move || {
+            while let Some(item) = stack.pop() {
+                match item {
+                    OsListItem::Image(x) => return Some(x),
+                    OsListItem::SubList(x) => stack.extend(x.subitems.iter().rev()),
+                    OsListItem::RemoteSubList(_) => {}
+                }
+            }
+            None
+        })
+    }
+}
+
 /// Contains information regarding BeagleBoard Images version and a list of [BeagleBoard.org]
 /// boards along with information regarding each board.
 ///
 /// [BeagleBoard.org]: https://www.beagleboard.org/
 #[serde_as]
-#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, Default, PartialEq, Eq)]
 pub struct Imager {
     /// A list of remote config files
     #[serde(default)]
     pub remote_configs: Vec<Url>,
+    /// Minisign public keys for remote config files, by URL. A remote config with a key must be
+    /// signed (see [`signature`](crate::signature)). Remote configs without a key are unverified.
+    #[serde(default)]
+    pub remote_config_keys: BTreeMap<Url, String>,
     #[serde_as(as = "VecSkipError<_>")]
     #[serde(default)]
     /// List of BeagleBoard.org boards
@@ -37,7 +64,7 @@ pub struct Imager {
 ///
 /// [BeagleBoard.org]: https://www.beagleboard.org/
 #[serde_as]
-#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
 pub struct Device {
     /// Board Name
     pub name: String,
@@ -63,7 +90,7 @@ pub struct Device {
 }
 
 /// Types of customization Initialization formats
-#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq, Default)]
 #[non_exhaustive]
 #[serde(rename_all = "lowercase")]
 pub enum InitFormat {
@@ -115,7 +142,7 @@ impl std::fmt::Display for InitFormat {
 }
 
 /// Os List can contain multiple types of items depending on the situation.
-#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
 #[serde(untagged)]
 #[allow(clippy::large_enum_variant)]
 pub enum OsListItem {
@@ -137,7 +164,7 @@ pub enum OsListItem {
 
 /// [`OsListItem`] which itself can contain a list of [`OsListItem`].
 #[serde_as]
-#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
 pub struct OsSubList {
     /// Sublist name
     pub name: String,
@@ -154,7 +181,7 @@ pub struct OsSubList {
 }
 
 /// Sublists stored in a remote location
-#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
 pub struct OsRemoteSubList {
     /// Remote Sublist name
     pub name: String,
@@ -169,10 +196,13 @@ pub struct OsRemoteSubList {
     pub devices: Box<[Box<str>]>,
     /// Url to the Remote list
     pub subitems_url: Url,
+    /// Minisign public key for the remote list. The list must be signed if present (see
+    /// [`signature`](crate::signature)). Remote lists without a key are unverified.
+    pub public_key: Option<String>,
 }
 
 /// A singular Os Image for board(s)
-#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
 pub struct OsImage {
     /// Os Image name
     pub name: String,
@@ -182,10 +212,14 @@ pub struct OsImage {
     pub icon: Url,
     /// Os Image download URL
     pub url: Url,
+    /// Mirrors for the download URL. Tried in order when downloading from `url` fails.
+    #[serde(default)]
+    pub mirrors: Vec<Url>,
     /// Os Image size before download
     pub image_download_size: Option<u64>,
     /// Os Image sha256 (before extraction)
     #[serde(with = "const_hex")]
+    #[schemars(with = "String", pattern(r"^(0x)?[0-9a-fA-F]{64}$"))]
     pub image_download_sha256: [u8; 32],
     /// Os Image size after extraction
     pub extract_size: u64,
@@ -205,7 +239,7 @@ pub struct OsImage {
 }
 
 /// Types of flashers Os Image(s) support
-#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize, JsonSchema)]
 pub enum Flasher {
     #[default]
     /// Image needs to be written to SD Card
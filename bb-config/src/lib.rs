@@ -6,6 +6,9 @@
 //! This crate provides abstractions to parse and generate distros.json file.
 
 pub mod config;
+pub mod lint;
+pub mod schema;
+pub mod signature;
 
 pub use config::Config;
 
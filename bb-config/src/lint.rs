@@ -0,0 +1,231 @@
+//! Validation of distros.json files.
+//!
+//! [`Config`] skips boards and OS list items which fail to parse, so that a single malformed entry
+//! does not break the whole config. [`lint`] reports such entries along with the reason, and also
+//! checks for entries which parse fine but are likely mistakes.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::config::{Config, Device, Flasher, OsImage, OsRemoteSubList, OsSubList};
+
+/// SD card images larger than this (after extraction) should provide a bmap file.
+pub const LARGE_IMAGE_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// Severity of a [`Diagnostic`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The entry is skipped when parsing the config.
+    Error,
+    /// The entry is used, but is likely a mistake.
+    Warning,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+/// An issue found by [`lint`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// JSON path of the entry. Eg: `$.os_list[2].subitems[0]`
+    pub path: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}: {}", self.severity, self.path, self.message)
+    }
+}
+
+/// Lint a distros.json file.
+///
+/// Returns an error if the file cannot be parsed as a [`Config`] at all. Otherwise, returns all
+/// skipped entries, along with the following checks:
+///
+/// - Board tags used by more than one board.
+/// - Images whose `devices` match no board in the config.
+/// - SD card images larger than [`LARGE_IMAGE_SIZE`] without a bmap file.
+/// - Images with `release_date` after `today`.
+pub fn lint(data: &[u8], today: chrono::NaiveDate) -> Result<Vec<Diagnostic>, serde_json::Error> {
+    let value: Value = serde_json::from_slice(data)?;
+    Config::deserialize(&value)?;
+
+    let mut linter = Linter {
+        today,
+        diagnostics: Vec::new(),
+        devices: Vec::new(),
+        images: Vec::new(),
+    };
+
+    linter.parse_devices(&value["imager"]["devices"]);
+    linter.parse_os_list("$.os_list", &value["os_list"], None);
+    linter.check_tags();
+    linter.check_images();
+
+    Ok(linter.diagnostics)
+}
+
+struct Linter {
+    today: chrono::NaiveDate,
+    diagnostics: Vec<Diagnostic>,
+    devices: Vec<(String, Device)>,
+    /// Images along with the flasher of the sublist they are in.
+    images: Vec<(String, OsImage, Option<Flasher>)>,
+}
+
+impl Linter {
+    fn error(&mut self, path: String, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            path,
+            message,
+        });
+    }
+
+    fn warn(&mut self, path: String, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity: Severity::Warning,
+            path,
+            message,
+        });
+    }
+
+    fn parse_devices(&mut self, devices: &Value) {
+        let Some(devices) = devices.as_array() else {
+            return;
+        };
+
+        for (i, device) in devices.iter().enumerate() {
+            let path = format!("$.imager.devices[{i}]");
+            match Device::deserialize(device) {
+                Ok(x) => self.devices.push((path, x)),
+                Err(e) => self.error(path, format!("Skipped board: {e}")),
+            }
+        }
+    }
+
+    fn parse_os_list(&mut self, parent: &str, items: &Value, flasher: Option<Flasher>) {
+        let Some(items) = items.as_array() else {
+            return;
+        };
+
+        for (i, item) in items.iter().enumerate() {
+            let path = format!("{parent}[{i}]");
+
+            // Same order as the untagged OsListItem.
+            let image_err = match OsImage::deserialize(item) {
+                Ok(x) => {
+                    self.images.push((path, x, flasher));
+                    continue;
+                }
+                Err(e) => e,
+            };
+            let sublist_err = match OsSubList::deserialize(item) {
+                Ok(x) => {
+                    let parent = format!("{path}.subitems");
+                    self.parse_os_list(&parent, &item["subitems"], Some(x.flasher));
+                    continue;
+                }
+                Err(e) => e,
+            };
+            let remote_err = match OsRemoteSubList::deserialize(item) {
+                Ok(_) => continue,
+                Err(e) => e,
+            };
+
+            // Errors from the untagged OsListItem do not say what is wrong, so report the error of
+            // the variant the item looks like instead.
+            let message = if item.get("subitems").is_some() {
+                format!("Skipped sublist: {sublist_err}")
+            } else if item.get("subitems_url").is_some() {
+                format!("Skipped remote sublist: {remote_err}")
+            } else {
+                format!("Skipped image: {image_err}")
+            };
+            self.error(path, message);
+        }
+    }
+
+    fn check_tags(&mut self) {
+        let mut seen: HashMap<&str, &str> = HashMap::new();
+        let mut warnings = Vec::new();
+
+        for (path, device) in &self.devices {
+            let tags: HashSet<&str> = device.tags.iter().map(|x| x.as_ref()).collect();
+            for tag in tags {
+                match seen.get(tag) {
+                    Some(other) => warnings.push((
+                        format!("{path}.tags"),
+                        format!("Tag `{tag}` is also used by board `{other}`"),
+                    )),
+                    None => {
+                        seen.insert(tag, &device.name);
+                    }
+                }
+            }
+        }
+
+        for (path, message) in warnings {
+            self.warn(path, message);
+        }
+    }
+
+    fn check_images(&mut self) {
+        let mut warnings = Vec::new();
+
+        for (path, image, flasher) in &self.images {
+            let boards: Vec<&Device> = self
+                .devices
+                .iter()
+                .map(|(_, x)| x)
+                .filter(|x| x.tags.iter().any(|t| image.devices.contains(t)))
+                .collect();
+
+            if boards.is_empty() {
+                warnings.push((
+                    format!("{path}.devices"),
+                    format!("`{}` matches no board", image.name),
+                ));
+            }
+
+            // Top level images use the flasher of the board.
+            let sd_card = match flasher {
+                Some(x) => *x == Flasher::SdCard,
+                None => boards.iter().any(|x| x.flasher == Flasher::SdCard),
+            };
+            if sd_card && image.extract_size > LARGE_IMAGE_SIZE && image.bmap.is_none() {
+                warnings.push((
+                    format!("{path}.bmap"),
+                    format!(
+                        "`{}` is a large SD card image ({} bytes) without a bmap file",
+                        image.name, image.extract_size
+                    ),
+                ));
+            }
+
+            if image.release_date > self.today {
+                warnings.push((
+                    format!("{path}.release_date"),
+                    format!(
+                        "`{}` has release date {} in the future",
+                        image.name, image.release_date
+                    ),
+                ));
+            }
+        }
+
+        for (path, message) in warnings {
+            self.warn(path, message);
+        }
+    }
+}
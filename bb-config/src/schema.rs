@@ -0,0 +1,29 @@
+//! [JSON Schema] for distros.json files, generated from the types in [`config`](crate::config).
+//!
+//! The generated schemas are also checked in as `config.schema.json` and `os_list.schema.json`,
+//! so that lists can be validated in CI using any JSON Schema validator.
+//!
+//! ```
+//! let schema = bb_config::schema::config_schema();
+//! let json = serde_json::to_string_pretty(&schema).unwrap();
+//!
+//! assert_eq!(schema.get("title").unwrap(), "Config");
+//! assert!(json.contains("\"image_download_sha256\""));
+//! ```
+//!
+//! [JSON Schema]: https://json-schema.org/
+
+use crate::config::{Config, OsListItem};
+
+/// Schema for a [`Config`]. Used by the main distros.json file, and remote configs.
+pub fn config_schema() -> schemars::Schema {
+    schemars::schema_for!(Config)
+}
+
+/// Schema for the list of items fetched from
+/// [`OsRemoteSubList::subitems_url`](crate::config::OsRemoteSubList::subitems_url).
+pub fn os_list_schema() -> schemars::Schema {
+    let mut schema = schemars::schema_for!(Vec<OsListItem>);
+    schema.insert("title".to_string(), "OsList".into());
+    schema
+}
@@ -0,0 +1,51 @@
+//! Detached [minisign] signatures for remote configs.
+//!
+//! A signed config is served along with its signature at the same URL with a `.minisig` suffix
+//! (see [`signature_url`]). The signature covers the exact bytes of the JSON file, and is created
+//! with:
+//!
+//! ```shell
+//! minisign -S -s key.sec -m distros.json
+//! ```
+//!
+//! The public key to verify a remote with comes from the config referencing it, so a config can
+//! only be trusted if the config referencing it is trusted.
+//!
+//! [minisign]: https://jedisct1.github.io/minisign/
+
+use url::Url;
+
+/// Error verifying the signature of a config.
+#[derive(Debug)]
+pub struct SignatureError(minisign_verify::Error);
+
+impl std::fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid config signature: {}", self.0)
+    }
+}
+
+impl std::error::Error for SignatureError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+/// URL of the detached signature for a config.
+pub fn signature_url(url: &Url) -> Url {
+    let mut ans = url.clone();
+    ans.set_path(&format!("{}.minisig", url.path()));
+    ans
+}
+
+/// Verify a detached signature of config data, using a minisign public key (base64 encoded, as
+/// printed by `minisign -G`). Legacy signatures, which are not pre-hashed, are not accepted.
+pub fn verify(data: &[u8], signature: &str, public_key: &str) -> Result<(), SignatureError> {
+    let public_key =
+        minisign_verify::PublicKey::from_base64(public_key.trim()).map_err(SignatureError)?;
+    let signature = minisign_verify::Signature::decode(signature).map_err(SignatureError)?;
+
+    public_key
+        .verify(data, &signature, false)
+        .map_err(SignatureError)
+}
@@ -72,7 +72,10 @@ fn full_config_round_trip() {
     let serialized = serde_json::to_string(&first).expect("serialize");
     let second: Config = serde_json::from_str(&serialized).expect("re-parse");
 
-    assert_eq!(first, second, "config should survive a serialize round-trip");
+    assert_eq!(
+        first, second,
+        "config should survive a serialize round-trip"
+    );
 
     // Spot-check that the interesting fields actually populated.
     assert_eq!(first.os_list.len(), 3);
@@ -240,3 +243,50 @@ fn sqlite_invalid_discriminant_errors() {
         "an out-of-range discriminant must fail FromSql for InitFormat"
     );
 }
+
+#[test]
+fn os_image_mirrors() {
+    let cfg = config_with_os_list(&format!("[{OS_IMAGE_JSON}]"));
+    let OsListItem::Image(image) = &cfg.os_list[0] else {
+        panic!("expected an image");
+    };
+    assert!(image.mirrors.is_empty(), "mirrors should default to empty");
+
+    let with_mirrors = OS_IMAGE_JSON.replacen(
+        r#""devices""#,
+        r#""mirrors": ["https://mirror-a.example.com/image.img.xz", "https://mirror-b.example.com/image.img.xz"],
+    "devices""#,
+        1,
+    );
+    let cfg = config_with_os_list(&format!("[{with_mirrors}]"));
+    let OsListItem::Image(image) = &cfg.os_list[0] else {
+        panic!("expected an image");
+    };
+    assert_eq!(
+        image
+            .mirrors
+            .iter()
+            .map(|x| x.host_str().unwrap())
+            .collect::<Vec<_>>(),
+        ["mirror-a.example.com", "mirror-b.example.com"]
+    );
+}
+
+#[test]
+fn images_walks_nested_sublists() {
+    let sublist = format!(
+        r#"{{
+            "name": "Testing",
+            "description": "nested list",
+            "icon": "https://example.com/sub.png",
+            "subitems": [{OS_IMAGE_JSON}, {OS_SUBLIST_JSON}]
+        }}"#
+    );
+    let config = config_with_os_list(&format!(
+        "[{OS_IMAGE_JSON}, {sublist}, {OS_REMOTE_SUBLIST_JSON}, {OS_IMAGE_JSON}]"
+    ));
+
+    // Remote sublists are not resolved.
+    assert_eq!(config.images().count(), 3);
+    assert!(config.images().all(|x| x.name == "Test Image"));
+}
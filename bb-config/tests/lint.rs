@@ -0,0 +1,175 @@
+use bb_config::lint::{Diagnostic, LARGE_IMAGE_SIZE, Severity, lint};
+use chrono::NaiveDate;
+
+fn today() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2025, 6, 1).unwrap()
+}
+
+fn board(name: &str, tags: &str, flasher: &str) -> String {
+    format!(
+        r#"{{
+            "name": "{name}",
+            "tags": [{tags}],
+            "description": "a board",
+            "flasher": "{flasher}"
+        }}"#
+    )
+}
+
+fn image(name: &str, devices: &str, extract_size: u64, release_date: &str) -> String {
+    format!(
+        r#"{{
+            "name": "{name}",
+            "description": "an image",
+            "icon": "https://example.com/icon.png",
+            "url": "https://example.com/image.img.xz",
+            "image_download_sha256": "0000000000000000000000000000000000000000000000000000000000000000",
+            "extract_size": {extract_size},
+            "release_date": "{release_date}",
+            "devices": [{devices}]
+        }}"#
+    )
+}
+
+fn config(devices: &[String], os_list: &[String]) -> Vec<u8> {
+    format!(
+        r#"{{ "imager": {{ "devices": [{}] }}, "os_list": [{}] }}"#,
+        devices.join(","),
+        os_list.join(",")
+    )
+    .into_bytes()
+}
+
+fn paths(diagnostics: &[Diagnostic], severity: Severity) -> Vec<&str> {
+    diagnostics
+        .iter()
+        .filter(|x| x.severity == severity)
+        .map(|x| x.path.as_str())
+        .collect()
+}
+
+#[test]
+fn clean_config() {
+    let data = config(
+        &[board("Board A", r#""a""#, "SdCard")],
+        &[image("Image", r#""a""#, 1024, "2025-01-01")],
+    );
+
+    assert_eq!(lint(&data, today()).unwrap(), []);
+}
+
+#[test]
+fn bundled_config() {
+    let data = include_bytes!("../../config.json");
+    let diagnostics = lint(data, today()).unwrap();
+
+    assert_eq!(paths(&diagnostics, Severity::Error), Vec::<&str>::new());
+}
+
+#[test]
+fn invalid_config() {
+    assert!(lint(b"{", today()).is_err());
+    assert!(lint(br#"{ "imager": {} }"#, today()).is_err());
+}
+
+#[test]
+fn skipped_entries() {
+    let sublist = format!(
+        r#"{{
+            "name": "Testing",
+            "description": "nested list",
+            "icon": "https://example.com/sub.png",
+            "subitems": [{}, {{ "name": "broken" }}]
+        }}"#,
+        image("Nested", r#""a""#, 1024, "2025-01-01")
+    );
+    let data = config(
+        &[
+            board("Board A", r#""a""#, "SdCard"),
+            r#"{ "name": "Board B" }"#.to_string(),
+        ],
+        &[
+            r#"{ "name": "Image", "url": "not a url" }"#.to_string(),
+            sublist,
+            r#"{ "name": "Remote", "subitems_url": "https://example.com/remote.json" }"#
+                .to_string(),
+        ],
+    );
+
+    let diagnostics = lint(&data, today()).unwrap();
+
+    assert_eq!(
+        paths(&diagnostics, Severity::Error),
+        [
+            "$.imager.devices[1]",
+            "$.os_list[0]",
+            "$.os_list[1].subitems[1]",
+            "$.os_list[2]"
+        ]
+    );
+    assert!(diagnostics[1].message.starts_with("Skipped image: "));
+    assert!(diagnostics[1].message.contains("relative URL"));
+    assert!(
+        diagnostics[3]
+            .message
+            .starts_with("Skipped remote sublist: ")
+    );
+    assert_eq!(paths(&diagnostics, Severity::Warning), Vec::<&str>::new());
+}
+
+/// Items are parsed like the untagged `OsListItem`, so a valid image is not reported as a broken
+/// sublist.
+#[test]
+fn os_list_variant_order() {
+    let item = image("Image", r#""a""#, 1024, "2025-01-01");
+    let item = format!(
+        r#"{}, "subitems": null }}"#,
+        item.trim_end().trim_end_matches('}')
+    );
+    let data = config(&[board("Board A", r#""a""#, "SdCard")], &[item]);
+
+    assert_eq!(lint(&data, today()).unwrap(), []);
+}
+
+#[test]
+fn semantic_checks() {
+    let sublist = format!(
+        r#"{{
+            "name": "Firmware",
+            "description": "nested list",
+            "icon": "https://example.com/sub.png",
+            "flasher": "BeagleConnectFreedom",
+            "subitems": [{}]
+        }}"#,
+        image("Firmware", r#""a""#, 2 * LARGE_IMAGE_SIZE, "2025-01-01")
+    );
+    let data = config(
+        &[
+            board("Board A", r#""a", "shared""#, "SdCard"),
+            board("Board B", r#""b", "shared""#, "SdCard"),
+        ],
+        &[
+            image("Orphan", r#""c""#, 1024, "2025-01-01"),
+            image("Large", r#""a""#, 2 * LARGE_IMAGE_SIZE, "2025-01-01"),
+            image("Future", r#""b""#, 1024, "2025-06-02"),
+            sublist,
+        ],
+    );
+
+    let diagnostics = lint(&data, today()).unwrap();
+
+    assert_eq!(paths(&diagnostics, Severity::Error), Vec::<&str>::new());
+    assert_eq!(
+        paths(&diagnostics, Severity::Warning),
+        [
+            "$.imager.devices[1].tags",
+            "$.os_list[0].devices",
+            "$.os_list[1].bmap",
+            "$.os_list[2].release_date"
+        ]
+    );
+    assert_eq!(
+        diagnostics[0].to_string(),
+        "warning: $.imager.devices[1].tags: Tag `shared` is also used by board `Board A`"
+    );
+}
@@ -0,0 +1,30 @@
+//! Checks that the checked-in JSON Schemas match the config types.
+//!
+//! Run with `UPDATE_SCHEMA=1` to regenerate them after changing [`bb_config::config`].
+
+use std::path::Path;
+
+fn check_schema(name: &str, schema: schemars::Schema) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(name);
+    let expected = serde_json::to_string_pretty(&schema).unwrap() + "\n";
+
+    if std::env::var_os("UPDATE_SCHEMA").is_some() {
+        std::fs::write(&path, &expected).unwrap();
+    }
+
+    let actual = std::fs::read_to_string(&path).unwrap_or_default();
+    assert!(
+        actual == expected,
+        "{name} is out of date. Run `UPDATE_SCHEMA=1 cargo test -p bb-config --test schema`"
+    );
+}
+
+#[test]
+fn config_schema_in_sync() {
+    check_schema("config.schema.json", bb_config::schema::config_schema());
+}
+
+#[test]
+fn os_list_schema_in_sync() {
+    check_schema("os_list.schema.json", bb_config::schema::os_list_schema());
+}
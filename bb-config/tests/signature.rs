@@ -0,0 +1,64 @@
+//! Tests for detached config signatures, using a signature created with minisign.
+
+use bb_config::{Config, signature};
+
+const CONFIG: &str = "{ \"imager\": { \"remote_configs\": [] }, \"os_list\": [] }\n";
+const PUBLIC_KEY: &str = "RWQBI0VniavN77vkgjo0MqTUAYLuZVB3xNw18RpKe27xTj7GtQNOckET";
+const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQBI0VniavN7+vO/GiiXcGX2z0ds033xISIUf8Ghst4DbIppBRruVRcG5EizMN9lpFoheGNa5ALws+KPl8WNImCa3JwRiYqMAo=
+trusted comment: timestamp:1760000000\tfile:distros.json
+KYLAjmUiKBAVC2lD2hQihuft5M2NgP1qEzSv/BOGdiOFztxNhSh4OqlRv3fbFHj43OnMMWy0XGZD1wd2YHmIDw==
+";
+/// Public key of a different key pair.
+const OTHER_PUBLIC_KEY: &str = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+
+#[test]
+fn verify_valid_signature() {
+    signature::verify(CONFIG.as_bytes(), SIGNATURE, PUBLIC_KEY).unwrap();
+    serde_json::from_str::<Config>(CONFIG).unwrap();
+}
+
+#[test]
+fn verify_rejects_modified_config() {
+    let modified = CONFIG.replace("[]", "[ ]");
+    assert!(signature::verify(modified.as_bytes(), SIGNATURE, PUBLIC_KEY).is_err());
+}
+
+#[test]
+fn verify_rejects_other_key() {
+    assert!(signature::verify(CONFIG.as_bytes(), SIGNATURE, OTHER_PUBLIC_KEY).is_err());
+}
+
+#[test]
+fn verify_rejects_malformed_input() {
+    assert!(signature::verify(CONFIG.as_bytes(), "not a signature", PUBLIC_KEY).is_err());
+    assert!(signature::verify(CONFIG.as_bytes(), SIGNATURE, "not a key").is_err());
+}
+
+#[test]
+fn signature_url_appends_suffix() {
+    let url = "https://example.com/configs/distros.json?v=1"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        signature::signature_url(&url).as_str(),
+        "https://example.com/configs/distros.json.minisig?v=1"
+    );
+}
+
+#[test]
+fn remote_config_keys_parse() {
+    let config: Config = serde_json::from_str(&format!(
+        r#"{{
+            "imager": {{
+                "remote_configs": ["https://example.com/distros.json"],
+                "remote_config_keys": {{ "https://example.com/distros.json": "{PUBLIC_KEY}" }}
+            }},
+            "os_list": []
+        }}"#
+    ))
+    .unwrap();
+
+    let url = &config.imager.remote_configs[0];
+    assert_eq!(config.imager.remote_config_keys[url], PUBLIC_KEY);
+}
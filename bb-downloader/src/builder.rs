@@ -0,0 +1,235 @@
+//! Builder for [`Downloader`].
+
+use std::{io, path::PathBuf, sync::Arc, time::Duration};
+
+use crate::{Downloader, RetryPolicy};
+
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Builder for [`Downloader`], allowing configuration of the underlying HTTP client.
+///
+/// # Proxy
+///
+/// The system proxy configuration (e.g. `HTTPS_PROXY` environment variable) is used unless
+/// [`proxy`](Self::proxy) or [`no_proxy`](Self::no_proxy) is used. SOCKS proxies require the
+/// `socks` feature.
+///
+/// # Offline
+///
+/// In [offline](Self::offline) mode, no requests are made. Files are only served from cache, and a
+/// cache miss fails with [`OfflineCacheMiss`].
+#[derive(Debug)]
+pub struct DownloaderBuilder {
+    cache_dir: PathBuf,
+    user_agent: String,
+    connect_timeout: Duration,
+    read_timeout: Duration,
+    timeout: Option<Duration>,
+    proxies: Vec<reqwest::Proxy>,
+    no_proxy: bool,
+    #[cfg(any(feature = "rustls", feature = "native-tls"))]
+    root_certificates: Vec<reqwest::Certificate>,
+    #[cfg(any(feature = "rustls", feature = "native-tls"))]
+    identity: Option<reqwest::Identity>,
+    retry: RetryPolicy,
+    max_cache_size: Option<u64>,
+    offline: bool,
+    local_mirror: Option<PathBuf>,
+    segments: usize,
+}
+
+impl DownloaderBuilder {
+    /// Create a builder for a downloader that uses a directory for storing cached files.
+    pub fn new<P: Into<PathBuf>>(cache_dir: P) -> Self {
+        Self {
+            cache_dir: cache_dir.into(),
+            user_agent: env!("CARGO_PKG_NAME").to_string(),
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            read_timeout: DEFAULT_READ_TIMEOUT,
+            timeout: None,
+            proxies: Vec::new(),
+            no_proxy: false,
+            #[cfg(any(feature = "rustls", feature = "native-tls"))]
+            root_certificates: Vec::new(),
+            #[cfg(any(feature = "rustls", feature = "native-tls"))]
+            identity: None,
+            retry: RetryPolicy::default(),
+            max_cache_size: None,
+            offline: false,
+            local_mirror: None,
+            segments: 1,
+        }
+    }
+
+    /// Set the `User-Agent` header. Defaults to the crate name.
+    pub fn user_agent<S: Into<String>>(mut self, user_agent: S) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Timeout for connecting to the server. Defaults to 10 seconds.
+    pub const fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Timeout for each read from the server. Defaults to 15 seconds.
+    pub const fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = timeout;
+        self
+    }
+
+    /// Timeout for a complete request, including the response body. Disabled by default, since
+    /// images can take a long time to download.
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Add a proxy. Can be called multiple times, in which case the first matching proxy is used.
+    pub fn proxy(mut self, proxy: reqwest::Proxy) -> Self {
+        self.proxies.push(proxy);
+        self
+    }
+
+    /// Do not use the system proxy.
+    pub const fn no_proxy(mut self) -> Self {
+        self.no_proxy = true;
+        self
+    }
+
+    /// Trust an additional root certificate, along with the system ones.
+    #[cfg(any(feature = "rustls", feature = "native-tls"))]
+    pub fn add_root_certificate(mut self, cert: reqwest::Certificate) -> Self {
+        self.root_certificates.push(cert);
+        self
+    }
+
+    /// Client certificate to use for TLS client authentication.
+    #[cfg(any(feature = "rustls", feature = "native-tls"))]
+    pub fn identity(mut self, identity: reqwest::Identity) -> Self {
+        self.identity = Some(identity);
+        self
+    }
+
+    /// Set the policy used to retry failed requests. Defaults to [`RetryPolicy::default`].
+    pub const fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Set the maximum size of the cache in bytes. See
+    /// [`Downloader::with_max_cache_size`].
+    pub const fn max_cache_size(mut self, max_cache_size: u64) -> Self {
+        self.max_cache_size = Some(max_cache_size);
+        self
+    }
+
+    /// Only serve files from cache, without making any requests.
+    pub const fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Serve HTTP(S) URLs from a local mirror when possible. A URL like
+    /// `https://example.com/images/os.img.xz` is served from `<mirror>/example.com/images/os.img.xz`
+    /// if the file exists, which is the layout created by `wget --mirror`. Other URLs are
+    /// downloaded as usual.
+    ///
+    /// The port of the URL is ignored.
+    pub fn local_mirror<P: Into<PathBuf>>(mut self, mirror: P) -> Self {
+        self.local_mirror = Some(mirror.into());
+        self
+    }
+
+    /// Download large files using up to `segments` concurrent Range requests, if the server
+    /// advertises `Accept-Ranges: bytes`. Otherwise, files are downloaded in a single request.
+    /// Defaults to 1.
+    ///
+    /// Streams still receive the data in order, as soon as it is available.
+    pub const fn segments(mut self, segments: usize) -> Self {
+        self.segments = segments;
+        self
+    }
+
+    /// Create the downloader. Fails if the cache directory is invalid, or the TLS configuration
+    /// cannot be loaded.
+    pub fn build(self) -> io::Result<Downloader> {
+        let cache_dir = self.cache_dir;
+
+        if !cache_dir.exists() {
+            let _ = std::fs::create_dir_all(&cache_dir);
+        }
+
+        if cache_dir.exists() && !cache_dir.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotADirectory,
+                "cache_dir should be a directory",
+            ));
+        }
+
+        let mut client = reqwest::Client::builder()
+            .user_agent(self.user_agent)
+            .connect_timeout(self.connect_timeout)
+            .read_timeout(self.read_timeout);
+
+        if let Some(x) = self.timeout {
+            client = client.timeout(x);
+        }
+
+        if self.no_proxy {
+            client = client.no_proxy();
+        }
+        for proxy in self.proxies {
+            client = client.proxy(proxy);
+        }
+
+        #[cfg(any(feature = "rustls", feature = "native-tls"))]
+        {
+            for cert in self.root_certificates {
+                client = client.add_root_certificate(cert);
+            }
+            if let Some(x) = self.identity {
+                client = client.identity(x);
+            }
+        }
+
+        Ok(Downloader {
+            client: client.build().map_err(io::Error::other)?,
+            cache_dir: cache_dir.into(),
+            retry: self.retry,
+            max_cache_size: self.max_cache_size,
+            offline: self.offline,
+            local_mirror: self.local_mirror.map(Into::into),
+            segments: self.segments,
+            index_lock: Arc::default(),
+        })
+    }
+}
+
+/// Error for a cache miss in offline mode. Returned wrapped in an [`io::Error`] of kind
+/// [`NotConnected`](io::ErrorKind::NotConnected).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OfflineCacheMiss;
+
+impl OfflineCacheMiss {
+    /// Check if an error is a cache miss in offline mode.
+    pub fn is(e: &io::Error) -> bool {
+        e.get_ref().is_some_and(|x| x.is::<Self>())
+    }
+}
+
+impl std::fmt::Display for OfflineCacheMiss {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("File not found in cache, and downloads are disabled in offline mode")
+    }
+}
+
+impl std::error::Error for OfflineCacheMiss {}
+
+impl From<OfflineCacheMiss> for io::Error {
+    fn from(value: OfflineCacheMiss) -> Self {
+        io::Error::new(io::ErrorKind::NotConnected, value)
+    }
+}
@@ -0,0 +1,318 @@
+//! Cache index.
+//!
+//! A small index is kept next to the cached files, recording when each file was last used, where
+//! it was downloaded from, whether it is pinned, and the validators (`ETag`, `Last-Modified`) of
+//! the response. Files missing from the index (e.g. cached by older versions) are treated as
+//! unpinned and last used at their modification time.
+//!
+//! The index is a text file with one tab separated line per file:
+//! `name  last used (unix milliseconds)  pinned (0/1)  url  etag  last modified`. The last three
+//! fields can be empty.
+
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use reqwest::header::{self, HeaderMap};
+
+const INDEX_FILE: &str = "index";
+const INDEX_TEMP_FILE: &str = "index.tmp";
+const PARTIAL_EXTENSION: &str = "part";
+
+/// A file in the download cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheEntry {
+    /// Path to the cached file.
+    pub path: PathBuf,
+    /// Size of the file in bytes.
+    pub size: u64,
+    /// Last time the file was downloaded or found in cache.
+    pub last_used: SystemTime,
+    /// URL the file was downloaded from, if known.
+    pub url: Option<reqwest::Url>,
+    /// Pinned files are never evicted or purged.
+    pub pinned: bool,
+}
+
+/// Validators of a response, used to make conditional requests.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Validators {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl Validators {
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Self {
+        let get = |name| {
+            headers
+                .get(name)
+                .and_then(|x| x.to_str().ok())
+                // Cannot be stored in the index.
+                .filter(|x| !x.contains(['\t', '\n']))
+                .map(String::from)
+        };
+
+        Self {
+            etag: get(header::ETAG),
+            last_modified: get(header::LAST_MODIFIED),
+        }
+    }
+
+    /// Headers for a conditional request.
+    pub(crate) fn to_headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let values = [
+            (header::IF_NONE_MATCH, &self.etag),
+            (header::IF_MODIFIED_SINCE, &self.last_modified),
+        ];
+
+        for (name, value) in values {
+            if let Some(x) = value.as_deref().and_then(|x| x.parse().ok()) {
+                headers.insert(name, x);
+            }
+        }
+
+        headers
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Meta {
+    last_used: Option<SystemTime>,
+    url: Option<reqwest::Url>,
+    pinned: bool,
+    validators: Validators,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Index(HashMap<String, Meta>);
+
+impl Index {
+    pub(crate) fn load(dir: &Path) -> io::Result<Self> {
+        let data = match std::fs::read_to_string(dir.join(INDEX_FILE)) {
+            Ok(x) => x,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e),
+        };
+
+        let entries = data
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.split('\t');
+                let name = parts.next()?;
+                let last_used = parts.next()?.parse().ok()?;
+                let pinned = parts.next()? == "1";
+                let url = parts.next().and_then(|x| x.parse().ok());
+                let mut optional = || parts.next().filter(|x| !x.is_empty()).map(String::from);
+                let validators = Validators {
+                    etag: optional(),
+                    last_modified: optional(),
+                };
+
+                let meta = Meta {
+                    last_used: Some(SystemTime::UNIX_EPOCH + Duration::from_millis(last_used)),
+                    url,
+                    pinned,
+                    validators,
+                };
+                Some((name.to_string(), meta))
+            })
+            .collect();
+
+        Ok(Self(entries))
+    }
+
+    /// Save the index. Entries for files which no longer exist are dropped.
+    pub(crate) fn save(&self, dir: &Path) -> io::Result<()> {
+        let mut data = Vec::new();
+        for (name, meta) in &self.0 {
+            if !dir.join(name).exists() {
+                continue;
+            }
+
+            let last_used = meta
+                .last_used
+                .and_then(|x| x.duration_since(SystemTime::UNIX_EPOCH).ok())
+                .unwrap_or_default()
+                .as_millis();
+            let url = meta.url.as_ref().map(|x| x.as_str()).unwrap_or_default();
+            let etag = meta.validators.etag.as_deref().unwrap_or_default();
+            let last_modified = meta.validators.last_modified.as_deref().unwrap_or_default();
+            writeln!(
+                data,
+                "{name}\t{last_used}\t{}\t{url}\t{etag}\t{last_modified}",
+                u8::from(meta.pinned)
+            )?;
+        }
+
+        let temp = dir.join(INDEX_TEMP_FILE);
+        std::fs::write(&temp, data)?;
+        std::fs::rename(temp, dir.join(INDEX_FILE))
+    }
+
+    /// Mark a file as used now. The URL is only updated if provided.
+    pub(crate) fn touch(&mut self, name: &str, url: Option<&reqwest::Url>) {
+        let meta = self.0.entry(name.to_string()).or_default();
+        meta.last_used = Some(SystemTime::now());
+        if let Some(x) = url {
+            meta.url = Some(x.clone());
+        }
+    }
+
+    pub(crate) fn validators(&self, name: &str) -> Validators {
+        self.0
+            .get(name)
+            .map(|x| x.validators.clone())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn set_validators(&mut self, name: &str, validators: Validators) {
+        self.0.entry(name.to_string()).or_default().validators = validators;
+    }
+
+    pub(crate) fn set_pinned(&mut self, name: &str, pinned: bool) {
+        self.0.entry(name.to_string()).or_default().pinned = pinned;
+    }
+
+    /// List cached files, least recently used first. Partial downloads are not included.
+    pub(crate) fn entries(&self, dir: &Path) -> io::Result<Vec<CacheEntry>> {
+        let mut ans = Vec::new();
+
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            let path = entry.path();
+
+            if !metadata.is_file() || is_internal(&path) {
+                continue;
+            }
+
+            let meta = entry
+                .file_name()
+                .to_str()
+                .and_then(|x| self.0.get(x))
+                .cloned()
+                .unwrap_or_default();
+            let last_used = match meta.last_used {
+                Some(x) => x,
+                None => metadata.modified()?,
+            };
+
+            ans.push(CacheEntry {
+                path,
+                size: metadata.len(),
+                last_used,
+                url: meta.url,
+                pinned: meta.pinned,
+            });
+        }
+
+        ans.sort_by_key(|x| x.last_used);
+        Ok(ans)
+    }
+}
+
+/// Check if a file in the cache directory is not a cached file.
+fn is_internal(path: &Path) -> bool {
+    path.extension().is_some_and(|x| x == PARTIAL_EXTENSION)
+        || path
+            .file_name()
+            .is_some_and(|x| x == INDEX_FILE || x == INDEX_TEMP_FILE)
+}
+
+/// Partial downloads in the cache directory.
+pub(crate) fn partial_files(dir: &Path) -> io::Result<Vec<(PathBuf, u64)>> {
+    let mut ans = Vec::new();
+
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+
+        if path.extension().is_some_and(|x| x == PARTIAL_EXTENSION) {
+            ans.push((path, entry.metadata()?.len()));
+        }
+    }
+
+    Ok(ans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a"), b"a").unwrap();
+        std::fs::write(dir.path().join("b.txt"), b"bb").unwrap();
+        std::fs::write(dir.path().join("c.part"), b"ccc").unwrap();
+
+        let url = reqwest::Url::parse("https://example.com/b.txt").unwrap();
+        let validators = Validators {
+            etag: Some("\"abc\"".to_string()),
+            last_modified: Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string()),
+        };
+        let mut index = Index::default();
+        index.touch("b.txt", Some(&url));
+        index.set_validators("b.txt", validators.clone());
+        index.set_pinned("a", true);
+        // Dropped since the file does not exist.
+        index.touch("missing", None);
+        index.save(dir.path()).unwrap();
+
+        let index = Index::load(dir.path()).unwrap();
+        assert!(!index.0.contains_key("missing"));
+        assert_eq!(index.validators("a"), Validators::default());
+        assert_eq!(index.validators("b.txt"), validators);
+
+        let entries = index.entries(dir.path()).unwrap();
+        assert_eq!(entries.len(), 2);
+
+        let a = entries.iter().find(|x| x.path.ends_with("a")).unwrap();
+        assert!(a.pinned);
+        assert_eq!(a.size, 1);
+        assert_eq!(a.url, None);
+
+        let b = entries.iter().find(|x| x.path.ends_with("b.txt")).unwrap();
+        assert!(!b.pinned);
+        assert_eq!(b.size, 2);
+        assert_eq!(b.url, Some(url));
+    }
+
+    #[test]
+    fn validators_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ETAG, "W/\"abc\"".parse().unwrap());
+        let validators = Validators::from_headers(&headers);
+        assert_eq!(validators.etag.as_deref(), Some("W/\"abc\""));
+        assert_eq!(validators.last_modified, None);
+
+        let headers = validators.to_headers();
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers[header::IF_NONE_MATCH], "W/\"abc\"");
+    }
+
+    #[test]
+    fn index_ignores_invalid_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(INDEX_FILE),
+            "a\tnot a number\t0\t\nb\t10\t1\tnot a url\nc\n",
+        )
+        .unwrap();
+
+        let index = Index::load(dir.path()).unwrap();
+        assert_eq!(index.0.len(), 1);
+
+        let b = &index.0["b"];
+        assert!(b.pinned);
+        assert_eq!(b.url, None);
+        assert_eq!(
+            b.last_used,
+            Some(SystemTime::UNIX_EPOCH + Duration::from_millis(10))
+        );
+    }
+}
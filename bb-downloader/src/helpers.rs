@@ -18,6 +18,21 @@ pub(crate) fn sha256_from_path(p: &Path) -> io::Result<[u8; 32]> {
     Ok(hash)
 }
 
+/// Same as [`IntoUrl::into_url`](reqwest::IntoUrl), but also accepts `file://` URLs.
+pub(crate) fn into_url<U: reqwest::IntoUrl>(url: U) -> io::Result<reqwest::Url> {
+    match reqwest::Url::parse(url.as_str()) {
+        Ok(x) if x.scheme() == "file" => Ok(x),
+        _ => url.into_url().map_err(io::Error::other),
+    }
+}
+
+/// Start offset from a `Content-Range: bytes start-end/size` header.
+pub(crate) fn content_range_start(headers: &reqwest::header::HeaderMap) -> Option<u64> {
+    let range = headers.get(reqwest::header::CONTENT_RANGE)?.to_str().ok()?;
+    let (start, _) = range.strip_prefix("bytes ")?.split_once('-')?;
+    start.trim().parse().ok()
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::Write;
@@ -48,4 +63,37 @@ mod tests {
         let bad_path = std::path::Path::new("this_file_does_not_exist.txt");
         assert!(sha256_from_path(bad_path).is_err());
     }
+
+    #[test]
+    fn test_into_url() {
+        assert_eq!(
+            into_url("file:///tmp/os.img").unwrap().as_str(),
+            "file:///tmp/os.img"
+        );
+        assert_eq!(
+            into_url("https://example.com/os.img").unwrap().as_str(),
+            "https://example.com/os.img"
+        );
+        assert!(into_url("not a url").is_err());
+    }
+
+    #[test]
+    fn test_content_range_start() {
+        let headers = |x: &'static str| {
+            let mut h = reqwest::header::HeaderMap::new();
+            h.insert(
+                reqwest::header::CONTENT_RANGE,
+                reqwest::header::HeaderValue::from_static(x),
+            );
+            h
+        };
+
+        assert_eq!(
+            content_range_start(&headers("bytes 100-199/200")),
+            Some(100)
+        );
+        assert_eq!(content_range_start(&headers("bytes 0-9/*")), Some(0));
+        assert_eq!(content_range_start(&headers("bytes */200")), None);
+        assert_eq!(content_range_start(&Default::default()), None);
+    }
 }
@@ -0,0 +1,135 @@
+//! Import files from a local directory into the cache.
+//!
+//! Useful for pre-staging images on machines which will be offline. Files are matched by SHA256,
+//! and stored under the same name as downloaded files, so they are found by
+//! [`Downloader::check_cache_from_sha`].
+
+use std::{
+    collections::HashSet,
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{Downloader, helpers::sha256_from_path};
+
+/// How files are added to the cache by [`Downloader::import_dir`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImportMode {
+    /// Copy the files.
+    #[default]
+    Copy,
+    /// Hard link the files, falling back to copying if that is not possible (e.g. the directory
+    /// is on a different filesystem).
+    HardLink,
+}
+
+/// A file imported by [`Downloader::import_dir`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedFile {
+    /// Path of the file in the imported directory.
+    pub source: PathBuf,
+    /// Path of the file in cache.
+    pub path: PathBuf,
+    /// SHA256 of the file.
+    pub sha256: [u8; 32],
+}
+
+impl Downloader {
+    /// Import files from `dir` (recursively) whose SHA256 is in `sha256s` into the cache. Files
+    /// which are already in cache are skipped, as are files and subdirectories which cannot be
+    /// read. Symbolic links to directories are not followed.
+    ///
+    /// Imported files are pinned, so that they are not evicted before being used offline.
+    ///
+    /// This hashes every file in the directory, so it should not be called from an async context.
+    pub fn import_dir<I>(
+        &self,
+        dir: &Path,
+        sha256s: I,
+        mode: ImportMode,
+    ) -> io::Result<Vec<ImportedFile>>
+    where
+        I: IntoIterator<Item = [u8; 32]>,
+    {
+        let mut wanted: HashSet<[u8; 32]> = sha256s
+            .into_iter()
+            .filter(|x| !self.path_from_sha(*x).exists())
+            .collect();
+        let mut ans = Vec::new();
+
+        let mut dirs = vec![dir.to_path_buf()];
+        while let Some(subdir) = dirs.pop() {
+            let entries = match std::fs::read_dir(&subdir) {
+                Ok(x) => x,
+                Err(e) if subdir == dir => return Err(e),
+                Err(e) => {
+                    tracing::warn!("Skipping {subdir:?}: {e}");
+                    continue;
+                }
+            };
+
+            for entry in entries {
+                if wanted.is_empty() {
+                    return Ok(ans);
+                }
+
+                let entry = entry?;
+                let file_type = entry.file_type()?;
+                let source = entry.path();
+
+                if file_type.is_dir() {
+                    dirs.push(source);
+                    continue;
+                }
+                if !source.is_file() {
+                    continue;
+                }
+
+                let sha256 = match sha256_from_path(&source) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        tracing::warn!("Skipping {source:?}: {e}");
+                        continue;
+                    }
+                };
+                if !wanted.remove(&sha256) {
+                    continue;
+                }
+
+                tracing::info!("Importing {source:?} into cache");
+                let path = self.path_from_sha(sha256);
+                import_file(&source, &path, mode)?;
+                // Pinned before touching, which can evict files.
+                self.set_pinned(&path, true)?;
+                self.touch(&path, None, None);
+
+                ans.push(ImportedFile {
+                    source,
+                    path,
+                    sha256,
+                });
+            }
+        }
+
+        Ok(ans)
+    }
+}
+
+fn import_file(source: &Path, path: &Path, mode: ImportMode) -> io::Result<()> {
+    if mode == ImportMode::HardLink {
+        match std::fs::hard_link(source, path) {
+            Ok(()) => return Ok(()),
+            Err(e) => tracing::info!("Failed to hard link {source:?}: {e}. Copying"),
+        }
+    }
+
+    let dir = path
+        .parent()
+        .expect("Cached files are in the cache directory");
+    let mut temp = tempfile::Builder::new().suffix(".part").tempfile_in(dir)?;
+    std::io::copy(&mut std::fs::File::open(source)?, temp.as_file_mut())?;
+    temp.as_file().sync_all()?;
+    temp.persist(path)?;
+
+    Ok(())
+}
@@ -7,24 +7,56 @@
 //! - Cache downloaded file in a directory in filesystem.
 //! - Check if a file is available in cache.
 //! - Uses SHA256 for verifying cached files.
+//! - Limit the cache size with least recently used eviction. Files can be pinned to keep them.
+//! - Resume interrupted downloads using HTTP Range requests.
+//! - Download large files using concurrent Range requests, while streaming them in order.
+//! - Retry failed requests with exponential backoff, and fall back to mirrors.
+//! - Report download progress.
+//! - Configurable proxies, TLS certificates, timeouts and offline mode.
+//! - Download from `file://` URLs and local mirrors.
+//! - Import files from a local directory into the cache.
+//! - Revalidate cached files using `ETag` and `Last-Modified`, falling back to stale files when
+//!   offline.
 //! - Optional support to download files without caching.
 
+mod builder;
+mod cache;
 mod helpers;
+mod import;
+mod local;
+mod retry;
+mod segmented;
 
-use helpers::sha256_from_path;
+use cache::{Index, Validators};
+use helpers::{content_range_start, into_url, sha256_from_path};
+use retry::is_retryable;
 
-use futures_util::{StreamExt, TryStreamExt};
 #[cfg(feature = "json")]
 use serde::de::DeserializeOwned;
 use sha2::{Digest as _, Sha256};
 use std::{
     io,
     path::{Path, PathBuf},
-    time::Duration,
+    sync::{Arc, Mutex, PoisonError, mpsc},
 };
 use tokio::io::AsyncWriteExt;
 
-pub use reqwest::IntoUrl;
+pub use builder::{DownloaderBuilder, OfflineCacheMiss};
+pub use cache::CacheEntry;
+pub use import::{ImportMode, ImportedFile};
+#[cfg(any(feature = "rustls", feature = "native-tls"))]
+pub use reqwest::{Certificate, Identity};
+pub use reqwest::{IntoUrl, Proxy};
+pub use retry::RetryPolicy;
+
+/// Progress of a download.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DownloadProgress {
+    /// Bytes received so far.
+    pub received: u64,
+    /// Size of the file, if known.
+    pub total: Option<u64>,
+}
 
 /// Simple downloader that caches files in the provided directory. Uses SHA256 to determine if the
 /// file is already downloaded.
@@ -41,6 +73,24 @@ pub use reqwest::IntoUrl;
 /// Files downloaded with just URL cannot be invalidated without changing the URL, or deleting the
 /// file manually.
 ///
+/// # Cache Size
+///
+/// The cache grows without bound by default. A limit can be set using
+/// [`with_max_cache_size`](Self::with_max_cache_size), in which case the least recently used files
+/// are evicted when a new file is added. Files can be [pinned](Self::set_pinned) to keep them
+/// around. Usage is tracked in a small index stored next to the cached files.
+///
+/// # Configuration
+///
+/// [`DownloaderBuilder`] can be used to configure proxies, certificates, timeouts, offline mode
+/// and local mirrors.
+///
+/// # Local Files
+///
+/// `file://` URLs are supported, and behave exactly like HTTP URLs. A
+/// [local mirror](DownloaderBuilder::local_mirror) can also be used to serve HTTP(S) URLs from
+/// disk.
+///
 /// # Thread Safety
 ///
 /// You do not have to wrap the Client in an Rc or Arc to reuse it, because it already uses an Arc
@@ -49,35 +99,87 @@ pub use reqwest::IntoUrl;
 pub struct Downloader {
     client: reqwest::Client,
     cache_dir: std::sync::Arc<std::path::Path>,
+    retry: RetryPolicy,
+    max_cache_size: Option<u64>,
+    offline: bool,
+    local_mirror: Option<Arc<Path>>,
+    /// Number of concurrent Range requests for large files.
+    segments: usize,
+    /// Serializes updates to the cache index.
+    index_lock: Arc<Mutex<()>>,
 }
 
 impl Downloader {
     /// Create a new downloader that uses a directory for storing cached files.
     pub fn new<P: Into<PathBuf>>(cache_dir: P) -> io::Result<Self> {
-        let cache_dir = cache_dir.into();
+        Self::builder(cache_dir).build()
+    }
 
-        if !cache_dir.exists() {
-            let _ = std::fs::create_dir_all(&cache_dir);
-        }
+    /// Create a builder to configure the downloader.
+    pub fn builder<P: Into<PathBuf>>(cache_dir: P) -> DownloaderBuilder {
+        DownloaderBuilder::new(cache_dir)
+    }
 
-        if cache_dir.exists() && !cache_dir.is_dir() {
-            return Err(io::Error::new(
-                io::ErrorKind::NotADirectory,
-                "cache_dir should be a directory",
-            ));
-        }
+    /// Set the policy used to retry failed requests. Defaults to [`RetryPolicy::default`].
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Set the maximum size of the cache in bytes. Whenever a file is added to the cache, the least
+    /// recently used files are evicted until the cache fits. Pinned files are never evicted.
+    pub fn with_max_cache_size(mut self, max_cache_size: u64) -> Self {
+        self.max_cache_size = Some(max_cache_size);
+        self
+    }
+
+    /// List the files in cache, least recently used first. Partial downloads are not included.
+    pub fn cache_entries(&self) -> io::Result<Vec<CacheEntry>> {
+        let _guard = self.lock_index();
+        Index::load(&self.cache_dir)?.entries(&self.cache_dir)
+    }
+
+    /// Pin or unpin a cached file. Pinned files are never evicted or purged.
+    pub fn set_pinned(&self, path: &Path, pinned: bool) -> io::Result<()> {
+        let name = self.cache_name(path)?;
+
+        let _guard = self.lock_index();
+        let mut index = Index::load(&self.cache_dir)?;
+        index.set_pinned(name, pinned);
+        index.save(&self.cache_dir)
+    }
+
+    /// Remove a file from cache, even if it is pinned.
+    pub fn remove_from_cache(&self, path: &Path) -> io::Result<()> {
+        self.cache_name(path)?;
 
-        let client = reqwest::Client::builder()
-            .user_agent(env!("CARGO_PKG_NAME"))
-            .connect_timeout(Duration::from_secs(10))
-            .read_timeout(Duration::from_secs(15))
-            .build()
-            .expect("Unsupported OS");
+        let _guard = self.lock_index();
+        std::fs::remove_file(path)?;
+        Index::load(&self.cache_dir)?.save(&self.cache_dir)
+    }
+
+    /// Remove all files which are not pinned, along with partial downloads. Returns the number of
+    /// bytes freed.
+    ///
+    /// Should not be called while downloads are in progress.
+    pub fn purge_cache(&self) -> io::Result<u64> {
+        let _guard = self.lock_index();
+        let index = Index::load(&self.cache_dir)?;
+        let mut freed = 0;
+
+        let entries = index.entries(&self.cache_dir)?;
+        let files = entries
+            .into_iter()
+            .filter(|x| !x.pinned)
+            .map(|x| (x.path, x.size))
+            .chain(cache::partial_files(&self.cache_dir)?);
+        for (path, size) in files {
+            std::fs::remove_file(path)?;
+            freed += size;
+        }
 
-        Ok(Self {
-            client,
-            cache_dir: cache_dir.into(),
-        })
+        index.save(&self.cache_dir)?;
+        Ok(freed)
     }
 
     /// Check if a downloaded file with a particular SHA256 is already in cache.
@@ -88,6 +190,7 @@ impl Downloader {
             if let Ok(hash) = sha256_from_path(&file_path)
                 && hash == sha256
             {
+                self.touch(&file_path, None, None);
                 return Some(file_path);
             }
 
@@ -100,15 +203,20 @@ impl Downloader {
 
     /// Download a JSON file without caching the contents. Should be used when there is no point in
     /// caching the file.
+    ///
+    /// Always fails with [`OfflineCacheMiss`] in offline mode.
     #[cfg(feature = "json")]
     pub async fn download_json_no_cache<T, U>(&self, url: U) -> io::Result<T>
     where
         T: DeserializeOwned,
         U: reqwest::IntoUrl,
     {
-        self.client
-            .get(url)
-            .send()
+        let url = into_url(url)?;
+        if self.offline {
+            return Err(OfflineCacheMiss.into());
+        }
+
+        self.get(&[url], None)
             .await
             .map_err(io::Error::other)?
             .json()
@@ -119,114 +227,573 @@ impl Downloader {
     /// Checks if the file is present in cache. If the file is present, returns path to it. Else
     /// downloads the file.
     ///
+    /// The file is streamed to a temporary file in the cache directory, and only moved into place
+    /// once complete.
+    ///
     /// [`download_with_sha`](Self::download_with_sha) should be prefered when the SHA256 of the
     /// file is known in advance.
     pub async fn download<U: reqwest::IntoUrl>(&self, url: U) -> io::Result<PathBuf> {
-        let url = url.into_url().map_err(io::Error::other)?;
+        self.download_with_progress(url, None).await
+    }
+
+    /// Same as [`download`](Self::download), but reports progress to `chan`.
+    pub async fn download_with_progress<U: reqwest::IntoUrl>(
+        &self,
+        url: U,
+        chan: Option<mpsc::SyncSender<DownloadProgress>>,
+    ) -> io::Result<PathBuf> {
+        let url = into_url(url)?;
 
         let file_path = self.path_from_url(&url);
 
         // Check cache
         if file_path.exists() {
+            self.touch(&file_path, Some(&url), None);
             return Ok(file_path);
         }
+        if self.offline {
+            return Err(OfflineCacheMiss.into());
+        }
 
         let response = self
-            .client
-            .get(url)
-            .send()
-            .await
-            .map_err(io::Error::other)?
-            .bytes()
+            .get(std::slice::from_ref(&url), None)
             .await
             .map_err(io::Error::other)?;
+        self.save_response(&url, response, &file_path, chan).await?;
 
-        let fp_clone = file_path.clone();
-        tokio::task::spawn_blocking(move || std::fs::write(fp_clone, response))
-            .await
-            .unwrap()?;
+        Ok(file_path)
+    }
+
+    /// Same as [`download`](Self::download), but checks if the cached file is still up to date
+    /// using the `ETag` and `Last-Modified` headers of the response it was downloaded with. The
+    /// file is only downloaded again if it has changed.
+    ///
+    /// If the server cannot be reached, the cached file is returned even though it might be stale.
+    pub async fn download_revalidate<U: reqwest::IntoUrl>(&self, url: U) -> io::Result<PathBuf> {
+        let url = into_url(url)?;
+
+        let file_path = self.path_from_url(&url);
+        if !file_path.exists() || self.offline {
+            return self.download(url).await;
+        }
+
+        let urls = std::slice::from_ref(&url);
+        let headers = self.validators(&file_path).to_headers();
+        let response = match self.get_with_headers(urls, None, &headers).await {
+            Ok(x) => x,
+            Err(e) if is_retryable(&e) => {
+                tracing::warn!("Failed to revalidate {url}: {e}. Using cached file");
+                self.touch(&file_path, Some(&url), None);
+                return Ok(file_path);
+            }
+            Err(e) => return Err(io::Error::other(e)),
+        };
+
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+            tracing::debug!("{url} not modified");
+            self.touch(&file_path, Some(&url), None);
+        } else {
+            self.save_response(&url, response, &file_path, None).await?;
+        }
 
         Ok(file_path)
     }
 
+    /// Download a JSON file, caching the contents. The cached file is revalidated using
+    /// [`download_revalidate`](Self::download_revalidate), so it is still available when offline.
+    #[cfg(feature = "json")]
+    pub async fn download_json<T, U>(&self, url: U) -> io::Result<T>
+    where
+        T: DeserializeOwned,
+        U: reqwest::IntoUrl,
+    {
+        let path = self.download_revalidate(url).await?;
+        let data = tokio::fs::read(path).await?;
+
+        serde_json::from_slice(&data).map_err(io::Error::other)
+    }
+
+    /// Save the response body to `file_path` in cache. The file is streamed to a temporary file,
+    /// and only moved into place once complete.
+    async fn save_response(
+        &self,
+        url: &reqwest::Url,
+        response: reqwest::Response,
+        file_path: &Path,
+        chan: Option<mpsc::SyncSender<DownloadProgress>>,
+    ) -> io::Result<()> {
+        let urls = std::slice::from_ref(url);
+        let total = response.content_length();
+        let validators = Validators::from_headers(response.headers());
+
+        // Removed on drop if the download fails. Left over files from crashes are cleaned up
+        // along with partial downloads.
+        let temp = tempfile::Builder::new()
+            .suffix(".part")
+            .tempfile_in(&self.cache_dir)?;
+        let mut file = tokio::io::BufWriter::new(tokio::fs::File::from_std(temp.reopen()?));
+
+        let mut received = 0;
+        let mut body = Body::new(self, urls, response, 0);
+        while let Some(data) = body.chunk().await? {
+            file.write_all(&data).await?;
+
+            received += data.len() as u64;
+            if let Some(tx) = &chan {
+                let _ = tx.try_send(DownloadProgress { received, total });
+            }
+        }
+        file.flush().await?;
+        file.into_inner().sync_all().await?;
+
+        temp.persist(file_path).map_err(|e| e.error)?;
+        self.touch(file_path, Some(url), Some(validators));
+
+        Ok(())
+    }
+
     /// Downloads the file and streams the content to pipe. This allows not having to wait for the
     /// download to finish to use the partial file.
     ///
     /// Uses SHA256 to verify that the file in cache is valid.
+    ///
+    /// Partially downloaded files are kept in cache and resumed using HTTP Range requests, if the
+    /// server supports them. A resumed download is only streamed after the complete file has been
+    /// verified, and falls back to a full download if it is invalid.
+    ///
+    /// In offline mode, the file is streamed from cache if present.
     pub async fn download_to_stream<U: reqwest::IntoUrl>(
         self,
         url: U,
         sha256: [u8; 32],
-        mut writer: bb_helper::file_stream::WriterFileStream,
+        writer: bb_helper::file_stream::WriterFileStream,
     ) -> io::Result<()> {
-        let url = url.into_url().map_err(io::Error::other)?;
+        self.download_to_stream_with_mirrors([url], sha256, writer)
+            .await
+    }
+
+    /// Same as [`download_to_stream`](Self::download_to_stream), but with a list of mirrors. The
+    /// URLs are tried in order whenever a request fails.
+    pub async fn download_to_stream_with_mirrors<U, I>(
+        self,
+        urls: I,
+        sha256: [u8; 32],
+        mut writer: bb_helper::file_stream::WriterFileStream,
+    ) -> io::Result<()>
+    where
+        U: reqwest::IntoUrl,
+        I: IntoIterator<Item = U>,
+    {
+        let urls = urls
+            .into_iter()
+            .map(into_url)
+            .collect::<io::Result<Vec<_>>>()?;
+        if urls.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "No URL to download",
+            ));
+        }
         tracing::debug!(
             "Download {:?} with sha256: {:?}",
-            url,
+            urls,
             const_hex::encode(sha256)
         );
 
+        if self.offline {
+            let path = self.check_cache_from_sha(sha256).ok_or(OfflineCacheMiss)?;
+            let mut file = tokio::fs::File::open(path).await?;
+            tokio::io::copy(&mut file, &mut writer).await?;
+            return writer.flush().await;
+        }
+
         let file_path = self.path_from_sha(sha256);
+        let part_path = self.partial_path_from_sha(sha256);
+
+        if self.resume(&urls, sha256, &part_path).await? {
+            let mut part = tokio::fs::File::open(&part_path).await?;
+            tokio::io::copy(&mut part, &mut writer).await?;
+            writer.flush().await?;
+        } else {
+            let response = self.get(&urls, None).await.map_err(io::Error::other)?;
+            let ranges = segmented::segmented_len(&response)
+                .filter(|_| self.segments > 1)
+                .map(|len| segmented::segments(len, self.segments))
+                .filter(|x| x.len() > 1);
+            let segments = match ranges {
+                Some(ranges) => match self.segment_responses(&urls, &ranges).await? {
+                    Some(x) => Some((ranges, x)),
+                    None => {
+                        tracing::warn!("Range requests not served, downloading in a single stream");
+                        None
+                    }
+                },
+                None => None,
+            };
+
+            if let Some((ranges, mut responses)) = segments {
+                responses.insert(0, response);
+                self.download_segmented(&urls, sha256, &mut writer, responses, ranges, &part_path)
+                    .await
+                    .inspect_err(|_| {
+                        // The file has holes, so it cannot be resumed.
+                        let _ = std::fs::remove_file(&part_path);
+                    })?;
+            } else {
+                let part = tokio::fs::File::create(&part_path).await?;
+                self.download_full(&urls, sha256, &mut writer, part, response)
+                    .await
+                    .inspect_err(|e| {
+                        // Nothing to resume from if the file itself is invalid.
+                        if e.kind() == io::ErrorKind::InvalidInput {
+                            let _ = std::fs::remove_file(&part_path);
+                        }
+                    })?;
+            }
+        }
+
+        tracing::info!("Saving donwloaded file to disk");
+        tokio::fs::rename(&part_path, &file_path).await?;
+        self.touch(&file_path, urls.first(), None);
+
+        Ok(())
+    }
+
+    /// Resume a partial download. Returns `false` if there is nothing to resume, the server does
+    /// not support Range requests, or the resumed file is invalid.
+    async fn resume(
+        &self,
+        urls: &[reqwest::Url],
+        sha256: [u8; 32],
+        part_path: &Path,
+    ) -> io::Result<bool> {
+        let offset = match tokio::fs::metadata(part_path).await {
+            Ok(x) if x.len() > 0 => x.len(),
+            _ => return Ok(false),
+        };
+
+        tracing::info!("Resuming download from {offset} bytes");
+        let response = match self.get(urls, Some(offset)).await {
+            Ok(x) => x,
+            // Range not satisfiable, etc.
+            Err(e) if e.status().is_some() => {
+                tracing::info!("Failed to resume download: {e}");
+                return Ok(false);
+            }
+            Err(e) => return Err(io::Error::other(e)),
+        };
 
+        if response.status() != reqwest::StatusCode::PARTIAL_CONTENT
+            || content_range_start(response.headers()) != Some(offset)
         {
-            let mut file = tokio::io::BufWriter::new(&mut writer);
+            tracing::info!("Server does not support resuming downloads");
+            return Ok(false);
+        }
+
+        let part = tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(part_path)
+            .await?;
+        let mut part = tokio::io::BufWriter::new(part);
+
+        let mut body = Body::new(self, urls, response, offset);
+        let res = async {
+            while let Some(data) = body.chunk().await? {
+                part.write_all(&data).await?;
+            }
+            io::Result::Ok(())
+        }
+        .await;
+        // Keep the progress for the next attempt.
+        part.flush().await?;
+        res?;
 
-            let response = self
-                .client
-                .get(url)
-                .send()
-                .await
-                .map_err(io::Error::other)?;
+        let p = part_path.to_path_buf();
+        let hash = tokio::task::spawn_blocking(move || sha256_from_path(&p))
+            .await
+            .unwrap()?;
 
-            let mut response_stream = response.bytes_stream().map_err(io::Error::other);
+        if hash != sha256 {
+            tracing::warn!("Resumed download is invalid. Falling back to full download");
+            return Ok(false);
+        }
 
-            let mut hasher = Sha256::new();
+        Ok(true)
+    }
 
-            while let Some(x) = response_stream.next().await {
-                let mut data = x?;
+    /// Download the whole file from `response` to `writer`. The data is also written to `part` so
+    /// that the download can be resumed if interrupted.
+    async fn download_full(
+        &self,
+        urls: &[reqwest::Url],
+        sha256: [u8; 32],
+        writer: &mut bb_helper::file_stream::WriterFileStream,
+        part: tokio::fs::File,
+        response: reqwest::Response,
+    ) -> io::Result<()> {
+        let mut file = tokio::io::BufWriter::new(writer);
+        let mut part = tokio::io::BufWriter::new(part);
+
+        let mut hasher = Sha256::new();
+
+        let mut body = Body::new(self, urls, response, 0);
+        let res = async {
+            while let Some(data) = body.chunk().await? {
                 hasher.update(&data);
-                file.write_all_buf(&mut data).await?;
+                file.write_all(&data).await?;
+                part.write_all(&data).await?;
             }
+            io::Result::Ok(())
+        }
+        .await;
+        // Keep the progress for the next attempt.
+        part.flush().await?;
+        res?;
 
-            let hash: [u8; 32] = hasher
-                .finalize()
-                .as_slice()
-                .try_into()
-                .expect("SHA-256 is 32 bytes");
-
-            if hash != sha256 {
-                tracing::error!(
-                    "Expected SHA256: {}, got {}",
-                    const_hex::encode(sha256),
-                    const_hex::encode(hash)
-                );
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    "Invalid SHA256",
-                ));
+        verify_sha256(hasher, sha256)?;
+        file.flush().await
+    }
+
+    /// Send a GET request, starting at `range` bytes if provided. Local files are served without
+    /// making any request.
+    ///
+    /// The URLs are tried in order. Once all of them fail, the request is retried according to
+    /// the retry policy. URLs that fail with errors which are not retryable are skipped.
+    async fn get(
+        &self,
+        urls: &[reqwest::Url],
+        range: Option<u64>,
+    ) -> reqwest::Result<reqwest::Response> {
+        self.get_with_headers(urls, range, &reqwest::header::HeaderMap::new())
+            .await
+    }
+
+    /// Same as [`get`](Self::get), but with additional headers. Local files ignore the headers.
+    async fn get_with_headers(
+        &self,
+        urls: &[reqwest::Url],
+        range: Option<u64>,
+        headers: &reqwest::header::HeaderMap,
+    ) -> reqwest::Result<reqwest::Response> {
+        let mut skip = vec![false; urls.len()];
+        let mut attempt = 0;
+
+        loop {
+            let mut last_err = None;
+
+            for (url, skip) in urls.iter().zip(skip.iter_mut()).filter(|(_, s)| !**s) {
+                let res = match local::local_path(url, self.local_mirror.as_deref()) {
+                    Some(path) => Ok(local::response(url, &path, range).await),
+                    None => {
+                        let mut req = self.client.get(url.clone()).headers(headers.clone());
+                        if let Some(x) = range {
+                            req = req.header(reqwest::header::RANGE, format!("bytes={x}-"));
+                        }
+                        req.send().await
+                    }
+                };
+
+                match res.and_then(|x| x.error_for_status()) {
+                    Ok(x) => return Ok(x),
+                    Err(e) => {
+                        tracing::warn!("Request to {url} failed: {e}");
+                        *skip = !is_retryable(&e);
+                        last_err = Some(e);
+                    }
+                }
+            }
+
+            let e = last_err.expect("At least one URL is required");
+            if attempt >= self.retry.max_retries || skip.iter().all(|x| *x) {
+                return Err(e);
             }
-            file.flush().await?;
+
+            let delay = self.retry.backoff(attempt);
+            tracing::info!("Retrying in {delay:?}");
+            tokio::time::sleep(delay).await;
+            attempt += 1;
         }
+    }
 
-        tracing::info!("Saving donwloaded file to disk");
-        writer.persist(&file_path).await
+    fn lock_index(&self) -> std::sync::MutexGuard<'_, ()> {
+        self.index_lock
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Name of a file in the cache directory.
+    fn cache_name<'a>(&self, path: &'a Path) -> io::Result<&'a str> {
+        if path.parent() != Some(&*self.cache_dir) || !path.is_file() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "File not found in cache",
+            ));
+        }
+
+        path.file_name()
+            .and_then(|x| x.to_str())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid file name"))
+    }
+
+    /// Mark a cached file as used, and evict least recently used files if the cache is too big.
+    /// Failing to update the index is not fatal.
+    fn touch(&self, path: &Path, url: Option<&reqwest::Url>, validators: Option<Validators>) {
+        if let Err(e) = self.touch_inner(path, url, validators) {
+            tracing::warn!("Failed to update cache index: {e}");
+        }
+    }
+
+    fn touch_inner(
+        &self,
+        path: &Path,
+        url: Option<&reqwest::Url>,
+        validators: Option<Validators>,
+    ) -> io::Result<()> {
+        let name = self.cache_name(path)?;
+
+        let _guard = self.lock_index();
+        let mut index = Index::load(&self.cache_dir)?;
+        index.touch(name, url);
+        if let Some(x) = validators {
+            index.set_validators(name, x);
+        }
+
+        if let Some(max) = self.max_cache_size {
+            let entries = index.entries(&self.cache_dir)?;
+            let mut total: u64 = entries.iter().map(|x| x.size).sum();
+
+            // The file being used is never evicted, even if it is bigger than the limit.
+            for entry in entries.into_iter().filter(|x| !x.pinned && x.path != path) {
+                if total <= max {
+                    break;
+                }
+
+                tracing::info!("Evicting {:?} from cache", entry.path);
+                std::fs::remove_file(&entry.path)?;
+                total -= entry.size;
+            }
+        }
+
+        index.save(&self.cache_dir)
     }
 
     fn path_from_url(&self, url: &reqwest::Url) -> PathBuf {
-        let fext = Path::new(url.path()).extension().expect("Invalid URL");
         let file_name: [u8; 32] = Sha256::new()
             .chain_update(url.as_str())
             .finalize()
             .as_slice()
             .try_into()
             .expect("SHA-256 is 32 bytes");
-        self.path_from_sha(file_name).with_extension(fext)
+        let path = self.path_from_sha(file_name);
+
+        match Path::new(url.path()).extension() {
+            Some(fext) => path.with_extension(fext),
+            None => path,
+        }
+    }
+
+    fn validators(&self, path: &Path) -> Validators {
+        let Ok(name) = self.cache_name(path) else {
+            return Validators::default();
+        };
+
+        let _guard = self.lock_index();
+        Index::load(&self.cache_dir)
+            .map(|x| x.validators(name))
+            .unwrap_or_default()
     }
 
     fn path_from_sha(&self, sha256: [u8; 32]) -> PathBuf {
         let file_name = const_hex::encode(sha256);
         self.cache_dir.join(file_name)
     }
+
+    fn partial_path_from_sha(&self, sha256: [u8; 32]) -> PathBuf {
+        self.path_from_sha(sha256).with_extension("part")
+    }
+}
+
+/// Check the hash of downloaded data. Fails with [`InvalidInput`](io::ErrorKind::InvalidInput)
+/// on mismatch.
+fn verify_sha256(hasher: Sha256, sha256: [u8; 32]) -> io::Result<()> {
+    let hash: [u8; 32] = hasher
+        .finalize()
+        .as_slice()
+        .try_into()
+        .expect("SHA-256 is 32 bytes");
+
+    if hash != sha256 {
+        tracing::error!(
+            "Expected SHA256: {}, got {}",
+            const_hex::encode(sha256),
+            const_hex::encode(hash)
+        );
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Invalid SHA256",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Response body which is continued using Range requests if the connection drops, according to
+/// the retry policy.
+struct Body<'a> {
+    downloader: &'a Downloader,
+    urls: &'a [reqwest::Url],
+    response: reqwest::Response,
+    /// Position of the next chunk in the file.
+    offset: u64,
+    retries: u32,
+}
+
+impl<'a> Body<'a> {
+    const fn new(
+        downloader: &'a Downloader,
+        urls: &'a [reqwest::Url],
+        response: reqwest::Response,
+        offset: u64,
+    ) -> Self {
+        Self {
+            downloader,
+            urls,
+            response,
+            offset,
+            retries: 0,
+        }
+    }
+
+    async fn chunk(&mut self) -> io::Result<Option<bytes::Bytes>> {
+        loop {
+            let err = match self.response.chunk().await {
+                Ok(x) => {
+                    self.offset += x.as_ref().map_or(0, |x| x.len() as u64);
+                    return Ok(x);
+                }
+                Err(e) => e,
+            };
+
+            if self.retries >= self.downloader.retry.max_retries {
+                return Err(io::Error::other(err));
+            }
+            self.retries += 1;
+            tracing::warn!(
+                "Download interrupted at {} bytes: {err}. Continuing",
+                self.offset
+            );
+
+            self.response = match self.downloader.get(self.urls, Some(self.offset)).await {
+                Ok(x)
+                    if x.status() == reqwest::StatusCode::PARTIAL_CONTENT
+                        && content_range_start(x.headers()) == Some(self.offset) =>
+                {
+                    x
+                }
+                _ => return Err(io::Error::other(err)),
+            };
+        }
+    }
 }
 
 #[cfg(test)]
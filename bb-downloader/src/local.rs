@@ -0,0 +1,175 @@
+//! Local sources: `file://` URLs and local mirrors.
+//!
+//! Local files are served as regular HTTP responses, so that they go through the same code paths
+//! as remote files, including Range requests, retries and SHA256 verification. IO errors are
+//! mapped to the closest HTTP status.
+
+use std::{
+    io::{self, SeekFrom},
+    path::{Component, Path, PathBuf},
+};
+
+use reqwest::{ResponseBuilderExt, StatusCode, header};
+use tokio::io::AsyncSeekExt;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Path of a URL on disk, if it is local.
+///
+/// `file://` URLs are always local. HTTP(S) URLs are served from the local mirror, if present
+/// there as `<mirror>/<host>/<path>`.
+pub(crate) fn local_path(url: &reqwest::Url, mirror: Option<&Path>) -> Option<PathBuf> {
+    match url.scheme() {
+        "file" => url.to_file_path().ok(),
+        "http" | "https" => {
+            let mirror = reqwest::Url::from_directory_path(mirror?).ok()?;
+            // The path of a parsed URL is normalized, but encoded separators (`..%2F`) are only
+            // decoded here, so `..` can still show up and escape the mirror.
+            let path = mirror
+                .join(&format!("{}{}", url.host_str()?, url.path()))
+                .ok()?
+                .to_file_path()
+                .ok()?;
+
+            if path.components().any(|x| x == Component::ParentDir) {
+                return None;
+            }
+
+            path.is_file().then_some(path)
+        }
+        _ => None,
+    }
+}
+
+/// Response for a local file, starting at `range` bytes if provided.
+pub(crate) async fn response(
+    url: &reqwest::Url,
+    path: &Path,
+    range: Option<u64>,
+) -> reqwest::Response {
+    let builder = http::Response::builder().url(url.clone());
+
+    match file_response(builder, path, range).await {
+        Ok(x) => x,
+        Err(e) => {
+            let status = match e.kind() {
+                io::ErrorKind::NotFound | io::ErrorKind::IsADirectory => StatusCode::NOT_FOUND,
+                io::ErrorKind::PermissionDenied => StatusCode::FORBIDDEN,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            tracing::warn!("Failed to open {path:?}: {e}");
+
+            http::Response::builder()
+                .url(url.clone())
+                .status(status)
+                .body(reqwest::Body::from(e.to_string()))
+                .expect("Valid response")
+                .into()
+        }
+    }
+}
+
+async fn file_response(
+    builder: http::response::Builder,
+    path: &Path,
+    range: Option<u64>,
+) -> io::Result<reqwest::Response> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let metadata = file.metadata().await?;
+    if metadata.is_dir() {
+        return Err(io::ErrorKind::IsADirectory.into());
+    }
+    let len = metadata.len();
+
+    let builder = match range {
+        Some(offset) if offset >= len => {
+            return Ok(builder
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{len}"))
+                .body(reqwest::Body::from(""))
+                .expect("Valid response")
+                .into());
+        }
+        Some(offset) => {
+            file.seek(SeekFrom::Start(offset)).await?;
+            builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(
+                    header::CONTENT_RANGE,
+                    format!("bytes {offset}-{}/{len}", len - 1),
+                )
+                .header(header::CONTENT_LENGTH, len - offset)
+        }
+        None => builder
+            .status(StatusCode::OK)
+            .header(header::CONTENT_LENGTH, len),
+    };
+
+    let body = tokio_util::io::ReaderStream::with_capacity(file, CHUNK_SIZE);
+    Ok(builder
+        .body(reqwest::Body::wrap_stream(body))
+        .expect("Valid response")
+        .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_path() {
+        let mirror = tempfile::tempdir().unwrap();
+        let file = mirror.path().join("example.com/images/a b.img");
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::write(&file, b"data").unwrap();
+
+        let url = |x: &str| reqwest::Url::parse(x).unwrap();
+
+        assert_eq!(
+            local_path(
+                &url("https://example.com/images/a%20b.img"),
+                Some(mirror.path())
+            ),
+            Some(file.clone())
+        );
+        assert_eq!(
+            local_path(
+                &url("https://example.com/images/../images/a%20b.img"),
+                Some(mirror.path())
+            ),
+            Some(file.clone())
+        );
+        // Not mirrored.
+        assert_eq!(
+            local_path(&url("https://example.com/other.img"), Some(mirror.path())),
+            None
+        );
+        assert_eq!(
+            local_path(&url("https://example.com/images/a%20b.img"), None),
+            None
+        );
+
+        // File URLs are always local, even if missing.
+        let file_url = reqwest::Url::from_file_path(&file).unwrap();
+        assert_eq!(local_path(&file_url, None), Some(file.clone()));
+        let missing = reqwest::Url::from_file_path(file.with_extension("xz")).unwrap();
+        assert_eq!(
+            local_path(&missing, Some(mirror.path())),
+            Some(file.with_extension("xz"))
+        );
+    }
+
+    /// Encoded separators are decoded after the URL is normalized, so they cannot be used to
+    /// escape the mirror.
+    #[test]
+    fn test_local_path_encoded_traversal() {
+        let base = tempfile::tempdir().unwrap();
+        let mirror = base.path().join("mirror");
+        std::fs::create_dir_all(mirror.join("example.com/images")).unwrap();
+        std::fs::write(base.path().join("secret.img"), b"data").unwrap();
+
+        let url =
+            reqwest::Url::parse("https://example.com/images/..%2F..%2F..%2Fsecret.img").unwrap();
+        assert_eq!(local_path(&url, Some(&mirror)), None);
+    }
+}
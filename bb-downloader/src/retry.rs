@@ -0,0 +1,96 @@
+//! Retry policy for failed requests.
+
+use std::{
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+/// Policy for retrying failed requests.
+///
+/// Connection errors, timeouts and server errors (5xx) are retried with exponential backoff and
+/// jitter. Other errors, such as 404, fail immediately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Maximum number of retries after the first attempt.
+    pub max_retries: u32,
+    /// Delay before the first retry. Doubled after every retry.
+    pub initial_backoff: Duration,
+    /// Upper bound for the delay between retries.
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Do not retry failed requests.
+    pub const NONE: Self = Self {
+        max_retries: 0,
+        initial_backoff: Duration::ZERO,
+        max_backoff: Duration::ZERO,
+    };
+
+    /// Delay before retry number `attempt`, starting from 0. A random delay between half and the
+    /// full backoff is used, so that clients do not retry in lockstep.
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2_u32.saturating_pow(attempt))
+            .min(self.max_backoff);
+        let half = backoff / 2;
+
+        half + half.mul_f64(jitter())
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Check if a failed request should be retried.
+pub(crate) fn is_retryable(e: &reqwest::Error) -> bool {
+    e.is_connect() || e.is_timeout() || e.status().is_some_and(|x| x.is_server_error())
+}
+
+/// Random number in `[0, 1)`. `RandomState` is randomly seeded, which is good enough for jitter.
+fn jitter() -> f64 {
+    let x = std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish();
+    (x >> 11) as f64 / (1_u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(10),
+        };
+
+        for (attempt, expected) in [(0, 1), (1, 2), (2, 4), (3, 8), (4, 10), (30, 10)] {
+            let expected = Duration::from_secs(expected);
+            let backoff = policy.backoff(attempt);
+            assert!(
+                backoff >= expected / 2 && backoff <= expected,
+                "attempt {attempt}: {backoff:?}"
+            );
+        }
+
+        assert_eq!(RetryPolicy::NONE.backoff(5), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_jitter() {
+        for _ in 0..100 {
+            assert!((0.0..1.0).contains(&jitter()));
+        }
+    }
+}
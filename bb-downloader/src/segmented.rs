@@ -0,0 +1,215 @@
+//! Parallel segmented downloads.
+//!
+//! The file is split into segments which are downloaded concurrently using Range requests, and
+//! written to the partial file at their offsets. The partial file is read back in order, so that
+//! the data is streamed and hashed sequentially, as soon as a contiguous prefix is available.
+
+use std::{io, ops::Range, path::Path, sync::Arc};
+
+use reqwest::header;
+use sha2::{Digest, Sha256};
+use tokio::{
+    io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+    sync::watch,
+};
+
+use crate::{Body, Downloader, helpers::content_range_start};
+
+/// Segments smaller than this are not worth an extra request.
+const MIN_SEGMENT_SIZE: u64 = 1024 * 1024;
+const BUF_SIZE: usize = 64 * 1024;
+
+/// Size of the file, if the response allows downloading it in segments.
+pub(crate) fn segmented_len(response: &reqwest::Response) -> Option<u64> {
+    let accept_ranges = response
+        .headers()
+        .get(header::ACCEPT_RANGES)?
+        .to_str()
+        .ok()?;
+
+    if response.status() != reqwest::StatusCode::OK || accept_ranges.trim() != "bytes" {
+        return None;
+    }
+
+    response.content_length()
+}
+
+/// Split `len` bytes into at most `n` segments of at least [`MIN_SEGMENT_SIZE`].
+pub(crate) fn segments(len: u64, n: usize) -> Vec<Range<u64>> {
+    let n = (len / MIN_SEGMENT_SIZE).clamp(1, n.max(1) as u64);
+    let seg_len = len.div_ceil(n);
+
+    (0..n)
+        .map(|i| (i * seg_len)..((i + 1) * seg_len).min(len))
+        .collect()
+}
+
+impl Downloader {
+    /// Request every segment after the first one, which is served by the plain GET request.
+    ///
+    /// Returns `None` if any of them is not served as the requested range, in which case the file
+    /// should be downloaded in a single stream instead.
+    pub(crate) async fn segment_responses(
+        &self,
+        urls: &[reqwest::Url],
+        ranges: &[Range<u64>],
+    ) -> io::Result<Option<Vec<reqwest::Response>>> {
+        let mut responses = Vec::with_capacity(ranges.len());
+
+        for range in ranges.iter().skip(1) {
+            let mut headers = header::HeaderMap::new();
+            let value = format!("bytes={}-{}", range.start, range.end - 1);
+            headers.insert(header::RANGE, value.parse().expect("Valid header"));
+
+            let response = self
+                .get_with_headers(urls, None, &headers)
+                .await
+                .map_err(io::Error::other)?;
+
+            if response.status() != reqwest::StatusCode::PARTIAL_CONTENT
+                || content_range_start(response.headers()) != Some(range.start)
+            {
+                return Ok(None);
+            }
+
+            responses.push(response);
+        }
+
+        Ok(Some(responses))
+    }
+
+    /// Download the file in segments to `part_path`, while streaming it to `writer` in order.
+    ///
+    /// `responses` has one response per segment, starting with the response to the plain GET
+    /// request.
+    pub(crate) async fn download_segmented(
+        &self,
+        urls: &[reqwest::Url],
+        sha256: [u8; 32],
+        writer: &mut bb_helper::file_stream::WriterFileStream,
+        responses: Vec<reqwest::Response>,
+        ranges: Vec<Range<u64>>,
+        part_path: &Path,
+    ) -> io::Result<()> {
+        let len = ranges.last().map_or(0, |x| x.end);
+        tracing::info!("Downloading {len} bytes in {} segments", ranges.len());
+
+        std::fs::File::create(part_path)?.set_len(len)?;
+
+        let part_path: Arc<Path> = part_path.into();
+        let urls: Arc<[reqwest::Url]> = urls.into();
+        let mut tasks = tokio::task::JoinSet::new();
+        let mut progress = Vec::with_capacity(ranges.len());
+
+        for (range, response) in ranges.iter().zip(responses) {
+            let (tx, rx) = watch::channel(0);
+            progress.push(rx);
+
+            tasks.spawn(self.clone().download_segment(
+                urls.clone(),
+                response,
+                range.clone(),
+                part_path.clone(),
+                tx,
+            ));
+        }
+
+        let mut part = tokio::fs::File::open(&part_path).await?;
+        let mut hasher = Sha256::new();
+        let mut buf = vec![0; BUF_SIZE];
+
+        for (range, mut rx) in ranges.into_iter().zip(progress) {
+            let mut pos = 0;
+            let seg_len = range.end - range.start;
+
+            while pos < seg_len {
+                let available = *rx.borrow_and_update();
+                if pos == available {
+                    // Sender is dropped once the segment is done.
+                    if rx.changed().await.is_err() && *rx.borrow() == pos {
+                        tasks.abort_all();
+                        return Err(segment_error(tasks).await);
+                    }
+                    continue;
+                }
+
+                let count = (available - pos).min(BUF_SIZE as u64) as usize;
+                part.read_exact(&mut buf[..count]).await?;
+                hasher.update(&buf[..count]);
+                writer.write_all(&buf[..count]).await?;
+                pos += count as u64;
+            }
+        }
+
+        while let Some(x) = tasks.join_next().await {
+            x.map_err(io::Error::other)??;
+        }
+
+        crate::verify_sha256(hasher, sha256)?;
+        writer.flush().await
+    }
+
+    async fn download_segment(
+        self,
+        urls: Arc<[reqwest::Url]>,
+        response: reqwest::Response,
+        range: Range<u64>,
+        part_path: Arc<Path>,
+        progress: watch::Sender<u64>,
+    ) -> io::Result<()> {
+        // Separate handle, since cloned handles share the position.
+        let mut part = tokio::fs::OpenOptions::new()
+            .write(true)
+            .open(&part_path)
+            .await?;
+        part.seek(io::SeekFrom::Start(range.start)).await?;
+
+        let seg_len = range.end - range.start;
+        let mut written = 0;
+        let mut body = Body::new(&self, &urls, response, range.start);
+
+        while written < seg_len {
+            let data = body
+                .chunk()
+                .await?
+                .ok_or(io::Error::from(io::ErrorKind::UnexpectedEof))?;
+            let count = data.len().min((seg_len - written) as usize);
+
+            part.write_all(&data[..count]).await?;
+            // Needs to be visible to the reader.
+            part.flush().await?;
+
+            written += count as u64;
+            progress.send_replace(written);
+        }
+
+        Ok(())
+    }
+}
+
+/// Error of the first failed segment.
+async fn segment_error(mut tasks: tokio::task::JoinSet<io::Result<()>>) -> io::Error {
+    while let Some(x) = tasks.join_next().await {
+        if let Ok(Err(e)) = x {
+            return e;
+        }
+    }
+
+    io::Error::other("Segment download failed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segments() {
+        const M: u64 = MIN_SEGMENT_SIZE;
+
+        assert_eq!(segments(10, 4).len(), 1);
+        assert_eq!(segments(3 * M, 4), [0..M, M..2 * M, 2 * M..3 * M]);
+        assert_eq!(segments(4 * M + 1, 2), [0..2 * M + 1, 2 * M + 1..4 * M + 1]);
+        assert_eq!(segments(0, 4).len(), 1);
+        assert_eq!(segments(4 * M, 0).len(), 1);
+    }
+}
@@ -0,0 +1,113 @@
+use bb_downloader::{Downloader, OfflineCacheMiss, Proxy};
+use bb_helper::file_stream::file_stream;
+use httpmock::{Method::GET, MockServer};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use tempfile::TempDir;
+
+#[tokio::test]
+async fn offline_serves_from_cache() {
+    let server = MockServer::start();
+    let tmp = TempDir::new().unwrap();
+
+    let mock = server.mock(|when, then| {
+        when.method(GET).path("/file.txt");
+        then.status(200).body("cached");
+    });
+    let url = server.url("/file.txt");
+
+    let path = Downloader::new(tmp.path())
+        .unwrap()
+        .download(&url)
+        .await
+        .unwrap();
+
+    let offline = Downloader::builder(tmp.path())
+        .offline(true)
+        .build()
+        .unwrap();
+    assert_eq!(offline.download(&url).await.unwrap(), path);
+
+    let err = offline
+        .download(server.url("/other.txt"))
+        .await
+        .unwrap_err();
+    assert!(OfflineCacheMiss::is(&err));
+    assert_eq!(err.kind(), std::io::ErrorKind::NotConnected);
+
+    mock.assert_calls(1);
+}
+
+#[tokio::test]
+async fn offline_stream_from_cache() {
+    let server = MockServer::start();
+    let tmp = TempDir::new().unwrap();
+
+    let content = b"streamed payload bytes";
+    let sha: [u8; 32] = Sha256::digest(content).into();
+    let mock = server.mock(|when, then| {
+        when.method(GET).path("/img");
+        then.status(200).body(content);
+    });
+
+    let offline = Downloader::builder(tmp.path())
+        .offline(true)
+        .build()
+        .unwrap();
+
+    let (writer, _reader) = file_stream().unwrap();
+    let err = offline
+        .clone()
+        .download_to_stream(server.url("/img"), sha, writer)
+        .await
+        .unwrap_err();
+    assert!(OfflineCacheMiss::is(&err));
+    mock.assert_calls(0);
+
+    let (writer, _reader) = file_stream().unwrap();
+    Downloader::new(tmp.path())
+        .unwrap()
+        .download_to_stream(server.url("/img"), sha, writer)
+        .await
+        .unwrap();
+    mock.assert_calls(1);
+
+    let (writer, mut reader) = file_stream().unwrap();
+    offline
+        .download_to_stream(server.url("/img"), sha, writer)
+        .await
+        .unwrap();
+
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data).unwrap();
+    assert_eq!(data, content);
+    mock.assert_calls(1);
+}
+
+#[tokio::test]
+async fn proxy_and_user_agent() {
+    let proxy = MockServer::start();
+    let tmp = TempDir::new().unwrap();
+
+    let mock = proxy.mock(|when, then| {
+        when.method(GET)
+            .host("example.invalid")
+            .path("/file.txt")
+            .header("user-agent", "test-agent");
+        then.status(200).body("proxied");
+    });
+
+    let downloader = Downloader::builder(tmp.path())
+        .user_agent("test-agent")
+        .proxy(Proxy::http(proxy.base_url()).unwrap())
+        .build()
+        .unwrap();
+
+    let path = downloader
+        .download("http://example.invalid/file.txt")
+        .await
+        .unwrap();
+
+    mock.assert_calls(1);
+    assert_eq!(std::fs::read(path).unwrap(), b"proxied");
+}
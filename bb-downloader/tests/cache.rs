@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+use bb_downloader::Downloader;
+use httpmock::{Method::GET, MockServer};
+use tempfile::TempDir;
+
+/// Mock `/<name>.txt` returning `size` bytes.
+fn mock_file(server: &MockServer, name: &str, size: usize) -> String {
+    let path = format!("/{name}.txt");
+    server.mock(|when, then| {
+        when.method(GET).path(path.as_str());
+        then.status(200).body(vec![b'a'; size]);
+    });
+    server.url(path)
+}
+
+/// Download a file, making sure that it is used later than the previous one.
+async fn download(downloader: &Downloader, url: &str) -> std::path::PathBuf {
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    downloader.download(url).await.unwrap()
+}
+
+#[tokio::test]
+async fn cache_entries() {
+    let server = MockServer::start();
+    let tmp = TempDir::new().unwrap();
+    let downloader = Downloader::new(tmp.path()).unwrap();
+
+    let url_a = mock_file(&server, "a", 10);
+    let url_b = mock_file(&server, "b", 20);
+
+    let a = download(&downloader, &url_a).await;
+    let b = download(&downloader, &url_b).await;
+    // Cache hit marks the file as used.
+    download(&downloader, &url_a).await;
+
+    let entries = downloader.cache_entries().unwrap();
+    assert_eq!(entries.len(), 2);
+
+    assert_eq!(entries[0].path, b);
+    assert_eq!(entries[0].size, 20);
+    assert_eq!(entries[0].url.as_ref().unwrap().as_str(), url_b);
+    assert!(!entries[0].pinned);
+
+    assert_eq!(entries[1].path, a);
+    assert_eq!(entries[1].size, 10);
+    assert_eq!(entries[1].url.as_ref().unwrap().as_str(), url_a);
+}
+
+#[tokio::test]
+async fn evicts_least_recently_used() {
+    let server = MockServer::start();
+    let tmp = TempDir::new().unwrap();
+    let downloader = Downloader::new(tmp.path()).unwrap().with_max_cache_size(25);
+
+    let a = download(&downloader, &mock_file(&server, "a", 10)).await;
+    let b = download(&downloader, &mock_file(&server, "b", 10)).await;
+    // Use `a` again, so that `b` is evicted.
+    download(&downloader, &mock_file(&server, "a", 10)).await;
+    let c = download(&downloader, &mock_file(&server, "c", 10)).await;
+
+    assert!(a.exists());
+    assert!(!b.exists());
+    assert!(c.exists());
+
+    // Files bigger than the limit are kept until the next download.
+    let d = download(&downloader, &mock_file(&server, "d", 30)).await;
+    assert!(d.exists());
+    assert!(!a.exists());
+    assert!(!c.exists());
+}
+
+#[tokio::test]
+async fn pinned_files_are_kept() {
+    let server = MockServer::start();
+    let tmp = TempDir::new().unwrap();
+    let downloader = Downloader::new(tmp.path()).unwrap().with_max_cache_size(15);
+
+    let a = download(&downloader, &mock_file(&server, "a", 10)).await;
+    downloader.set_pinned(&a, true).unwrap();
+    let b = download(&downloader, &mock_file(&server, "b", 10)).await;
+
+    assert!(a.exists());
+    assert!(b.exists());
+    assert!(downloader.cache_entries().unwrap()[0].pinned);
+
+    assert_eq!(downloader.purge_cache().unwrap(), 10);
+    assert!(a.exists());
+    assert!(!b.exists());
+
+    downloader.set_pinned(&a, false).unwrap();
+    assert_eq!(downloader.purge_cache().unwrap(), 10);
+    assert!(downloader.cache_entries().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn remove_from_cache() {
+    let server = MockServer::start();
+    let tmp = TempDir::new().unwrap();
+    let downloader = Downloader::new(tmp.path()).unwrap();
+
+    let a = download(&downloader, &mock_file(&server, "a", 10)).await;
+    downloader.set_pinned(&a, true).unwrap();
+    downloader.remove_from_cache(&a).unwrap();
+
+    assert!(!a.exists());
+    assert!(downloader.cache_entries().unwrap().is_empty());
+
+    // Only files in the cache can be removed.
+    let other = TempDir::new().unwrap();
+    let file = other.path().join("file");
+    std::fs::write(&file, b"data").unwrap();
+    assert_eq!(
+        downloader.remove_from_cache(&file).unwrap_err().kind(),
+        std::io::ErrorKind::NotFound
+    );
+    assert!(file.exists());
+}
@@ -0,0 +1,150 @@
+use bb_downloader::{Downloader, RetryPolicy};
+use httpmock::{Method::GET, MockServer};
+use tempfile::TempDir;
+
+#[tokio::test]
+async fn revalidate_with_etag() {
+    let server = MockServer::start();
+    let tmp = TempDir::new().unwrap();
+    let downloader = Downloader::new(tmp.path()).unwrap();
+
+    let mut first = server.mock(|when, then| {
+        when.method(GET).path("/list.json");
+        then.status(200).header("etag", "\"v1\"").body("first");
+    });
+    let url = server.url("/list.json");
+
+    let path = downloader.download_revalidate(&url).await.unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"first");
+    first.assert_calls(1);
+    first.delete();
+
+    let not_modified = server.mock(|when, then| {
+        when.method(GET)
+            .path("/list.json")
+            .header("if-none-match", "\"v1\"");
+        then.status(304);
+    });
+
+    assert_eq!(downloader.download_revalidate(&url).await.unwrap(), path);
+    assert_eq!(std::fs::read(&path).unwrap(), b"first");
+    not_modified.assert_calls(1);
+}
+
+#[tokio::test]
+async fn revalidate_with_last_modified() {
+    let server = MockServer::start();
+    let tmp = TempDir::new().unwrap();
+    let downloader = Downloader::new(tmp.path()).unwrap();
+
+    let last_modified = "Wed, 21 Oct 2015 07:28:00 GMT";
+    let mut first = server.mock(|when, then| {
+        when.method(GET).path("/list.json");
+        then.status(200)
+            .header("last-modified", last_modified)
+            .body("first");
+    });
+    let url = server.url("/list.json");
+
+    // Validators are also stored by `download`.
+    let path = downloader.download(&url).await.unwrap();
+    first.assert_calls(1);
+    first.delete();
+
+    let changed = server.mock(|when, then| {
+        when.method(GET)
+            .path("/list.json")
+            .header("if-modified-since", last_modified);
+        then.status(200).body("second");
+    });
+
+    assert_eq!(downloader.download_revalidate(&url).await.unwrap(), path);
+    assert_eq!(std::fs::read(&path).unwrap(), b"second");
+    changed.assert_calls(1);
+
+    // `download` keeps using the cached file.
+    assert_eq!(downloader.download(&url).await.unwrap(), path);
+    changed.assert_calls(1);
+}
+
+#[tokio::test]
+async fn stale_file_when_offline() {
+    let tmp = TempDir::new().unwrap();
+    let downloader = Downloader::builder(tmp.path())
+        .retry_policy(RetryPolicy::NONE)
+        .build()
+        .unwrap();
+
+    let url = {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/list.json");
+            then.status(200).header("etag", "\"v1\"").body("cached");
+        });
+        let url = server.url("/list.json");
+
+        downloader.download_revalidate(&url).await.unwrap();
+        url
+    };
+
+    // The server is gone.
+    let path = downloader.download_revalidate(&url).await.unwrap();
+    assert_eq!(std::fs::read(path).unwrap(), b"cached");
+}
+
+#[tokio::test]
+async fn revalidate_client_error() {
+    let server = MockServer::start();
+    let tmp = TempDir::new().unwrap();
+    let downloader = Downloader::new(tmp.path()).unwrap();
+
+    let mut first = server.mock(|when, then| {
+        when.method(GET).path("/list.json");
+        then.status(200).body("first");
+    });
+    let url = server.url("/list.json");
+    downloader.download_revalidate(&url).await.unwrap();
+    first.delete();
+
+    server.mock(|when, then| {
+        when.method(GET).path("/list.json");
+        then.status(404);
+    });
+    downloader.download_revalidate(&url).await.unwrap_err();
+}
+
+#[cfg(feature = "json")]
+#[tokio::test]
+async fn download_json_cached() {
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct TestData {
+        status: String,
+    }
+
+    let server = MockServer::start();
+    let tmp = TempDir::new().unwrap();
+    let downloader = Downloader::new(tmp.path()).unwrap();
+
+    let mut first = server.mock(|when, then| {
+        when.method(GET).path("/api/status");
+        then.status(200)
+            .header("etag", "\"v1\"")
+            .body(r#"{"status": "ok"}"#);
+    });
+    let url = server.url("/api/status");
+
+    let res: TestData = downloader.download_json(&url).await.unwrap();
+    assert_eq!(res.status, "ok");
+    first.delete();
+
+    let not_modified = server.mock(|when, then| {
+        when.method(GET)
+            .path("/api/status")
+            .header("if-none-match", "\"v1\"");
+        then.status(304);
+    });
+
+    let res: TestData = downloader.download_json(&url).await.unwrap();
+    assert_eq!(res.status, "ok");
+    not_modified.assert_calls(1);
+}
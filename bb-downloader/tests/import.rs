@@ -0,0 +1,97 @@
+use bb_downloader::{Downloader, ImportMode};
+use sha2::{Digest, Sha256};
+use tempfile::TempDir;
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+#[test]
+fn import_dir() {
+    let cache = TempDir::new().unwrap();
+    let staged = TempDir::new().unwrap();
+    let downloader = Downloader::new(cache.path()).unwrap();
+
+    std::fs::create_dir(staged.path().join("nested")).unwrap();
+    std::fs::write(staged.path().join("a.img.xz"), b"image a").unwrap();
+    std::fs::write(staged.path().join("nested/b.img.xz"), b"image b").unwrap();
+    std::fs::write(staged.path().join("unknown.img.xz"), b"unknown").unwrap();
+
+    let (sha_a, sha_b) = (sha256(b"image a"), sha256(b"image b"));
+    let mut imported = downloader
+        .import_dir(staged.path(), [sha_a, sha_b, [0; 32]], ImportMode::Copy)
+        .unwrap();
+    imported.sort_by_key(|x| x.source.clone());
+
+    assert_eq!(imported.len(), 2);
+    assert_eq!(imported[0].source, staged.path().join("a.img.xz"));
+    assert_eq!(imported[0].sha256, sha_a);
+    assert_eq!(imported[1].source, staged.path().join("nested/b.img.xz"));
+    assert_eq!(imported[1].sha256, sha_b);
+
+    // Treated as downloaded.
+    assert_eq!(
+        downloader.check_cache_from_sha(sha_a),
+        Some(imported[0].path.clone())
+    );
+    assert_eq!(std::fs::read(&imported[1].path).unwrap(), b"image b");
+    let entries = downloader.cache_entries().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert!(entries.iter().all(|x| x.pinned));
+
+    // Already in cache.
+    let imported = downloader
+        .import_dir(staged.path(), [sha_a, sha_b], ImportMode::Copy)
+        .unwrap();
+    assert!(imported.is_empty());
+}
+
+#[test]
+fn import_dir_hard_link() {
+    let cache = TempDir::new().unwrap();
+    let staged = TempDir::new_in(cache.path().parent().unwrap()).unwrap();
+    let downloader = Downloader::new(cache.path()).unwrap();
+
+    std::fs::write(staged.path().join("a.img.xz"), b"image a").unwrap();
+
+    let sha = sha256(b"image a");
+    let imported = downloader
+        .import_dir(staged.path(), [sha], ImportMode::HardLink)
+        .unwrap();
+
+    assert_eq!(imported.len(), 1);
+    assert_eq!(
+        downloader.check_cache_from_sha(sha),
+        Some(imported[0].path.clone())
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn import_dir_skips_unreadable_dir() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let cache = TempDir::new().unwrap();
+    let staged = TempDir::new().unwrap();
+    let downloader = Downloader::new(cache.path()).unwrap();
+
+    let locked = staged.path().join("locked");
+    std::fs::create_dir(&locked).unwrap();
+    std::fs::write(locked.join("b.img.xz"), b"image b").unwrap();
+    std::fs::write(staged.path().join("a.img.xz"), b"image a").unwrap();
+    std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000)).unwrap();
+
+    // Permissions do not apply to root.
+    if std::fs::read_dir(&locked).is_ok() {
+        return;
+    }
+
+    let sha = sha256(b"image a");
+    let imported = downloader
+        .import_dir(staged.path(), [sha, sha256(b"image b")], ImportMode::Copy)
+        .unwrap();
+    std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    assert_eq!(imported.len(), 1);
+    assert_eq!(imported[0].sha256, sha);
+}
@@ -0,0 +1,150 @@
+use bb_downloader::Downloader;
+use bb_helper::file_stream::file_stream;
+use httpmock::{Method::GET, MockServer};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use tempfile::TempDir;
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+fn read_stream(mut reader: impl Read) -> Vec<u8> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data).unwrap();
+    data
+}
+
+#[tokio::test]
+async fn download_file_url() {
+    let src = TempDir::new().unwrap();
+    let tmp = TempDir::new().unwrap();
+    let downloader = Downloader::new(tmp.path()).unwrap();
+
+    let file = src.path().join("file.txt");
+    std::fs::write(&file, b"local file").unwrap();
+    let url = reqwest::Url::from_file_path(&file).unwrap();
+
+    let path = downloader.download(url.clone()).await.unwrap();
+    assert!(path.starts_with(tmp.path()));
+    assert_eq!(std::fs::read(&path).unwrap(), b"local file");
+
+    // Served from cache, even once the source is gone.
+    std::fs::remove_file(&file).unwrap();
+    assert_eq!(downloader.download(url.clone()).await.unwrap(), path);
+
+    let missing = reqwest::Url::from_file_path(src.path().join("missing.txt")).unwrap();
+    downloader.download(missing).await.unwrap_err();
+}
+
+#[tokio::test]
+async fn download_to_stream_file_url() {
+    let src = TempDir::new().unwrap();
+    let tmp = TempDir::new().unwrap();
+    let downloader = Downloader::new(tmp.path()).unwrap();
+
+    let content = vec![b'x'; 200 * 1024];
+    let sha = sha256(&content);
+    let file = src.path().join("os.img");
+    std::fs::write(&file, &content).unwrap();
+    let url = reqwest::Url::from_file_path(&file).unwrap();
+
+    // Resumed from a partial download.
+    let part = tmp
+        .path()
+        .join(const_hex::encode(sha))
+        .with_extension("part");
+    std::fs::write(&part, &content[..1000]).unwrap();
+
+    let (writer, reader) = file_stream().unwrap();
+    downloader
+        .clone()
+        .download_to_stream(url.clone(), sha, writer)
+        .await
+        .unwrap();
+    assert_eq!(read_stream(reader), content);
+    assert!(!part.exists());
+    assert!(downloader.check_cache_from_sha(sha).is_some());
+
+    // SHA256 is verified.
+    let (writer, _reader) = file_stream().unwrap();
+    let err = downloader
+        .download_to_stream(url, [0; 32], writer)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[tokio::test]
+async fn local_mirror() {
+    let server = MockServer::start();
+    let mirror = TempDir::new().unwrap();
+    let tmp = TempDir::new().unwrap();
+
+    let content = b"mirrored image";
+    let sha = sha256(content);
+    let host = mirror.path().join("127.0.0.1").join("images");
+    std::fs::create_dir_all(&host).unwrap();
+    std::fs::write(host.join("os.img"), content).unwrap();
+
+    let mirrored = server.mock(|when, then| {
+        when.method(GET).path("/images/os.img");
+        then.status(200).body(content);
+    });
+    let remote = server.mock(|when, then| {
+        when.method(GET).path("/images/other.img");
+        then.status(200).body("remote");
+    });
+
+    let downloader = Downloader::builder(tmp.path())
+        .local_mirror(mirror.path())
+        .build()
+        .unwrap();
+
+    let (writer, reader) = file_stream().unwrap();
+    downloader
+        .clone()
+        .download_to_stream(server.url("/images/os.img"), sha, writer)
+        .await
+        .unwrap();
+    assert_eq!(read_stream(reader), content);
+    mirrored.assert_calls(0);
+
+    // Files missing from the mirror are downloaded.
+    let path = downloader
+        .download(server.url("/images/other.img"))
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(path).unwrap(), b"remote");
+    remote.assert_calls(1);
+}
+
+#[cfg(feature = "json")]
+#[tokio::test]
+async fn local_mirror_json() {
+    let mirror = TempDir::new().unwrap();
+    let tmp = TempDir::new().unwrap();
+
+    std::fs::create_dir_all(mirror.path().join("example.invalid")).unwrap();
+    std::fs::write(
+        mirror.path().join("example.invalid/distros.json"),
+        r#"{"os_list": []}"#,
+    )
+    .unwrap();
+
+    let downloader = Downloader::builder(tmp.path())
+        .local_mirror(mirror.path())
+        .build()
+        .unwrap();
+
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct Distros {
+        os_list: Vec<String>,
+    }
+
+    let res: Distros = downloader
+        .download_json_no_cache("https://example.invalid/distros.json")
+        .await
+        .unwrap();
+    assert_eq!(res, Distros { os_list: vec![] });
+}
@@ -0,0 +1,145 @@
+//! Integration tests for retrying failed requests and falling back to mirrors.
+
+use std::time::Duration;
+
+use bb_downloader::{Downloader, RetryPolicy};
+use bb_helper::file_stream::file_stream;
+use httpmock::{Method::GET, MockServer};
+use sha2::{Digest, Sha256};
+use tempfile::TempDir;
+
+const CONTENT: &[u8] = b"streamed payload bytes";
+
+/// Retry quickly so that tests do not have to wait.
+const POLICY: RetryPolicy = RetryPolicy {
+    max_retries: 2,
+    initial_backoff: Duration::from_millis(1),
+    max_backoff: Duration::from_millis(5),
+};
+
+fn downloader(tmp: &TempDir) -> Downloader {
+    Downloader::new(tmp.path())
+        .unwrap()
+        .with_retry_policy(POLICY)
+}
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+#[tokio::test]
+async fn retries_server_errors() {
+    let server = MockServer::start();
+    let tmp = TempDir::new().unwrap();
+
+    let mock = server.mock(|when, then| {
+        when.method(GET).path("/img");
+        then.status(503);
+    });
+
+    let (writer, _reader) = file_stream().unwrap();
+    downloader(&tmp)
+        .download_to_stream(server.url("/img"), sha256(CONTENT), writer)
+        .await
+        .expect_err("server error should fail after retries");
+
+    mock.assert_calls(1 + POLICY.max_retries as usize);
+}
+
+#[tokio::test]
+async fn does_not_retry_client_errors() {
+    let server = MockServer::start();
+    let tmp = TempDir::new().unwrap();
+
+    let mock = server.mock(|when, then| {
+        when.method(GET).path("/file.txt");
+        then.status(404);
+    });
+
+    downloader(&tmp)
+        .download(server.url("/file.txt"))
+        .await
+        .expect_err("404 should fail");
+
+    mock.assert_calls(1);
+    assert_eq!(
+        std::fs::read_dir(tmp.path()).unwrap().count(),
+        0,
+        "error responses should not be cached"
+    );
+}
+
+#[tokio::test]
+async fn falls_back_to_mirror() {
+    let server = MockServer::start();
+    let tmp = TempDir::new().unwrap();
+
+    let primary = server.mock(|when, then| {
+        when.method(GET).path("/img");
+        then.status(502);
+    });
+    let mirror = server.mock(|when, then| {
+        when.method(GET).path("/mirror/img");
+        then.status(200).body(CONTENT);
+    });
+
+    let (writer, _reader) = file_stream().unwrap();
+    downloader(&tmp)
+        .download_to_stream_with_mirrors(
+            [server.url("/img"), server.url("/mirror/img")],
+            sha256(CONTENT),
+            writer,
+        )
+        .await
+        .expect("mirror should be used");
+
+    primary.assert_calls(1);
+    mirror.assert_calls(1);
+    assert_eq!(
+        std::fs::read(tmp.path().join(const_hex::encode(sha256(CONTENT)))).unwrap(),
+        CONTENT
+    );
+}
+
+/// Connection errors are retried, and move on to the next mirror.
+#[tokio::test]
+async fn falls_back_to_mirror_on_connect_error() {
+    let server = MockServer::start();
+    let tmp = TempDir::new().unwrap();
+
+    // Nothing listens on port 1.
+    let unreachable = "http://127.0.0.1:1/img".to_string();
+    let mirror = server.mock(|when, then| {
+        when.method(GET).path("/img");
+        then.status(200).body(CONTENT);
+    });
+
+    let (writer, _reader) = file_stream().unwrap();
+    downloader(&tmp)
+        .download_to_stream_with_mirrors([unreachable, server.url("/img")], sha256(CONTENT), writer)
+        .await
+        .expect("mirror should be used");
+
+    mirror.assert_calls(1);
+}
+
+#[tokio::test]
+async fn without_retries() {
+    let server = MockServer::start();
+    let tmp = TempDir::new().unwrap();
+
+    let mock = server.mock(|when, then| {
+        when.method(GET).path("/img");
+        then.status(500);
+    });
+
+    let (writer, _reader) = file_stream().unwrap();
+    Downloader::new(tmp.path())
+        .unwrap()
+        .with_retry_policy(RetryPolicy::NONE)
+        .download_to_stream(server.url("/img"), sha256(CONTENT), writer)
+        .await
+        .expect_err("server error should fail");
+
+    mock.assert_calls(1);
+}
@@ -0,0 +1,140 @@
+//! Integration tests for segmented downloads with `DownloaderBuilder::segments`.
+
+use bb_downloader::Downloader;
+use bb_helper::file_stream::file_stream;
+use httpmock::{Method::GET, MockServer};
+use sha2::{Digest, Sha256};
+use std::io;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+const MIB: usize = 1024 * 1024;
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+fn content() -> Vec<u8> {
+    (0..3 * MIB).map(|x| (x % 251) as u8).collect()
+}
+
+fn partial_path(dir: &Path, sha: [u8; 32]) -> PathBuf {
+    dir.join(const_hex::encode(sha)).with_extension("part")
+}
+
+fn read_stream(mut reader: impl io::Read) -> Vec<u8> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data).unwrap();
+    data
+}
+
+#[tokio::test]
+async fn segmented_download() {
+    let server = MockServer::start();
+    let tmp = TempDir::new().unwrap();
+    let downloader = Downloader::builder(tmp.path()).segments(3).build().unwrap();
+
+    let content = content();
+    let sha = sha256(&content);
+
+    let full_mock = server.mock(|when, then| {
+        when.method(GET).path("/img").header_missing("range");
+        then.status(200)
+            .header("accept-ranges", "bytes")
+            .body(&content);
+    });
+    let range_mocks: Vec<_> = [1, 2]
+        .into_iter()
+        .map(|i| {
+            let (start, end) = (i * MIB, (i + 1) * MIB);
+            server.mock(|when, then| {
+                when.method(GET)
+                    .path("/img")
+                    .header("range", format!("bytes={start}-{}", end - 1));
+                then.status(206)
+                    .header(
+                        "content-range",
+                        format!("bytes {start}-{}/{}", end - 1, content.len()),
+                    )
+                    .body(&content[start..end]);
+            })
+        })
+        .collect();
+
+    let (writer, reader) = file_stream().unwrap();
+    let reader = std::thread::spawn(move || read_stream(reader));
+    downloader
+        .download_to_stream(server.url("/img"), sha, writer)
+        .await
+        .expect("segmented download should succeed");
+
+    full_mock.assert_calls(1);
+    for mock in range_mocks {
+        mock.assert_calls(1);
+    }
+    assert!(reader.join().unwrap() == content);
+    assert!(!partial_path(tmp.path(), sha).exists());
+    assert!(std::fs::read(tmp.path().join(const_hex::encode(sha))).unwrap() == content);
+}
+
+/// Servers which do not advertise Range support are downloaded in a single request.
+#[tokio::test]
+async fn segmented_download_falls_back_to_single_stream() {
+    let server = MockServer::start();
+    let tmp = TempDir::new().unwrap();
+    let downloader = Downloader::builder(tmp.path()).segments(3).build().unwrap();
+
+    let content = content();
+    let sha = sha256(&content);
+
+    let mock = server.mock(|when, then| {
+        when.method(GET).path("/img");
+        then.status(200).body(&content);
+    });
+
+    let (writer, reader) = file_stream().unwrap();
+    downloader
+        .download_to_stream(server.url("/img"), sha, writer)
+        .await
+        .expect("single stream download should succeed");
+
+    mock.assert_calls(1);
+    assert!(read_stream(reader) == content);
+}
+
+/// Segments served without a partial response fall back to downloading in a single stream.
+#[tokio::test]
+async fn segmented_download_falls_back_on_invalid_segment() {
+    let server = MockServer::start();
+    let tmp = TempDir::new().unwrap();
+    let downloader = Downloader::builder(tmp.path())
+        .segments(3)
+        .retry_policy(bb_downloader::RetryPolicy::NONE)
+        .build()
+        .unwrap();
+
+    let content = content();
+    let sha = sha256(&content);
+
+    let mock = server.mock(|when, then| {
+        when.method(GET).path("/img");
+        then.status(200)
+            .header("accept-ranges", "bytes")
+            .body(&content);
+    });
+
+    let (writer, reader) = file_stream().unwrap();
+    let reader = std::thread::spawn(move || read_stream(reader));
+    downloader
+        .download_to_stream(server.url("/img"), sha, writer)
+        .await
+        .expect("fallback to a single stream should succeed");
+
+    // Plain request, and the first segment which is not served as a range.
+    mock.assert_calls(2);
+    assert!(reader.join().unwrap() == content);
+    assert!(!partial_path(tmp.path(), sha).exists());
+    assert!(std::fs::read(tmp.path().join(const_hex::encode(sha))).unwrap() == content);
+}
@@ -17,12 +17,12 @@ fn sha256(data: &[u8]) -> [u8; 32] {
     hasher.finalize().into()
 }
 
-/// Return the single file in `dir`, or None if empty.
+/// Return the single file in `dir` other than the cache index, or None if empty.
 fn single_file(dir: &Path) -> Option<PathBuf> {
     std::fs::read_dir(dir)
         .unwrap()
         .map(|e| e.unwrap().path())
-        .next()
+        .find(|p| !p.ends_with("index"))
 }
 
 #[tokio::test]
@@ -84,3 +84,122 @@ async fn download_to_stream_rejects_sha_mismatch() {
         "no file should be persisted when the checksum does not match"
     );
 }
+
+/// Path of the partial download for `sha` in `dir`.
+fn partial_path(dir: &Path, sha: [u8; 32]) -> PathBuf {
+    dir.join(const_hex::encode(sha)).with_extension("part")
+}
+
+fn read_stream(mut reader: impl io::Read) -> Vec<u8> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data).unwrap();
+    data
+}
+
+#[tokio::test]
+async fn download_to_stream_resumes_partial_download() {
+    let server = MockServer::start();
+    let tmp = TempDir::new().unwrap();
+    let downloader = Downloader::new(tmp.path()).unwrap();
+
+    let content = b"streamed payload bytes";
+    let sha = sha256(content);
+    std::fs::write(partial_path(tmp.path(), sha), &content[..10]).unwrap();
+
+    let resume_mock = server.mock(|when, then| {
+        when.method(GET).path("/img").header("range", "bytes=10-");
+        then.status(206)
+            .header("content-range", format!("bytes 10-21/{}", content.len()))
+            .body(&content[10..]);
+    });
+    let full_mock = server.mock(|when, then| {
+        when.method(GET).path("/img").header_missing("range");
+        then.status(200).body(content);
+    });
+
+    let (writer, reader) = file_stream().unwrap();
+    downloader
+        .download_to_stream(server.url("/img"), sha, writer)
+        .await
+        .expect("resumed download should succeed");
+
+    resume_mock.assert_calls(1);
+    full_mock.assert_calls(0);
+    assert_eq!(read_stream(reader), content);
+
+    let path = single_file(tmp.path()).expect("a file should be persisted");
+    assert_eq!(std::fs::read(&path).unwrap(), content);
+    assert_eq!(
+        path.file_name().unwrap().to_str().unwrap(),
+        const_hex::encode(sha)
+    );
+}
+
+/// Servers without Range support reply with the whole file.
+#[tokio::test]
+async fn download_to_stream_restarts_without_range_support() {
+    let server = MockServer::start();
+    let tmp = TempDir::new().unwrap();
+    let downloader = Downloader::new(tmp.path()).unwrap();
+
+    let content = b"streamed payload bytes";
+    let sha = sha256(content);
+    std::fs::write(partial_path(tmp.path(), sha), &content[..10]).unwrap();
+
+    let mock = server.mock(|when, then| {
+        when.method(GET).path("/img");
+        then.status(200).body(content);
+    });
+
+    let (writer, reader) = file_stream().unwrap();
+    downloader
+        .download_to_stream(server.url("/img"), sha, writer)
+        .await
+        .expect("full download should succeed");
+
+    mock.assert_calls(2);
+    assert_eq!(read_stream(reader), content);
+    assert!(!partial_path(tmp.path(), sha).exists());
+    assert_eq!(
+        std::fs::read(tmp.path().join(const_hex::encode(sha))).unwrap(),
+        content
+    );
+}
+
+/// A stale partial file fails verification after resuming, so the whole file
+/// is downloaded again.
+#[tokio::test]
+async fn download_to_stream_falls_back_on_invalid_resume() {
+    let server = MockServer::start();
+    let tmp = TempDir::new().unwrap();
+    let downloader = Downloader::new(tmp.path()).unwrap();
+
+    let content = b"streamed payload bytes";
+    let sha = sha256(content);
+    std::fs::write(partial_path(tmp.path(), sha), b"stale data").unwrap();
+
+    let resume_mock = server.mock(|when, then| {
+        when.method(GET).path("/img").header("range", "bytes=10-");
+        then.status(206)
+            .header("content-range", format!("bytes 10-21/{}", content.len()))
+            .body(&content[10..]);
+    });
+    let full_mock = server.mock(|when, then| {
+        when.method(GET).path("/img").header_missing("range");
+        then.status(200).body(content);
+    });
+
+    let (writer, reader) = file_stream().unwrap();
+    downloader
+        .download_to_stream(server.url("/img"), sha, writer)
+        .await
+        .expect("fallback download should succeed");
+
+    resume_mock.assert_calls(1);
+    full_mock.assert_calls(1);
+    assert_eq!(read_stream(reader), content);
+    assert_eq!(
+        single_file(tmp.path()).unwrap(),
+        tmp.path().join(const_hex::encode(sha))
+    );
+}
@@ -1,6 +1,6 @@
 use std::io;
 
-use bb_downloader::Downloader;
+use bb_downloader::{DownloadProgress, Downloader};
 use httpmock::{Method::GET, MockServer};
 use tempfile::TempDir;
 
@@ -52,6 +52,45 @@ async fn test_download_and_cache_by_url() {
     download_mock.assert_calls(1);
 }
 
+#[tokio::test]
+async fn test_download_with_progress() {
+    let server = MockServer::start();
+    let tmp_dir = TempDir::new().unwrap();
+    let downloader = Downloader::new(tmp_dir.path()).unwrap();
+
+    let file_content = vec![b'a'; 256 * 1024];
+    let download_mock = server.mock(|when, then| {
+        when.method(GET).path("/file.bin");
+        then.status(200).body(&file_content);
+    });
+
+    let (tx, rx) = std::sync::mpsc::sync_channel(1024);
+    let path = downloader
+        .download_with_progress(server.url("/file.bin"), Some(tx))
+        .await
+        .unwrap();
+
+    download_mock.assert_calls(1);
+    assert_eq!(std::fs::read(&path).unwrap(), file_content);
+
+    let progress: Vec<_> = rx.try_iter().collect();
+    assert!(progress.is_sorted_by_key(|x| x.received));
+    assert_eq!(
+        progress.last(),
+        Some(&DownloadProgress {
+            received: file_content.len() as u64,
+            total: Some(file_content.len() as u64)
+        })
+    );
+
+    // No temporary files are left behind.
+    let parts = std::fs::read_dir(tmp_dir.path())
+        .unwrap()
+        .filter(|e| e.as_ref().unwrap().path().extension() == Some("part".as_ref()))
+        .count();
+    assert_eq!(parts, 0);
+}
+
 #[cfg(feature = "json")]
 #[tokio::test]
 async fn test_download_json_no_cache() {
@@ -87,11 +87,17 @@ impl PartitionTable {
     }
 }
 
+/// Edit applied to the contents of an existing file.
+pub type ModifyFn<'a> = Box<dyn FnOnce(Vec<u8>) -> std::io::Result<Vec<u8>> + 'a>;
+
 pub enum ContentType<'a> {
     Dir,
     Reader(Box<dyn Read + 'a>),
     File(Box<std::path::Path>),
     DataAppend(Box<[u8]>),
+    /// Rewrite an existing file with the output of the function. Skipped if the file does not
+    /// exist.
+    Modify(ModifyFn<'a>),
 }
 
 impl<'a> From<Box<[u8]>> for ContentType<'a> {
@@ -146,6 +152,24 @@ where
                     ContentType::Dir => {
                         root.create_dir(&path)?;
                     }
+                    ContentType::Modify(f) => {
+                        let mut file = match root.open_file(&path) {
+                            Ok(x) => x,
+                            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                                tracing::info!("Skipping {path} since it does not exist");
+                                continue;
+                            }
+                            Err(e) => return Err(customization_err(e)),
+                        };
+
+                        let mut data = Vec::new();
+                        file.read_to_end(&mut data)?;
+                        let data = f(data).map_err(customization_err)?;
+
+                        file.rewind()?;
+                        file.truncate()?;
+                        file.write_all(&data)?;
+                    }
                     ContentType::Reader(mut reader) => {
                         let mut dst = root.create_file(&path).map_err(customization_err)?;
                         dst.truncate()?;
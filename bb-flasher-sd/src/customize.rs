@@ -0,0 +1,228 @@
+//! Customize an already flashed SD card or image without writing the OS image again.
+
+use std::io::{Read, Seek, Write};
+
+use bb_helper::cancel::CancellationToken;
+
+use crate::helpers::{DeviceWrapper, Eject, check_cancel};
+use crate::{ContentType, Customization, ParitionType, Result};
+
+/// File path and content.
+pub type File = (Box<str>, Box<[u8]>);
+
+/// Apply customization to the destination.
+pub fn apply<'a, I>(
+    dst: crate::Destination,
+    customization: Customization<I>,
+    cancel: Option<CancellationToken>,
+) -> Result<()>
+where
+    I: Iterator<Item = (Box<str>, ContentType<'a>)>,
+{
+    tracing::info!("Opening Destination");
+
+    match dst {
+        crate::Destination::File(path) => {
+            let sd = open_file(&path)?;
+            apply_internal(sd, customization, cancel)
+        }
+        crate::Destination::SdCard(path) => {
+            let sd = crate::pal::open(&path)?;
+            apply_internal(sd, customization, cancel)
+        }
+    }
+}
+
+/// Read files from a partition of the destination.
+///
+/// A directory yields all files directly inside it as `{dir}/{name}`. Paths that do not exist are
+/// skipped.
+pub fn read<'p>(
+    dst: crate::Destination,
+    partition: ParitionType,
+    paths: impl IntoIterator<Item = &'p str>,
+) -> Result<Vec<File>> {
+    tracing::info!("Opening Destination");
+
+    match dst {
+        crate::Destination::File(path) => {
+            let sd = open_file(&path)?;
+            read_internal(sd, partition, paths)
+        }
+        crate::Destination::SdCard(path) => {
+            let sd = crate::pal::open(&path)?;
+            read_internal(sd, partition, paths)
+        }
+    }
+}
+
+fn open_file(path: &std::path::Path) -> std::io::Result<std::fs::File> {
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+}
+
+fn apply_internal<'a, I, S>(
+    sd: S,
+    customization: Customization<I>,
+    cancel: Option<CancellationToken>,
+) -> Result<()>
+where
+    S: Read + Write + Seek + std::fmt::Debug + Eject,
+    I: Iterator<Item = (Box<str>, ContentType<'a>)>,
+{
+    check_cancel(cancel.as_ref())?;
+
+    tracing::info!("Applying customization");
+    let mut sd = DeviceWrapper::new(sd)?;
+    customization.customize(&mut sd, cancel)?;
+    sd.flush()?;
+
+    tracing::info!("Ejecting SD Card");
+    let _ = sd.into_inner().eject();
+
+    Ok(())
+}
+
+fn read_internal<'p, S>(
+    sd: S,
+    partition: ParitionType,
+    paths: impl IntoIterator<Item = &'p str>,
+) -> Result<Vec<File>>
+where
+    S: Read + Write + Seek + std::fmt::Debug,
+{
+    let mut sd = DeviceWrapper::new(sd)?;
+    let partition = partition.open(&mut sd)?;
+    let root = partition.root_dir();
+    let mut ans = Vec::new();
+
+    for path in paths {
+        match root.open_file(path) {
+            Ok(mut f) => {
+                let mut data = Vec::new();
+                f.read_to_end(&mut data)?;
+                ans.push((path.into(), data.into()));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                tracing::info!("Skipping {path} since it does not exist");
+            }
+            Err(e) => {
+                let dir = root.open_dir(path).map_err(|_| e)?;
+                for entry in dir.iter() {
+                    let entry = entry?;
+                    if !entry.is_file() {
+                        continue;
+                    }
+
+                    let mut data = Vec::new();
+                    entry.to_file().read_to_end(&mut data)?;
+                    ans.push((
+                        format!("{}/{}", path.trim_end_matches('/'), entry.file_name()).into(),
+                        data.into(),
+                    ));
+                }
+            }
+        }
+    }
+
+    Ok(ans)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock_sd::MockSd;
+
+    use super::*;
+
+    fn dst(sd: &MockSd) -> crate::Destination {
+        crate::Destination::File(sd.path().into())
+    }
+
+    fn customization<'a>(
+        content: Vec<(&str, ContentType<'a>)>,
+    ) -> Customization<impl Iterator<Item = (Box<str>, ContentType<'a>)>> {
+        Customization {
+            partition: ParitionType::Boot,
+            content: content.into_iter().map(|(p, c)| (p.into(), c)),
+        }
+    }
+
+    #[test]
+    fn apply_and_read() {
+        let sd = MockSd::new();
+
+        apply(
+            dst(&sd),
+            customization(vec![
+                ("services", ContentType::Dir),
+                (
+                    "services/home.psk",
+                    ContentType::DataAppend((*b"psk").into()),
+                ),
+                (
+                    "sysconf.txt",
+                    ContentType::DataAppend((*b"hostname=a\n").into()),
+                ),
+            ]),
+            None,
+        )
+        .unwrap();
+
+        // Applying again edits the files already present.
+        apply(
+            dst(&sd),
+            customization(vec![(
+                "sysconf.txt",
+                ContentType::Modify(Box::new(|x| Ok([x, b"keymap=us\n".to_vec()].concat()))),
+            )]),
+            None,
+        )
+        .unwrap();
+
+        let files = read(
+            dst(&sd),
+            ParitionType::Boot,
+            ["sysconf.txt", "services", "missing.txt"],
+        )
+        .unwrap();
+        assert_eq!(
+            files,
+            [
+                ("sysconf.txt".into(), (*b"hostname=a\nkeymap=us\n").into()),
+                ("services/home.psk".into(), (*b"psk").into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn apply_cancelled() {
+        let cancel = CancellationToken::default();
+        drop(cancel.drop_guard());
+
+        let sd = MockSd::new();
+        let res = apply(
+            dst(&sd),
+            customization(vec![("a.txt", ContentType::Dir)]),
+            Some(cancel),
+        );
+
+        assert!(matches!(res.unwrap_err(), crate::Error::Aborted));
+        assert!(
+            read(dst(&sd), ParitionType::Boot, ["a.txt"])
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn missing_destination() {
+        let res = read(
+            crate::Destination::File(std::path::Path::new("/nonexistent/sd.img").into()),
+            ParitionType::Boot,
+            ["sysconf.txt"],
+        );
+        assert!(res.is_err());
+    }
+}
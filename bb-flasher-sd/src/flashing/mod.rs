@@ -28,16 +28,32 @@ fn reader_task(
         if count == 0 {
             break;
         }
-
-        buf_tx
-            .send((buf, count))
-            .map_err(|_| crate::Error::WriterClosed)?;
         check_cancel(cancel.as_ref())?;
+
+        // With bmap, the writer is done after the last mapped block. Errors of the writer are
+        // reported by the writer itself.
+        if buf_tx.send((buf, count)).is_err() {
+            break;
+        }
     }
 
     Ok(())
 }
 
+/// OS image written by [`flash`].
+pub trait Image: Read {
+    /// With bmap, writing stops after the last mapped block. Images which validate their input on
+    /// EOF (e.g. checksums) should return `true`, so that the rest of the image is read before
+    /// ejecting.
+    fn needs_full_read(&self) -> bool {
+        false
+    }
+}
+
+impl Image for std::fs::File {}
+
+impl<T: AsRef<[u8]>> Image for std::io::Cursor<T> {}
+
 /// While writing, a few assumptions should hold:
 /// - All writes should be in buffers multiple of block size (4K).
 /// - All writes should be aligned to block size (4K).
@@ -198,7 +214,7 @@ pub fn flash<'a, R, B, C>(
     cancel: Option<CancellationToken>,
 ) -> Result<()>
 where
-    R: Read + Send,
+    R: Image + Send,
     C: Iterator<Item = (Box<str>, crate::ContentType<'a>)> + Send,
     B: FnOnce() -> std::io::Result<Box<str>> + Send,
 {
@@ -231,7 +247,7 @@ fn flash_internal<'a, R, B, Sd, C>(
     cancel: Option<CancellationToken>,
 ) -> Result<()>
 where
-    R: Read + Send,
+    R: Image + Send,
     Sd: Read + Write + Seek + Eject + std::fmt::Debug,
     C: Iterator<Item = (Box<str>, crate::ContentType<'a>)> + Send,
     B: FnOnce() -> std::io::Result<Box<str>> + Send,
@@ -244,12 +260,20 @@ where
         None => None,
     };
     tracing::info!("Resolving Image");
-    let (img, img_size) = img()?;
+    let (mut img, img_size) = img()?;
+    let partial_read = bmap.is_some() && img.needs_full_read();
 
     chan_send(chan.as_mut(), 0.0);
 
     tracing::info!("Writing to SD Card");
-    write_sd(img, img_size, bmap, &mut sd, chan, cancel.clone())?;
+    write_sd(&mut img, img_size, bmap, &mut sd, chan, cancel.clone())?;
+
+    // With bmap, writing stops after the last mapped block. Read the rest of the image only if it
+    // is validated on EOF, since that means decompressing it.
+    if partial_read {
+        check_cancel(cancel.as_ref())?;
+        std::io::copy(&mut img, &mut std::io::sink())?;
+    }
 
     tracing::info!("Applying customization");
     let mut sd = crate::helpers::DeviceWrapper::new(sd).unwrap();
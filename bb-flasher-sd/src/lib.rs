@@ -1,6 +1,7 @@
 //! Library to flash SD cards with OS images. Powers sd card flashing in [BeagleBoard Imager].
 //!
-//! Also allows optional extra [Customization] for BeagleBoard images.
+//! Also allows optional extra [Customization] for BeagleBoard images, either while flashing or on
+//! an already flashed SD card using [`customize`].
 //!
 //! # Platform Support
 //!
@@ -24,14 +25,15 @@ use thiserror::Error;
 
 pub mod bootfs_update;
 pub(crate) mod customization;
+pub mod customize;
 mod flashing;
 mod helpers;
 #[cfg(any(feature = "mock_sd", test))]
 pub mod mock_sd;
 pub(crate) mod pal;
 
-pub use customization::{ContentType, Customization, ParitionType};
-pub use flashing::flash;
+pub use customization::{ContentType, Customization, ModifyFn, ParitionType};
+pub use flashing::{Image, flash};
 
 pub(crate) type Result<T, E = Error> = std::result::Result<T, E>;
 
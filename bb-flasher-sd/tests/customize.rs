@@ -6,7 +6,7 @@
 //! through the public API. This drives it by flashing a full (MBR + FAT32)
 //! image and asserting the injected file lands in the boot partition.
 
-use std::io::{Cursor, Read};
+use std::io::{Cursor, Read, Seek};
 
 use bb_flasher_sd::mock_sd::MockSd;
 use bb_flasher_sd::{ContentType, Customization, Destination, ParitionType};
@@ -59,3 +59,56 @@ fn flash_applies_customization_through_public_api() {
         .unwrap();
     assert_eq!(contents.as_bytes(), FILE_DATA);
 }
+
+#[test]
+fn flash_modifies_existing_file() {
+    const FILE_NAME: &str = "uEnv.txt";
+
+    let mut mock = MockSd::new();
+    {
+        let fs = mock.open_boot();
+        let mut f = fs.root_dir().create_file(FILE_NAME).unwrap();
+        std::io::Write::write_all(&mut f, b"enable_uboot_overlays=0\n").unwrap();
+    }
+    mock.rewind().unwrap();
+    let image_bytes: Box<[u8]> = std::fs::read(mock.path()).unwrap().into_boxed_slice();
+    let img_size = image_bytes.len() as u64;
+
+    let img_resolver = move || Ok((Cursor::new(image_bytes), img_size));
+    let bmap: Option<fn() -> std::io::Result<Box<str>>> = None;
+
+    // Files that do not exist are skipped instead of being created.
+    let content = [FILE_NAME, "missing.txt"].into_iter().map(|name| {
+        let f: bb_flasher_sd::ModifyFn = Box::new(|data: Vec<u8>| {
+            Ok(String::from_utf8(data)
+                .unwrap()
+                .replace("=0", "=1")
+                .into_bytes())
+        });
+        (Box::from(name), ContentType::Modify(f))
+    });
+    let customization = Customization {
+        partition: ParitionType::Boot,
+        content,
+    };
+
+    bb_flasher_sd::flash(
+        img_resolver,
+        bmap,
+        Destination::File(mock.path().into()),
+        None,
+        std::iter::once(customization),
+        None,
+    )
+    .expect("flash with customization should succeed");
+
+    let fs = mock.open_boot();
+    let mut contents = String::new();
+    fs.root_dir()
+        .open_file(FILE_NAME)
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    assert_eq!(contents, "enable_uboot_overlays=1\n");
+    assert!(fs.root_dir().open_file("missing.txt").is_err());
+}
@@ -114,3 +114,64 @@ fn destinations() {
     let temp = bb_flasher_sd::devices(false);
     assert!(temp.count() > 0);
 }
+
+/// Image which counts the bytes read from it.
+struct CountingImage {
+    inner: Cursor<Box<[u8]>>,
+    read: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    full_read: bool,
+}
+
+impl Read for CountingImage {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        self.read
+            .fetch_add(count, std::sync::atomic::Ordering::Relaxed);
+        Ok(count)
+    }
+}
+
+impl bb_flasher_sd::Image for CountingImage {
+    fn needs_full_read(&self) -> bool {
+        self.full_read
+    }
+}
+
+#[test]
+fn flash_bmap_reads_rest_only_when_needed() {
+    const FILE_LEN: usize = 1024 * 1024;
+    const BLOCK_LEN: u64 = 4096;
+
+    let mut bmap = bb_bmap_parser::Bmap::builder();
+    bmap.image_size(FILE_LEN as u64)
+        .block_size(BLOCK_LEN)
+        .blocks(FILE_LEN as u64 / BLOCK_LEN)
+        .mapped_blocks(1)
+        .checksum_type(bb_bmap_parser::HashType::Sha256)
+        .add_block_range(0, 0, bb_bmap_parser::HashValue::Sha256(Default::default()));
+    let bmap = bmap.build().unwrap().to_xml();
+
+    for full_read in [false, true] {
+        let temp_destination = NamedTempFile::new().expect("Failed to create temp file");
+        let read = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let img = CountingImage {
+            inner: test_file(FILE_LEN),
+            read: read.clone(),
+            full_read,
+        };
+        let bmap = bmap.clone();
+
+        bb_flasher_sd::flash(
+            move || Ok((img, FILE_LEN as u64)),
+            Some(move || Ok(bmap.into_boxed_str())),
+            Destination::File(temp_destination.path().into()),
+            None,
+            std::iter::empty::<Customization<std::iter::Empty<(Box<str>, ContentType)>>>(),
+            None,
+        )
+        .unwrap();
+
+        let read = read.load(std::sync::atomic::Ordering::Relaxed);
+        assert_eq!(read == FILE_LEN, full_read, "read {read} bytes");
+    }
+}
@@ -1,10 +1,15 @@
 //! Stuff common to all the flashers
 
-use std::{borrow::Cow, io::Read};
+use std::borrow::Cow;
 
-use thiserror::Error;
-
-#[derive(Error, Debug)]
+#[cfg(any(
+    feature = "bcf",
+    feature = "bcf_msp430",
+    feature = "pb2_mspm0",
+    feature = "mspm0_uart",
+    feature = "mspm0_i2c"
+))]
+#[derive(thiserror::Error, Debug)]
 pub(crate) enum FlasherError {
     #[error("Failed to fetch image.")]
     ImageResolvingError {
@@ -45,6 +50,13 @@ where
 }
 
 // Should only be used when image is expected to rather small and can fit in heap.
+#[cfg(any(
+    feature = "bcf",
+    feature = "bcf_msp430",
+    feature = "pb2_mspm0",
+    feature = "mspm0_uart",
+    feature = "mspm0_i2c"
+))]
 pub(crate) fn resolve_img(
     img: impl FnOnce() -> std::io::Result<(crate::img::OsImage, u64)>,
 ) -> Result<Vec<u8>, FlasherError> {
@@ -53,7 +65,7 @@ pub(crate) fn resolve_img(
     // If size > usize::MAX, this function should never have been called in the first place. So
     // panic is fine
     let mut data = Vec::with_capacity(usize::try_from(size).expect("Image size too big"));
-    img.read_to_end(&mut data)
+    std::io::Read::read_to_end(&mut img, &mut data)
         .map_err(|source| FlasherError::ImageResolvingError { source })?;
 
     Ok(data)
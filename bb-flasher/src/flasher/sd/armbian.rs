@@ -0,0 +1,189 @@
+//! Armbian first run configuration.
+//!
+//! Armbian sources `armbian_first_run.txt` from the boot partition on first boot
+//! (`armbian-firstrun-config`). The `FR_*` keys configure networking.
+//!
+//! The `PRESET_*` keys used to skip the interactive first login are read by `armbian-firstlogin`
+//! from `/root/.not_logged_in_yet` on the root filesystem, which cannot be written directly. Since
+//! `armbian_first_run.txt` is sourced before the first login, it appends them to that file.
+//!
+//! Armbian passes the user password to `chpasswd` as-is, so unlike other formats, it has to be
+//! written in plaintext. [`Password::Hashed`] is not supported. SSH keys are not supported either,
+//! since `PRESET_USER_KEY` is a URL to download the key from.
+
+use std::fmt::Write;
+
+use super::Password;
+
+pub(crate) const FILE_NAME: &str = "armbian_first_run.txt";
+/// Presets file read by `armbian-firstlogin`.
+const PRESET_FILE: &str = "/root/.not_logged_in_yet";
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ArmbianError {
+    #[error("Armbian requires a plaintext password")]
+    HashedPassword,
+}
+
+#[derive(Default)]
+pub(crate) struct ArmbianConfig {
+    hostname: Option<Box<str>>,
+    timezone: Option<Box<str>>,
+    locale: Option<Box<str>>,
+    user: Option<(Box<str>, Password)>,
+    wifi: Option<(Box<str>, Box<str>)>,
+}
+
+impl ArmbianConfig {
+    pub(crate) fn new(
+        hostname: Option<Box<str>>,
+        timezone: Option<Box<str>>,
+        locale: Option<Box<str>>,
+        user: Option<(Box<str>, Password)>,
+        wifi: Option<(Box<str>, Box<str>)>,
+    ) -> Result<Self, ArmbianError> {
+        if user.as_ref().is_some_and(|(_, p)| p.as_plain().is_none()) {
+            return Err(ArmbianError::HashedPassword);
+        }
+
+        Ok(Self {
+            hostname,
+            timezone,
+            locale,
+            user,
+            wifi,
+        })
+    }
+
+    pub(crate) fn to_file_data(&self) -> Box<[u8]> {
+        let mut temp = String::new();
+
+        armbian_w(
+            &mut temp,
+            "FR_general_delete_this_file_after_completion",
+            "1",
+        );
+
+        match &self.wifi {
+            Some((ssid, psk)) => {
+                armbian_w(&mut temp, "FR_net_change_defaults", "1");
+                armbian_w(&mut temp, "FR_net_ethernet_enabled", "1");
+                armbian_w(&mut temp, "FR_net_wifi_enabled", "1");
+                armbian_w(&mut temp, "FR_net_wifi_ssid", ssid);
+                armbian_w(&mut temp, "FR_net_wifi_key", psk);
+            }
+            None => armbian_w(&mut temp, "FR_net_change_defaults", "0"),
+        }
+
+        if let Some(h) = &self.hostname {
+            preset_w(&mut temp, "PRESET_HOSTNAME", h);
+        }
+        if let Some(tz) = &self.timezone {
+            preset_w(&mut temp, "PRESET_TIMEZONE", tz);
+        }
+        if let Some(l) = &self.locale {
+            preset_w(&mut temp, "PRESET_LOCALE", l);
+        }
+        if let Some((u, p)) = &self.user {
+            preset_w(&mut temp, "PRESET_USER_NAME", u);
+            if let Some(p) = p.as_plain() {
+                preset_w(&mut temp, "PRESET_USER_PASSWORD", p);
+            }
+        }
+
+        temp.into_bytes().into()
+    }
+}
+
+/// Write a `key='value'` line. The file is sourced by a shell script, so values are quoted.
+fn armbian_w(content: &mut String, key: &str, value: &str) {
+    writeln!(content, "{key}={}", quote(value)).unwrap();
+}
+
+/// Write a command appending a `key='value'` line to [`PRESET_FILE`], which is sourced as well.
+fn preset_w(content: &mut String, key: &str, value: &str) {
+    let line = format!("{key}={}", quote(value));
+    writeln!(content, "printf '%s\\n' {} >> {PRESET_FILE}", quote(&line)).unwrap();
+}
+
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn content(config: ArmbianConfig) -> String {
+        String::from_utf8(config.to_file_data().into()).unwrap()
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(
+            content(ArmbianConfig::default()),
+            "FR_general_delete_this_file_after_completion='1'\nFR_net_change_defaults='0'\n"
+        );
+    }
+
+    #[test]
+    fn wifi() {
+        let data = content(ArmbianConfig {
+            wifi: Some(("home".into(), "secret".into())),
+            ..Default::default()
+        });
+        let expected = "FR_general_delete_this_file_after_completion='1'
+FR_net_change_defaults='1'
+FR_net_ethernet_enabled='1'
+FR_net_wifi_enabled='1'
+FR_net_wifi_ssid='home'
+FR_net_wifi_key='secret'
+";
+
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn presets() {
+        let data = content(
+            ArmbianConfig::new(
+                Some("beagle".into()),
+                Some("Asia/Kolkata".into()),
+                Some("en_US.UTF-8".into()),
+                Some(("beagle".into(), Password::Plain("it's".into()))),
+                None,
+            )
+            .unwrap(),
+        );
+        let expected = r#"FR_general_delete_this_file_after_completion='1'
+FR_net_change_defaults='0'
+printf '%s\n' 'PRESET_HOSTNAME='\''beagle'\''' >> /root/.not_logged_in_yet
+printf '%s\n' 'PRESET_TIMEZONE='\''Asia/Kolkata'\''' >> /root/.not_logged_in_yet
+printf '%s\n' 'PRESET_LOCALE='\''en_US.UTF-8'\''' >> /root/.not_logged_in_yet
+printf '%s\n' 'PRESET_USER_NAME='\''beagle'\''' >> /root/.not_logged_in_yet
+printf '%s\n' 'PRESET_USER_PASSWORD='\''it'\''\'\'''\''s'\''' >> /root/.not_logged_in_yet
+"#;
+
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn hashed_password() {
+        let user = Some(("beagle".into(), Password::Hashed("$6$salt$hash".into())));
+        assert!(matches!(
+            ArmbianConfig::new(None, None, None, user, None),
+            Err(ArmbianError::HashedPassword)
+        ));
+    }
+
+    #[test]
+    fn quoting() {
+        let data = content(ArmbianConfig {
+            wifi: Some(("Bob's wifi".into(), "a'b".into())),
+            ..Default::default()
+        });
+
+        assert!(data.contains(r"FR_net_wifi_ssid='Bob'\''s wifi'"));
+        assert!(data.contains(r"FR_net_wifi_key='a'\''b'"));
+    }
+}
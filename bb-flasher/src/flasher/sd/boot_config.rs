@@ -0,0 +1,494 @@
+//! Structured edits of the boot configuration present in BOOT partition of [BeagleBoard.org]
+//! images.
+//!
+//! Older images use `uEnv.txt` while newer images use `extlinux/extlinux.conf`. Edits are applied
+//! to whichever of them is present, and applying the same edit again does not change the file.
+//!
+//! [BeagleBoard.org]: https://www.beagleboard.org/
+
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+pub(crate) const UENV: &str = "uEnv.txt";
+pub(crate) const EXTLINUX: &str = "extlinux/extlinux.conf";
+
+/// `uboot_overlay_addrN` slots in the order in which they are filled. Slots 4-7 are meant for
+/// custom overlays, while 0-3 are used by cape overrides.
+const UENV_OVERLAY_SLOTS: [u8; 8] = [4, 5, 6, 7, 0, 1, 2, 3];
+/// Directory containing overlays in BOOT partition of extlinux based images.
+const EXTLINUX_OVERLAY_DIR: &str = "/overlays/";
+
+/// Edits to boot configuration (`uEnv.txt` or `extlinux/extlinux.conf`).
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(default)]
+pub struct BootConfig {
+    /// Device tree overlays to enable, e.g. `BB-UART1-00A0.dtbo`.
+    ///
+    /// For extlinux, overlays without a directory are assumed to be in `/overlays/`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub add_overlays: Vec<Box<str>>,
+    /// Device tree overlays to disable. Matched by file name.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub remove_overlays: Vec<Box<str>>,
+    /// Kernel command line arguments. Arguments replace existing ones with the same key, e.g.
+    /// `console=ttyS2,115200` replaces any existing `console=...`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub cmdline: Vec<Box<str>>,
+    /// extlinux label to boot by default. Other edits only apply to this label.
+    ///
+    /// Applying fails if the label is not present, or if the image uses `uEnv.txt`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_label: Option<Box<str>>,
+}
+
+impl BootConfig {
+    pub fn is_empty(&self) -> bool {
+        self.add_overlays.is_empty()
+            && self.remove_overlays.is_empty()
+            && self.cmdline.is_empty()
+            && self.default_label.is_none()
+    }
+
+    /// Apply edits to the boot configuration file at `path` in BOOT partition.
+    pub(crate) fn apply(&self, path: &str, data: Vec<u8>) -> io::Result<Vec<u8>> {
+        let content = String::from_utf8(data).map_err(io::Error::other)?;
+
+        let res = match path {
+            UENV => self.edit_uenv(&content)?,
+            EXTLINUX => self.edit_extlinux(&content)?,
+            _ => unreachable!("Unknown boot config {path}"),
+        };
+
+        Ok(res.into_bytes())
+    }
+
+    fn edit_uenv(&self, content: &str) -> io::Result<String> {
+        if self.default_label.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Default label is only supported by extlinux.conf",
+            ));
+        }
+
+        let mut lines: Vec<String> = content.lines().map(ToString::to_string).collect();
+
+        for overlay in &self.remove_overlays {
+            for line in lines.iter_mut() {
+                if let Some((_, val)) = uenv_overlay(line)
+                    && same_overlay(val, overlay)
+                {
+                    *line = format!("#{line}");
+                }
+            }
+        }
+
+        for overlay in &self.add_overlays {
+            let active: Vec<(u8, &str)> = lines.iter().filter_map(|x| uenv_overlay(x)).collect();
+            if active.iter().any(|(_, x)| same_overlay(x, overlay)) {
+                continue;
+            }
+
+            let slot = UENV_OVERLAY_SLOTS
+                .into_iter()
+                .find(|x| active.iter().all(|(y, _)| x != y))
+                .ok_or_else(|| {
+                    io::Error::other(format!("No free uboot_overlay_addr slot for {overlay}"))
+                })?;
+
+            uenv_set(&mut lines, &format!("uboot_overlay_addr{slot}"), overlay);
+        }
+
+        if !self.add_overlays.is_empty() {
+            uenv_set(&mut lines, "enable_uboot_overlays", "1");
+        }
+
+        if !self.cmdline.is_empty() {
+            let cmdline = lines
+                .iter()
+                .find_map(|x| x.strip_prefix("cmdline="))
+                .unwrap_or_default();
+            let cmdline = merge_cmdline(cmdline, &self.cmdline);
+            uenv_set(&mut lines, "cmdline", &cmdline);
+        }
+
+        Ok(join_lines(lines, content))
+    }
+
+    fn edit_extlinux(&self, content: &str) -> io::Result<String> {
+        let mut lines: Vec<String> = content.lines().map(ToString::to_string).collect();
+
+        if let Some(label) = &self.default_label {
+            if !lines
+                .iter()
+                .any(|x| extlinux_key(x, "label") == Some(label.as_ref()))
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Label {label:?} not found in extlinux.conf"),
+                ));
+            }
+
+            let line = format!("default {label}");
+            match lines
+                .iter()
+                .position(|x| extlinux_key(x, "default").is_some())
+            {
+                Some(pos) => lines[pos] = line,
+                None => lines.insert(0, line),
+            }
+        }
+
+        let default = lines
+            .iter()
+            .find_map(|x| extlinux_key(x, "default"))
+            .map(str::to_string);
+        let labels: Vec<usize> = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| extlinux_key(x, "label").is_some())
+            .map(|(i, _)| i)
+            .collect();
+        let has_default = labels
+            .iter()
+            .any(|i| extlinux_key(&lines[*i], "label") == default.as_deref());
+
+        // Go in reverse so that inserting lines does not shift the labels yet to be processed.
+        for (idx, start) in labels.iter().enumerate().rev() {
+            let label = extlinux_key(&lines[*start], "label");
+            if has_default && label != default.as_deref() {
+                continue;
+            }
+
+            let end = labels.get(idx + 1).copied().unwrap_or(lines.len());
+            self.edit_extlinux_label(&mut lines, *start, end);
+        }
+
+        Ok(join_lines(lines, content))
+    }
+
+    /// Edit label present in `lines[start..end]`.
+    fn edit_extlinux_label(&self, lines: &mut Vec<String>, start: usize, mut end: usize) {
+        let indent = lines[(start + 1)..end]
+            .iter()
+            .find(|x| !x.trim().is_empty())
+            .map(|x| x[..(x.len() - x.trim_start().len())].to_string())
+            .unwrap_or_else(|| "    ".to_string());
+        // `end` changes as lines are inserted or removed.
+        let find = |lines: &[String], end: usize, key| {
+            (start..end).find(|i| extlinux_key(&lines[*i], key).is_some())
+        };
+
+        if !self.add_overlays.is_empty() || !self.remove_overlays.is_empty() {
+            let mut overlays: Vec<String> = find(lines, end, "fdtoverlays")
+                .and_then(|i| extlinux_key(&lines[i], "fdtoverlays"))
+                .unwrap_or_default()
+                .split_whitespace()
+                .filter(|x| !self.remove_overlays.iter().any(|y| same_overlay(x, y)))
+                .map(ToString::to_string)
+                .collect();
+
+            for overlay in &self.add_overlays {
+                if !overlays.iter().any(|x| same_overlay(x, overlay)) {
+                    overlays.push(if overlay.contains('/') {
+                        overlay.to_string()
+                    } else {
+                        format!("{EXTLINUX_OVERLAY_DIR}{overlay}")
+                    });
+                }
+            }
+
+            let line = format!("{indent}fdtoverlays {}", overlays.join(" "));
+            match (find(lines, end, "fdtoverlays"), overlays.is_empty()) {
+                (Some(i), true) => {
+                    lines.remove(i);
+                    end -= 1;
+                }
+                (Some(i), false) => lines[i] = line,
+                (None, true) => {}
+                (None, false) => {
+                    // Keep overlays next to the base devicetree.
+                    let pos = find(lines, end, "fdt")
+                        .or(find(lines, end, "fdtdir"))
+                        .unwrap_or(start);
+                    lines.insert(pos + 1, line);
+                    end += 1;
+                }
+            }
+        }
+
+        if !self.cmdline.is_empty() {
+            match find(lines, end, "append") {
+                Some(i) => {
+                    let cmdline = extlinux_key(&lines[i], "append").unwrap_or_default();
+                    lines[i] = format!("{indent}append {}", merge_cmdline(cmdline, &self.cmdline));
+                }
+                None => {
+                    let cmdline = merge_cmdline("", &self.cmdline);
+                    lines.insert(start + 1, format!("{indent}append {cmdline}"));
+                }
+            }
+        }
+    }
+}
+
+/// Parse an active `uboot_overlay_addrN=...` line.
+fn uenv_overlay(line: &str) -> Option<(u8, &str)> {
+    let (key, val) = line.trim().split_once('=')?;
+    let slot = key.strip_prefix("uboot_overlay_addr")?.parse().ok()?;
+    let val = val.trim();
+
+    (!val.is_empty()).then_some((slot, val))
+}
+
+/// Set `key=val` in uEnv.txt. Replaces existing line, enables a commented out line or appends.
+fn uenv_set(lines: &mut Vec<String>, key: &str, val: &str) {
+    let line = format!("{key}={val}");
+    let prefix = format!("{key}=");
+
+    if let Some(x) = lines.iter_mut().find(|x| x.trim().starts_with(&prefix)) {
+        *x = line;
+    } else if let Some(x) = lines
+        .iter_mut()
+        .find(|x| x.trim().trim_start_matches('#').trim().starts_with(&prefix))
+    {
+        *x = line;
+    } else {
+        lines.push(line);
+    }
+}
+
+/// Value of `key` in an extlinux.conf line. Keys are case-insensitive.
+fn extlinux_key<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let line = line.trim();
+    let (k, v) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    k.eq_ignore_ascii_case(key).then(|| v.trim())
+}
+
+fn overlay_name(x: &str) -> &str {
+    x.rsplit('/').next().unwrap_or(x)
+}
+
+fn same_overlay(a: &str, b: &str) -> bool {
+    overlay_name(a) == overlay_name(b)
+}
+
+fn merge_cmdline(cmdline: &str, args: &[Box<str>]) -> String {
+    fn key(x: &str) -> &str {
+        x.split_once('=').map(|x| x.0).unwrap_or(x)
+    }
+
+    let mut res: Vec<&str> = cmdline.split_whitespace().collect();
+
+    for arg in args {
+        match res.iter_mut().find(|x| key(x) == key(arg)) {
+            Some(x) => *x = arg,
+            None => res.push(arg),
+        }
+    }
+
+    res.join(" ")
+}
+
+/// Join lines while preserving the trailing newline of the original file.
+fn join_lines(lines: Vec<String>, original: &str) -> String {
+    let mut res = lines.join("\n");
+    if original.is_empty() || original.ends_with('\n') {
+        res.push('\n');
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UENV_DATA: &str = "\
+#Docs: http://elinux.org/Beagleboard:U-boot_partitioning_layout_2.0
+
+uname_r=5.10.168-ti-r71
+enable_uboot_overlays=1
+###Additional custom capes
+uboot_overlay_addr4=BB-UART1-00A0.dtbo
+#uboot_overlay_addr5=<file5>.dtbo
+#uboot_overlay_addr6=<file6>.dtbo
+#uboot_overlay_addr7=<file7>.dtbo
+cmdline=coherent_pool=1M net.ifnames=0 quiet
+";
+
+    const EXTLINUX_DATA: &str = "\
+label Linux microSD
+    kernel /Image
+    append root=/dev/mmcblk1p3 ro rootfstype=ext4 rootwait net.ifnames=0 quiet
+    fdtdir /
+    fdt /ti/k3-am625-beagleplay.dtb
+    initrd /initrd.img
+
+label Linux eMMC
+    kernel /Image
+    append root=/dev/mmcblk0p2 ro rootfstype=ext4 rootwait quiet
+    fdtdir /
+    fdtoverlays /overlays/k3-am625-beagleplay-csi2-ov5640.dtbo
+";
+
+    fn cfg() -> BootConfig {
+        BootConfig {
+            add_overlays: vec!["BB-I2C2-RTC-DS3231.dtbo".into()],
+            remove_overlays: vec!["BB-UART1-00A0.dtbo".into()],
+            cmdline: vec!["quiet".into(), "console=ttyS2,115200n8".into()],
+            default_label: None,
+        }
+    }
+
+    #[test]
+    fn uenv() {
+        let res = cfg().edit_uenv(UENV_DATA).unwrap();
+        let expected = "\
+#Docs: http://elinux.org/Beagleboard:U-boot_partitioning_layout_2.0
+
+uname_r=5.10.168-ti-r71
+enable_uboot_overlays=1
+###Additional custom capes
+uboot_overlay_addr4=BB-I2C2-RTC-DS3231.dtbo
+#uboot_overlay_addr5=<file5>.dtbo
+#uboot_overlay_addr6=<file6>.dtbo
+#uboot_overlay_addr7=<file7>.dtbo
+cmdline=coherent_pool=1M net.ifnames=0 quiet console=ttyS2,115200n8
+";
+
+        assert_eq!(res, expected);
+        assert_eq!(cfg().edit_uenv(&res).unwrap(), expected);
+    }
+
+    #[test]
+    fn uenv_no_free_slot() {
+        let content: String = (0..8)
+            .map(|x| format!("uboot_overlay_addr{x}=overlay{x}.dtbo\n"))
+            .collect();
+        assert!(cfg().edit_uenv(&content).is_err());
+    }
+
+    #[test]
+    fn extlinux_all_labels() {
+        let res = cfg().edit_extlinux(EXTLINUX_DATA).unwrap();
+        let expected = "\
+label Linux microSD
+    kernel /Image
+    append root=/dev/mmcblk1p3 ro rootfstype=ext4 rootwait net.ifnames=0 quiet console=ttyS2,115200n8
+    fdtdir /
+    fdt /ti/k3-am625-beagleplay.dtb
+    fdtoverlays /overlays/BB-I2C2-RTC-DS3231.dtbo
+    initrd /initrd.img
+
+label Linux eMMC
+    kernel /Image
+    append root=/dev/mmcblk0p2 ro rootfstype=ext4 rootwait quiet console=ttyS2,115200n8
+    fdtdir /
+    fdtoverlays /overlays/k3-am625-beagleplay-csi2-ov5640.dtbo /overlays/BB-I2C2-RTC-DS3231.dtbo
+";
+
+        assert_eq!(res, expected);
+        assert_eq!(cfg().edit_extlinux(&res).unwrap(), expected);
+    }
+
+    #[test]
+    fn extlinux_default_label() {
+        let cfg = BootConfig {
+            remove_overlays: vec!["k3-am625-beagleplay-csi2-ov5640.dtbo".into()],
+            cmdline: vec!["quiet".into()],
+            default_label: Some("Linux eMMC".into()),
+            ..Default::default()
+        };
+        let res = cfg.edit_extlinux(EXTLINUX_DATA).unwrap();
+        let expected = "\
+default Linux eMMC
+label Linux microSD
+    kernel /Image
+    append root=/dev/mmcblk1p3 ro rootfstype=ext4 rootwait net.ifnames=0 quiet
+    fdtdir /
+    fdt /ti/k3-am625-beagleplay.dtb
+    initrd /initrd.img
+
+label Linux eMMC
+    kernel /Image
+    append root=/dev/mmcblk0p2 ro rootfstype=ext4 rootwait quiet
+    fdtdir /
+";
+
+        assert_eq!(res, expected);
+        assert_eq!(cfg.edit_extlinux(&res).unwrap(), expected);
+    }
+
+    #[test]
+    fn default_label_invalid() {
+        let cfg = BootConfig {
+            default_label: Some("Missing".into()),
+            ..Default::default()
+        };
+
+        assert!(cfg.edit_extlinux(EXTLINUX_DATA).is_err());
+        // uEnv.txt has no labels.
+        assert!(cfg.edit_uenv(UENV_DATA).is_err());
+    }
+
+    #[test]
+    fn extlinux_append_last() {
+        let content = "\
+label Linux microSD
+    kernel /Image
+    fdtdir /
+    append root=/dev/mmcblk1p3 ro
+label Linux eMMC
+    kernel /Image
+    fdtdir /
+    fdtoverlays /overlays/BB-UART1-00A0.dtbo
+label Recovery
+    kernel /Image
+    append root=/dev/mmcblk0p3 ro
+";
+        let res = cfg().edit_extlinux(content).unwrap();
+        let expected = "\
+label Linux microSD
+    kernel /Image
+    fdtdir /
+    fdtoverlays /overlays/BB-I2C2-RTC-DS3231.dtbo
+    append root=/dev/mmcblk1p3 ro quiet console=ttyS2,115200n8
+label Linux eMMC
+    append quiet console=ttyS2,115200n8
+    kernel /Image
+    fdtdir /
+    fdtoverlays /overlays/BB-I2C2-RTC-DS3231.dtbo
+label Recovery
+    fdtoverlays /overlays/BB-I2C2-RTC-DS3231.dtbo
+    kernel /Image
+    append root=/dev/mmcblk0p3 ro quiet console=ttyS2,115200n8
+";
+
+        assert_eq!(res, expected);
+        assert_eq!(cfg().edit_extlinux(&res).unwrap(), expected);
+
+        // Removing the only overlay must not reach into the next label.
+        let cfg = BootConfig {
+            remove_overlays: vec!["BB-UART1-00A0.dtbo".into()],
+            cmdline: vec!["quiet".into()],
+            ..Default::default()
+        };
+        let res = cfg.edit_extlinux(content).unwrap();
+        let expected = "\
+label Linux microSD
+    kernel /Image
+    fdtdir /
+    append root=/dev/mmcblk1p3 ro quiet
+label Linux eMMC
+    append quiet
+    kernel /Image
+    fdtdir /
+label Recovery
+    kernel /Image
+    append root=/dev/mmcblk0p3 ro quiet
+";
+
+        assert_eq!(res, expected);
+        assert_eq!(cfg.edit_extlinux(&res).unwrap(), expected);
+    }
+}
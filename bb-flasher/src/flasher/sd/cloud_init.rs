@@ -1,203 +1,586 @@
-use std::collections::HashMap;
+//! cloud-init [NoCloud] seed.
+//!
+//! The seed consists of `user-data`, `meta-data` and (optionally) `network-config` files placed
+//! in the boot partition.
+//!
+//! [NoCloud]: https://cloudinit.readthedocs.io/en/latest/reference/datasources/nocloud.html
 
-use serde::Serialize;
+use std::collections::BTreeMap;
 
-#[derive(Serialize, Default)]
-pub(crate) struct CloudInitConfig {
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    users: Vec<User>,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    ssh_authorized_keys: Vec<Box<str>>,
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use yaml_serde::Value;
+
+use super::Password;
+
+pub(crate) const USER_DATA: &str = "user-data";
+pub(crate) const META_DATA: &str = "meta-data";
+pub(crate) const NETWORK_CONFIG: &str = "network-config";
+
+/// File name and content
+type SeedFile = (&'static str, Box<[u8]>);
+
+/// Wi-Fi interface used by [`FlashingSdLinuxConfig::cloud_init`].
+///
+/// [`FlashingSdLinuxConfig::cloud_init`]: super::FlashingSdLinuxConfig::cloud_init
+pub const DEFAULT_WIFI_INTERFACE: &str = "wlo1";
+
+/// cloud-init configuration.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CloudInit {
+    pub hostname: Option<Box<str>>,
+    pub timezone: Option<Box<str>>,
+    pub keymap: Option<Box<str>>,
+    /// Username and password
+    pub user: Option<(Box<str>, Password)>,
+    pub ssh_authorized_keys: Vec<Box<str>>,
+    /// Packages to install on first boot
+    pub packages: Vec<Box<str>>,
+    /// Commands to run early on every boot, before networking is configured
+    pub bootcmd: Vec<Box<str>>,
+    /// Commands to run on first boot
+    pub runcmd: Vec<Box<str>>,
+    pub write_files: Vec<WriteFile>,
+    pub network: Network,
+    /// Additional `#cloud-config` documents merged into the generated `user-data`.
+    ///
+    /// Mappings are merged recursively, lists are appended and other values are replaced by the
+    /// fragment. Fragments are applied in order.
+    pub fragments: Vec<Box<str>>,
+}
+
+/// File to create on first boot.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct WriteFile {
+    pub path: Box<str>,
+    pub content: Box<str>,
+    /// Octal permissions (e.g. "0644")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<Box<str>>,
+    /// Owner in `user:group` format
     #[serde(skip_serializing_if = "Option::is_none")]
-    keyboard: Option<Keyboard>,
+    pub owner: Option<Box<str>>,
+}
+
+/// Network configuration (`network-config`).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Network {
+    pub ethernets: Vec<Ethernet>,
+    pub wifis: Vec<Wifi>,
+}
+
+impl Network {
+    pub fn is_empty(&self) -> bool {
+        self.ethernets.is_empty() && self.wifis.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Ethernet {
+    /// Interface name (e.g. "eth0")
+    pub interface: Box<str>,
+    pub ip: IpConfig,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Wifi {
+    /// Interface name (e.g. "wlan0")
+    pub interface: Box<str>,
+    pub ip: IpConfig,
+    /// SSID and password of networks to connect to
+    pub access_points: Vec<(Box<str>, Box<str>)>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum IpConfig {
+    #[default]
+    Dhcp,
+    Static(StaticIp),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(default)]
+pub struct StaticIp {
+    /// Addresses in CIDR notation (e.g. "192.168.1.10/24" or "fd00::10/64")
+    pub addresses: Vec<Box<str>>,
+    pub gateway4: Option<Box<str>>,
+    pub gateway6: Option<Box<str>>,
+    pub nameservers: Vec<Box<str>>,
+}
+
+/// Fragment is not a valid `#cloud-config` document.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("Invalid cloud-config fragment {index}: {reason}")]
+pub struct FragmentError {
+    /// Index of the fragment in [`CloudInit::fragments`]
+    pub index: usize,
+    pub reason: Box<str>,
+}
+
+impl CloudInit {
+    /// Generate the seed files.
+    pub(crate) fn to_seed(&self) -> Result<Vec<SeedFile>, FragmentError> {
+        let user_data = self.user_data()?;
+        let network_config = if self.network.is_empty() {
+            None
+        } else {
+            Some(yaml_serde::to_string(&NetworkConfig::new(&self.network)).unwrap())
+        };
+
+        // cloud-init only runs per-instance modules once per instance-id, so derive it from the
+        // content to get them applied again when flashing a different config.
+        let mut hasher = Sha256::new();
+        hasher.update(&user_data);
+        if let Some(x) = &network_config {
+            hasher.update(x);
+        }
+        let meta_data = MetaData {
+            instance_id: format!(
+                "iid-bb-imager-{}",
+                const_hex::encode(&hasher.finalize()[..8])
+            ),
+            local_hostname: self.hostname.as_deref(),
+        };
+
+        let mut ans = vec![
+            (USER_DATA, user_data.into_bytes().into()),
+            (
+                META_DATA,
+                yaml_serde::to_string(&meta_data)
+                    .unwrap()
+                    .into_bytes()
+                    .into(),
+            ),
+        ];
+        if let Some(x) = network_config {
+            ans.push((NETWORK_CONFIG, x.into_bytes().into()));
+        }
+
+        Ok(ans)
+    }
+
+    fn user_data(&self) -> Result<String, FragmentError> {
+        let mut data = yaml_serde::to_value(UserData::new(self)).unwrap();
+
+        for (index, fragment) in self.fragments.iter().enumerate() {
+            let fragment: Value = yaml_serde::from_str(fragment).map_err(|e| FragmentError {
+                index,
+                reason: e.to_string().into(),
+            })?;
+
+            match fragment {
+                Value::Null => {}
+                Value::Mapping(_) => merge(&mut data, fragment),
+                _ => {
+                    return Err(FragmentError {
+                        index,
+                        reason: "Top level should be a mapping".into(),
+                    });
+                }
+            }
+        }
+
+        let mut temp = String::new();
+        temp.push_str("#cloud-config\n");
+        temp.push_str(&yaml_serde::to_string(&data).unwrap());
+
+        Ok(temp)
+    }
+}
+
+fn merge(base: &mut Value, fragment: Value) {
+    match (base, fragment) {
+        (Value::Mapping(base), Value::Mapping(fragment)) => {
+            for (k, v) in fragment {
+                match base.get_mut(&k) {
+                    Some(x) => merge(x, v),
+                    None => {
+                        base.insert(k, v);
+                    }
+                }
+            }
+        }
+        (Value::Sequence(base), Value::Sequence(fragment)) => base.extend(fragment),
+        (base, fragment) => *base = fragment,
+    }
+}
+
+#[derive(Serialize)]
+struct UserData<'a> {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    users: Vec<User<'a>>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    ssh_authorized_keys: &'a [Box<str>],
     #[serde(skip_serializing_if = "Option::is_none")]
-    timezone: Option<Box<str>>,
+    keyboard: Option<Keyboard<'a>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    hostname: Option<Box<str>>,
+    timezone: Option<&'a str>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    network: Option<Network>,
-}
-
-impl CloudInitConfig {
-    pub(crate) fn new(
-        hostname: Option<Box<str>>,
-        timezone: Option<Box<str>>,
-        keymap: Option<Box<str>>,
-        user: Option<(Box<str>, Box<str>)>,
-        wifi: Option<(Box<str>, Box<str>)>,
-        ssh: Option<Box<str>>,
-    ) -> Self {
+    hostname: Option<&'a str>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    packages: &'a [Box<str>],
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    bootcmd: &'a [Box<str>],
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    runcmd: &'a [Box<str>],
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    write_files: &'a [WriteFile],
+}
+
+impl<'a> UserData<'a> {
+    fn new(config: &'a CloudInit) -> Self {
         Self {
-            users: user
-                .map(|(name, plain_text_passwd)| {
-                    vec![User {
-                        name,
-                        plain_text_passwd,
-                    }]
+            users: config
+                .user
+                .iter()
+                .map(|(name, password)| User {
+                    name,
+                    hashed_passwd: password.to_hash(),
                 })
-                .unwrap_or_default(),
-            ssh_authorized_keys: ssh.map(|x| vec![x]).unwrap_or_default(),
-            keyboard: keymap.map(|x| Keyboard { layout: x }),
-            timezone,
-            hostname,
-            network: wifi.map(|(ssid, password)| Network {
-                version: 2,
-                renderer: "NetworkManager",
-                wifis: HashMap::from([(
-                    "wlo1",
-                    WifiInterface {
-                        access_points: HashMap::from([(ssid, AccessPoint { password })]),
-                    },
-                )]),
-            }),
+                .collect(),
+            ssh_authorized_keys: &config.ssh_authorized_keys,
+            keyboard: config.keymap.as_deref().map(|layout| Keyboard { layout }),
+            timezone: config.timezone.as_deref(),
+            hostname: config.hostname.as_deref(),
+            packages: &config.packages,
+            bootcmd: &config.bootcmd,
+            runcmd: &config.runcmd,
+            write_files: &config.write_files,
         }
     }
+}
 
-    pub(crate) fn to_file_data(&self) -> Box<[u8]> {
-        let mut temp = String::new();
+#[derive(Serialize)]
+struct User<'a> {
+    name: &'a str,
+    hashed_passwd: Box<str>,
+}
 
-        temp.push_str("#cloud-config\n");
-        temp.push_str(&yaml_serde::to_string(self).unwrap());
+#[derive(Serialize)]
+struct Keyboard<'a> {
+    layout: &'a str,
+}
 
-        temp.into_bytes().into()
-    }
+#[derive(Serialize)]
+struct MetaData<'a> {
+    #[serde(rename = "instance-id")]
+    instance_id: String,
+    #[serde(rename = "local-hostname", skip_serializing_if = "Option::is_none")]
+    local_hostname: Option<&'a str>,
 }
 
 #[derive(Serialize)]
-struct Network {
+struct NetworkConfig<'a> {
     version: u8,
-    renderer: &'static str,
-    wifis: HashMap<&'static str, WifiInterface>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    ethernets: BTreeMap<&'a str, Interface<'a>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    wifis: BTreeMap<&'a str, WifiInterface<'a>>,
 }
 
-#[derive(Debug, Serialize)]
-struct WifiInterface {
-    #[serde(rename = "access-points")]
-    access_points: HashMap<Box<str>, AccessPoint>,
+impl<'a> NetworkConfig<'a> {
+    fn new(network: &'a Network) -> Self {
+        Self {
+            version: 2,
+            ethernets: network
+                .ethernets
+                .iter()
+                .map(|x| (x.interface.as_ref(), Interface::new(&x.ip)))
+                .collect(),
+            wifis: network
+                .wifis
+                .iter()
+                .map(|x| {
+                    (
+                        x.interface.as_ref(),
+                        WifiInterface {
+                            iface: Interface::new(&x.ip),
+                            access_points: x
+                                .access_points
+                                .iter()
+                                .map(|(ssid, password)| (ssid.as_ref(), AccessPoint { password }))
+                                .collect(),
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
 }
 
-#[derive(Debug, Serialize)]
-struct AccessPoint {
-    password: Box<str>,
+#[derive(Serialize, Default)]
+struct Interface<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dhcp4: Option<bool>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    addresses: &'a [Box<str>],
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    routes: Vec<Route<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nameservers: Option<Nameservers<'a>>,
+}
+
+impl<'a> Interface<'a> {
+    fn new(ip: &'a IpConfig) -> Self {
+        match ip {
+            IpConfig::Dhcp => Self {
+                dhcp4: Some(true),
+                ..Default::default()
+            },
+            IpConfig::Static(x) => Self {
+                dhcp4: None,
+                addresses: &x.addresses,
+                routes: x
+                    .gateway4
+                    .iter()
+                    .map(|via| Route { to: "default", via })
+                    .chain(x.gateway6.iter().map(|via| Route { to: "::/0", via }))
+                    .collect(),
+                nameservers: (!x.nameservers.is_empty()).then_some(Nameservers {
+                    addresses: &x.nameservers,
+                }),
+            },
+        }
+    }
 }
 
 #[derive(Serialize)]
-struct User {
-    name: Box<str>,
-    plain_text_passwd: Box<str>,
+struct Route<'a> {
+    to: &'static str,
+    via: &'a str,
 }
 
 #[derive(Serialize)]
-struct Keyboard {
-    layout: Box<str>,
+struct Nameservers<'a> {
+    addresses: &'a [Box<str>],
+}
+
+#[derive(Serialize)]
+struct WifiInterface<'a> {
+    #[serde(flatten)]
+    iface: Interface<'a>,
+    #[serde(rename = "access-points")]
+    access_points: BTreeMap<&'a str, AccessPoint<'a>>,
+}
+
+#[derive(Serialize)]
+struct AccessPoint<'a> {
+    password: &'a str,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn user_data(config: &CloudInit) -> String {
+        config
+            .user_data()
+            .unwrap()
+            .strip_prefix("#cloud-config\n")
+            .unwrap()
+            .to_string()
+    }
+
     #[test]
     fn user() {
-        let data = CloudInitConfig {
-            users: vec![User {
-                name: "beagle".into(),
-                plain_text_passwd: "password".into(),
-            }],
+        let data = CloudInit {
+            user: Some(("beagle".into(), Password::Hashed("$6$salt$hash".into()))),
             ..Default::default()
         };
         let expected = r#"
 users:
 - name: beagle
-  plain_text_passwd: password"#;
+  hashed_passwd: $6$salt$hash"#;
 
-        assert_eq!(
-            yaml_serde::to_string(&data).unwrap().trim(),
-            expected.trim()
-        );
+        assert_eq!(user_data(&data).trim(), expected.trim());
     }
 
     #[test]
     fn keyboard() {
-        let data = CloudInitConfig {
-            keyboard: Some(Keyboard {
-                layout: "us".into(),
-            }),
+        let data = CloudInit {
+            keymap: Some("us".into()),
             ..Default::default()
         };
         let expected = r#"
 keyboard:
   layout: us"#;
 
-        assert_eq!(
-            yaml_serde::to_string(&data).unwrap().trim(),
-            expected.trim()
-        );
+        assert_eq!(user_data(&data).trim(), expected.trim());
     }
 
     #[test]
     fn timezone() {
-        let data = CloudInitConfig {
+        let data = CloudInit {
             timezone: Some("America/New_York".into()),
             ..Default::default()
         };
         let expected = r#"
 timezone: America/New_York"#;
 
-        assert_eq!(
-            yaml_serde::to_string(&data).unwrap().trim(),
-            expected.trim()
-        );
+        assert_eq!(user_data(&data).trim(), expected.trim());
     }
 
     #[test]
     fn hostname() {
-        let data = CloudInitConfig {
+        let data = CloudInit {
             hostname: Some("myhost".into()),
             ..Default::default()
         };
         let expected = r#"
 hostname: myhost"#;
 
+        assert_eq!(user_data(&data).trim(), expected.trim());
+    }
+
+    #[test]
+    fn packages_runcmd_write_files() {
+        let data = CloudInit {
+            packages: vec!["git".into(), "vim".into()],
+            runcmd: vec!["systemctl enable foo".into()],
+            write_files: vec![WriteFile {
+                path: "/etc/foo.conf".into(),
+                content: "bar=1\n".into(),
+                permissions: Some("0644".into()),
+                owner: None,
+            }],
+            ..Default::default()
+        };
+        let expected = r#"
+packages:
+- git
+- vim
+runcmd:
+- systemctl enable foo
+write_files:
+- path: /etc/foo.conf
+  content: |
+    bar=1
+  permissions: '0644'"#;
+
+        assert_eq!(user_data(&data).trim(), expected.trim());
+    }
+
+    #[test]
+    fn network() {
+        let network = Network {
+            ethernets: vec![Ethernet {
+                interface: "eth0".into(),
+                ip: IpConfig::Static(StaticIp {
+                    addresses: vec!["192.168.1.10/24".into()],
+                    gateway4: Some("192.168.1.1".into()),
+                    gateway6: None,
+                    nameservers: vec!["1.1.1.1".into()],
+                }),
+            }],
+            wifis: vec![Wifi {
+                interface: "wlan0".into(),
+                ip: IpConfig::Dhcp,
+                access_points: vec![
+                    ("home".into(), "password".into()),
+                    ("office".into(), "password2".into()),
+                ],
+            }],
+        };
+        let expected = r#"
+version: 2
+ethernets:
+  eth0:
+    addresses:
+    - 192.168.1.10/24
+    routes:
+    - to: default
+      via: 192.168.1.1
+    nameservers:
+      addresses:
+      - 1.1.1.1
+wifis:
+  wlan0:
+    dhcp4: true
+    access-points:
+      home:
+        password: password
+      office:
+        password: password2
+        "#;
+
         assert_eq!(
-            yaml_serde::to_string(&data).unwrap().trim(),
+            yaml_serde::to_string(&NetworkConfig::new(&network))
+                .unwrap()
+                .trim(),
             expected.trim()
         );
     }
 
     #[test]
-    fn network() {
-        let data = CloudInitConfig {
-            network: Some(Network {
-                version: 2,
-                renderer: "NetworkManager",
-                wifis: HashMap::from([(
-                    "wlp2s0b1",
-                    WifiInterface {
-                        access_points: HashMap::from([(
-                            "network_ssid_name".into(),
-                            AccessPoint {
-                                password: "password".into(),
-                            },
-                        )]),
-                    },
-                )]),
-            }),
+    fn fragments() {
+        let data = CloudInit {
+            hostname: Some("myhost".into()),
+            packages: vec!["git".into()],
+            fragments: vec![
+                "#cloud-config\npackages: [vim]\nhostname: other\n".into(),
+                "".into(),
+                "apt:\n  sources:\n    a: {}\n".into(),
+                "apt:\n  sources:\n    b: {}\n".into(),
+            ],
             ..Default::default()
         };
         let expected = r#"
-network:
-  version: 2
-  renderer: NetworkManager
-  wifis:
-    wlp2s0b1:
-      access-points:
-        network_ssid_name:
-          password: password
-        "#;
+hostname: other
+packages:
+- git
+- vim
+apt:
+  sources:
+    a: {}
+    b: {}"#;
+
+        assert_eq!(user_data(&data).trim(), expected.trim());
+    }
+
+    #[test]
+    fn invalid_fragment() {
+        let data = CloudInit {
+            fragments: vec!["a: 1".into(), "- a\n- b".into()],
+            ..Default::default()
+        };
+        assert_eq!(data.user_data().unwrap_err().index, 1);
+
+        let data = CloudInit {
+            fragments: vec!["a: [".into()],
+            ..Default::default()
+        };
+        assert_eq!(data.user_data().unwrap_err().index, 0);
+    }
+
+    #[test]
+    fn seed() {
+        let data = CloudInit {
+            hostname: Some("myhost".into()),
+            ..Default::default()
+        };
+        let seed = data.to_seed().unwrap();
 
         assert_eq!(
-            yaml_serde::to_string(&data).unwrap().trim(),
-            expected.trim()
+            seed.iter().map(|(x, _)| *x).collect::<Vec<_>>(),
+            [USER_DATA, META_DATA]
         );
+        let meta_data = std::str::from_utf8(&seed[1].1).unwrap();
+        assert!(meta_data.starts_with("instance-id: iid-bb-imager-"));
+        assert!(meta_data.contains("local-hostname: myhost\n"));
+
+        let data = CloudInit {
+            network: Network {
+                ethernets: vec![Ethernet {
+                    interface: "eth0".into(),
+                    ip: IpConfig::Dhcp,
+                }],
+                wifis: Vec::new(),
+            },
+            ..data
+        };
+        let seed2 = data.to_seed().unwrap();
+        assert_eq!(seed2[2].0, NETWORK_CONFIG);
+        // Different config should result in a new instance.
+        assert_ne!(seed[1].1, seed2[1].1);
     }
 }
@@ -0,0 +1,288 @@
+//! Customization of already flashed SD cards and images.
+//!
+//! While flashing, customization is appended to the files shipped in the image. Here files are
+//! replaced instead, and keys in `sysconf.txt` are updated in place, so the same card can be
+//! customized any number of times. Missing directories (e.g. `services/`) are created, and files
+//! not part of the customization are left as is.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use bb_helper::cancel::CancellationToken;
+
+use super::{Content, FlashingSdLinuxConfig, Target, armbian, boot_config, cloud_init};
+
+const SYSCONF: &str = "sysconf.txt";
+const SERVICES: &str = "services";
+
+/// Apply customization to a pre-flashed SD Card or image without flashing it again.
+#[derive(Debug, Clone)]
+pub struct Customizer {
+    dst: bb_flasher_sd::Destination,
+    customization: FlashingSdLinuxConfig,
+}
+
+impl Customizer {
+    pub fn new(dst: Target, customization: FlashingSdLinuxConfig) -> Self {
+        Self {
+            dst: bb_flasher_sd::Destination::SdCard(dst.0.path.into_boxed_path()),
+            customization,
+        }
+    }
+
+    pub fn with_file_dest(dst: PathBuf, customization: FlashingSdLinuxConfig) -> Self {
+        Self {
+            dst: bb_flasher_sd::Destination::File(dst.into_boxed_path()),
+            customization,
+        }
+    }
+
+    pub fn customize(self, cancel: Option<CancellationToken>) -> anyhow::Result<()> {
+        let customization = bb_flasher_sd::Customization {
+            partition: bb_flasher_sd::ParitionType::Boot,
+            content: content(self.customization).into_iter(),
+        };
+
+        bb_flasher_sd::customize::apply(self.dst, customization, cancel).map_err(Into::into)
+    }
+}
+
+/// Entries to write for `config`. Data for the same file is merged into a single entry.
+fn content<'a>(config: FlashingSdLinuxConfig) -> Vec<(Box<str>, bb_flasher_sd::ContentType<'a>)> {
+    let mut merged: Vec<(Box<str>, Content)> = Vec::new();
+    for (path, data) in config.0 {
+        let existing = merged.iter_mut().find_map(|(p, d)| match d {
+            Content::Data(x) if *p == path => Some(x),
+            _ => None,
+        });
+        match (existing, data) {
+            (Some(x), Content::Data(data)) => *x = [x.as_ref(), data.as_ref()].concat().into(),
+            (_, data) => merged.push((path, data)),
+        }
+    }
+
+    let mut dirs = HashSet::new();
+    let mut ans = Vec::with_capacity(merged.len());
+    for (path, data) in merged {
+        if matches!(data, Content::Data(_)) {
+            let parents = path.match_indices('/').map(|(i, _)| &path[..i]);
+            for dir in parents.filter(|x| dirs.insert(x.to_string())) {
+                ans.push((dir.into(), bb_flasher_sd::ContentType::Dir));
+            }
+        }
+
+        match data {
+            Content::Data(data) if path.as_ref() == SYSCONF => {
+                let data = String::from_utf8_lossy(&data).into_owned();
+                // Create the file if missing, so that it is always modified.
+                ans.push((
+                    path.clone(),
+                    bb_flasher_sd::ContentType::DataAppend(Box::default()),
+                ));
+                ans.push((
+                    path,
+                    bb_flasher_sd::ContentType::Modify(Box::new(move |old| {
+                        Ok(sysconf_update(&String::from_utf8_lossy(&old), &data).into_bytes())
+                    })),
+                ));
+            }
+            Content::Data(data) => ans.push((
+                path,
+                bb_flasher_sd::ContentType::Reader(Box::new(std::io::Cursor::new(data))),
+            )),
+            data => {
+                let data = data.into_content_type(&path);
+                ans.push((path, data));
+            }
+        }
+    }
+
+    ans
+}
+
+fn sysconf_key(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    if line.starts_with('#') {
+        None
+    } else {
+        line.split_once('=').map(|(k, _)| k.trim_end())
+    }
+}
+
+/// Replace the keys in `old` that are set in `new`. Comments and other keys are kept.
+fn sysconf_update(old: &str, new: &str) -> String {
+    let keys: HashSet<&str> = new.lines().filter_map(sysconf_key).collect();
+
+    let mut ans = String::with_capacity(old.len() + new.len());
+    for line in old
+        .lines()
+        .filter(|x| !sysconf_key(x).is_some_and(|k| keys.contains(k)))
+    {
+        ans.push_str(line);
+        ans.push('\n');
+    }
+    ans.push_str(new);
+
+    ans
+}
+
+/// Customization present on a pre-flashed SD Card or image.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CurrentConfig {
+    files: Vec<(Box<str>, Box<str>)>,
+}
+
+impl CurrentConfig {
+    /// Files that are read, if present. `services` is read as a directory.
+    const FILES: &[&str] = &[
+        SYSCONF,
+        SERVICES,
+        cloud_init::USER_DATA,
+        cloud_init::META_DATA,
+        cloud_init::NETWORK_CONFIG,
+        armbian::FILE_NAME,
+        boot_config::UENV,
+        boot_config::EXTLINUX,
+    ];
+
+    pub fn read(dst: Target) -> anyhow::Result<Self> {
+        Self::read_internal(bb_flasher_sd::Destination::SdCard(
+            dst.0.path.into_boxed_path(),
+        ))
+    }
+
+    pub fn read_file_dest(dst: PathBuf) -> anyhow::Result<Self> {
+        Self::read_internal(bb_flasher_sd::Destination::File(dst.into_boxed_path()))
+    }
+
+    fn read_internal(dst: bb_flasher_sd::Destination) -> anyhow::Result<Self> {
+        let files = bb_flasher_sd::customize::read(
+            dst,
+            bb_flasher_sd::ParitionType::Boot,
+            Self::FILES.iter().copied(),
+        )?;
+
+        Ok(Self {
+            files: files
+                .into_iter()
+                .map(|(p, d)| (p, String::from_utf8_lossy(&d).into()))
+                .collect(),
+        })
+    }
+
+    /// Customization files present, e.g. `sysconf.txt`, `user-data` or iwd service files in
+    /// `services/`.
+    pub fn files(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.files.iter().map(|(p, d)| (p.as_ref(), d.as_ref()))
+    }
+
+    pub fn file(&self, path: &str) -> Option<&str> {
+        self.files().find(|(p, _)| *p == path).map(|(_, d)| d)
+    }
+
+    /// Value of `key` in `sysconf.txt`. If set multiple times, the last one is returned.
+    pub fn sysconf(&self, key: &str) -> Option<&str> {
+        self.file(SYSCONF)?
+            .lines()
+            .filter(|x| sysconf_key(x) == Some(key))
+            .filter_map(|x| x.split_once('=').map(|(_, v)| v.trim()))
+            .next_back()
+    }
+
+    /// Top level scalar value in cloud-init `user-data`, e.g. `hostname` or `timezone`.
+    pub fn cloud_init(&self, key: &str) -> Option<String> {
+        let data: yaml_serde::Value =
+            yaml_serde::from_str(self.file(cloud_init::USER_DATA)?).ok()?;
+        match data.get(key)? {
+            yaml_serde::Value::String(x) => Some(x.clone()),
+            yaml_serde::Value::Bool(x) => Some(x.to_string()),
+            yaml_serde::Value::Number(x) => Some(x.to_string()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+    use crate::sd::CloudInit;
+
+    #[test]
+    fn sysconf_update_replaces_keys() {
+        let old = "# hostname=default\nhostname=old\nkeymap=us\n";
+        assert_eq!(
+            sysconf_update(old, "hostname=new\n"),
+            "# hostname=default\nkeymap=us\nhostname=new\n"
+        );
+    }
+
+    #[test]
+    fn content_merges_files() {
+        let mut config = FlashingSdLinuxConfig::generic_file("a.txt".into(), "1".into());
+        config.extend([
+            FlashingSdLinuxConfig::generic_file("x/y/b.txt".into(), "2".into()),
+            FlashingSdLinuxConfig::generic_file("a.txt".into(), "3".into()),
+            FlashingSdLinuxConfig::generic_file("x/c.txt".into(), "4".into()),
+        ]);
+
+        let mut ans = Vec::new();
+        for (path, data) in content(config) {
+            match data {
+                bb_flasher_sd::ContentType::Reader(mut r) => {
+                    let mut temp = String::new();
+                    r.read_to_string(&mut temp).unwrap();
+                    ans.push((path, Some(temp)));
+                }
+                bb_flasher_sd::ContentType::Dir => ans.push((path, None)),
+                _ => panic!("Unexpected content for {path}"),
+            }
+        }
+        assert_eq!(
+            ans,
+            [
+                ("a.txt".into(), Some("13".to_string())),
+                ("x".into(), None),
+                ("x/y".into(), None),
+                ("x/y/b.txt".into(), Some("2".to_string())),
+                ("x/c.txt".into(), Some("4".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn current_config() {
+        let cloud_init = CloudInit {
+            hostname: Some("beagle".into()),
+            timezone: Some("Asia/Kolkata".into()),
+            ..Default::default()
+        };
+        let seed = cloud_init.to_seed().unwrap();
+
+        let mut config = CurrentConfig {
+            files: seed
+                .into_iter()
+                .map(|(p, d)| (p.into(), String::from_utf8(d.into()).unwrap().into()))
+                .collect(),
+        };
+        config.files.push((
+            SYSCONF.into(),
+            "#hostname=x\nhostname=a\nuser_name = bob \nhostname=b\n".into(),
+        ));
+
+        assert_eq!(config.sysconf("hostname"), Some("b"));
+        assert_eq!(config.sysconf("user_name"), Some("bob"));
+        assert_eq!(config.sysconf("keymap"), None);
+        assert_eq!(config.cloud_init("hostname").as_deref(), Some("beagle"));
+        assert_eq!(
+            config.cloud_init("timezone").as_deref(),
+            Some("Asia/Kolkata")
+        );
+        assert_eq!(config.cloud_init("users"), None);
+
+        // No cloud-init config present.
+        let config = CurrentConfig::default();
+        assert_eq!(config.cloud_init("hostname"), None);
+    }
+}
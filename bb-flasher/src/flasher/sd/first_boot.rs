@@ -0,0 +1,523 @@
+//! Scripts run once on first boot.
+//!
+//! How the script is run depends on the init format of the image:
+//!
+//! - cloud-init: The script is written using `write_files` and run from `runcmd`. Early scripts
+//!   are instead run inline from `bootcmd` using `cloud-init-per once`, since `bootcmd` runs before
+//!   `write_files`.
+//! - sysconf: `bbbio-set-sysconf` cannot run scripts. Instead, a hook script is placed in
+//!   `bb-imager/` on the boot partition and run by systemd using `systemd.run` on the kernel
+//!   command line (`uEnv.txt` or `extlinux/extlinux.conf`). The hook removes these arguments and
+//!   the system reboots once it is done, so it only runs on first boot. Nothing is run if the
+//!   image has neither file.
+//! - Armbian: The script is placed in `bb-imager/` on the boot partition and run from
+//!   `armbian_first_run.txt`, which is sourced by `armbian-firstrun-config`.
+//!
+//! For sysconf and Armbian, early scripts are run directly by the hook. Other scripts are
+//! installed along with a systemd unit, which runs them once the network is online.
+//!
+//! Arguments are shell quoted.
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    BootConfig, Content, FlashingSdLinuxConfig, boot_config,
+    cloud_init::{CloudInit, WriteFile},
+};
+
+/// Maximum size of a script in bytes, after shell quoting.
+///
+/// Early cloud-init scripts are passed as a single command line argument, which Linux limits to
+/// 128 KiB.
+pub const MAX_SIZE: usize = 64 * 1024;
+
+/// Directory in the boot partition containing the hooks for sysconf and Armbian.
+const BOOT_DIR: &str = "bb-imager";
+/// Hook run by `systemd.run` on sysconf based images.
+const SYSCONF_HOOK: &str = "firstboot.sh";
+/// Mount point of the boot partition on [BeagleBoard.org] images.
+///
+/// [BeagleBoard.org]: https://www.beagleboard.org/
+pub(crate) const SYSCONF_BOOT_MOUNT: &str = "/boot/firmware";
+/// Mount point of the boot partition on Armbian images.
+const ARMBIAN_BOOT_MOUNT: &str = "/boot";
+/// Directory scripts are installed to.
+const SCRIPT_DIR: &str = "/var/lib/bb-imager/firstboot";
+/// systemd units installed by the hooks.
+const UNIT_DIR: &str = "/etc/systemd/system";
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FirstBootScriptError {
+    #[error("Invalid first boot script name {0:?}")]
+    InvalidName(Box<str>),
+    #[error("First boot script is empty")]
+    Empty,
+    #[error("First boot script is {0} bytes. At most {MAX_SIZE} bytes are supported")]
+    TooLarge(usize),
+    #[error("Invalid first boot script argument {0:?}")]
+    InvalidArg(Box<str>),
+}
+
+/// Script to run once on first boot.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FirstBootScript {
+    /// File name of the script. Only ASCII alphanumerics, `-`, `_` and `.` are allowed.
+    pub name: Box<str>,
+    pub content: Box<str>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<Box<str>>,
+    /// Run before networking is configured. The script is run with `sh`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub early: bool,
+}
+
+impl FirstBootScript {
+    pub fn new(name: Box<str>, content: Box<str>) -> Self {
+        Self {
+            name,
+            content,
+            args: Vec::new(),
+            early: false,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), FirstBootScriptError> {
+        if self.name.is_empty()
+            || self.name.len() > 64
+            || self.name.starts_with('.')
+            || !self
+                .name
+                .chars()
+                .all(|x| x.is_ascii_alphanumeric() || matches!(x, '-' | '_' | '.'))
+        {
+            return Err(FirstBootScriptError::InvalidName(self.name.clone()));
+        }
+
+        if self.content.trim().is_empty() {
+            return Err(FirstBootScriptError::Empty);
+        }
+        // Quoting can grow the script up to 4 times, so the quoted length is checked.
+        let size = shell_quote(&self.content).len();
+        if size > MAX_SIZE {
+            return Err(FirstBootScriptError::TooLarge(size));
+        }
+
+        // Config files are line based, so control characters cannot be passed through.
+        if let Some(x) = self.args.iter().find(|x| x.chars().any(char::is_control)) {
+            return Err(FirstBootScriptError::InvalidArg(x.clone()));
+        }
+
+        Ok(())
+    }
+
+    /// Arguments joined into a single shell quoted string.
+    fn quoted_args(&self) -> String {
+        self.args
+            .iter()
+            .map(|x| shell_quote(x))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn command(&self, program: &str) -> String {
+        let mut ans = shell_quote(program);
+        if !self.args.is_empty() {
+            ans.push(' ');
+            ans.push_str(&self.quoted_args());
+        }
+        ans
+    }
+
+    /// Path of the script relative to the boot partition, for sysconf and Armbian.
+    fn boot_path(&self) -> String {
+        format!("{BOOT_DIR}/{}", self.name)
+    }
+
+    /// Shell commands run by the sysconf and Armbian hooks, with the boot partition mounted at
+    /// `boot_mount`.
+    fn hook_commands(&self, boot_mount: &str) -> String {
+        let src = format!("{boot_mount}/{}", self.boot_path());
+
+        if self.early {
+            return format!("sh {}\nrm -f {}\n", self.command(&src), shell_quote(&src));
+        }
+
+        let path = format!("{SCRIPT_DIR}/{}", self.name);
+        let unit = format!("bb-imager-{}.service", self.name);
+        let wants = format!("{UNIT_DIR}/multi-user.target.wants/{unit}");
+
+        // The unit removes itself from `multi-user.target` before running the script, so that it
+        // only runs once.
+        let mut exec_start = systemd_quote(&path);
+        for arg in &self.args {
+            exec_start.push(' ');
+            exec_start.push_str(&systemd_quote(arg));
+        }
+
+        format!(
+            "mkdir -p {SCRIPT_DIR} {UNIT_DIR}/multi-user.target.wants
+cp {src} {path}
+chmod 755 {path}
+rm -f {src}
+cat > {UNIT_DIR}/{unit} << 'BB_IMAGER_EOF'
+[Unit]
+Description=bb-imager first boot script {name}
+Wants=network-online.target
+After=network-online.target
+
+[Service]
+Type=oneshot
+ExecStartPre=/bin/rm -f {wants}
+ExecStart={exec_start}
+
+[Install]
+WantedBy=multi-user.target
+BB_IMAGER_EOF
+ln -sf ../{unit} {wants}
+",
+            src = shell_quote(&src),
+            path = shell_quote(&path),
+            unit = shell_quote(&unit),
+            wants = shell_quote(&wants),
+            name = self.name,
+        )
+    }
+}
+
+impl FlashingSdLinuxConfig {
+    /// Run a script once on first boot of sysconf based images.
+    ///
+    /// See [`super::first_boot`] for details.
+    pub fn sysconfig_first_boot_script(
+        script: FirstBootScript,
+    ) -> Result<Self, FirstBootScriptError> {
+        script.validate()?;
+
+        let mut ans = sysconf_hook(&script.hook_commands(SYSCONF_BOOT_MOUNT));
+        ans.0.push((
+            script.boot_path().into(),
+            Content::Data(script.content.into_boxed_bytes()),
+        ));
+        Ok(ans)
+    }
+
+    /// Run a script once on first boot of Armbian images.
+    ///
+    /// Can be combined with [`Self::armbian`], in which case the commands are appended to the same
+    /// `armbian_first_run.txt`. See [`super::first_boot`] for details.
+    pub fn armbian_first_boot_script(
+        script: FirstBootScript,
+    ) -> Result<Self, FirstBootScriptError> {
+        script.validate()?;
+
+        let mut commands = script.hook_commands(ARMBIAN_BOOT_MOUNT);
+        if !script.early {
+            // `armbian-firstrun-config` runs during boot, so the new unit is started right away.
+            commands.push_str(&format!(
+                "systemctl daemon-reload\nsystemctl --no-block start {}\n",
+                shell_quote(&format!("bb-imager-{}.service", script.name))
+            ));
+        }
+
+        Ok(Self(vec![
+            (BOOT_DIR.into(), Content::Dir),
+            (
+                script.boot_path().into(),
+                Content::Data(script.content.into_boxed_bytes()),
+            ),
+            (
+                super::armbian::FILE_NAME.into(),
+                Content::Data(commands.into_bytes().into()),
+            ),
+        ]))
+    }
+}
+
+/// Run shell `commands` once on first boot of sysconf based images.
+///
+/// Each call appends to the same hook, so the hook starts by removing the kernel command line
+/// arguments again. The system reboots once the hook is done, even if it fails.
+pub(crate) fn sysconf_hook(commands: &str) -> FlashingSdLinuxConfig {
+    let hook = format!("{BOOT_DIR}/{SYSCONF_HOOK}");
+    let data = format!(
+        "#!/bin/sh
+sed -i 's/ *systemd\\.run[a-z_]*=[^ ]*//g' {SYSCONF_BOOT_MOUNT}/{} {SYSCONF_BOOT_MOUNT}/{} 2> /dev/null
+{commands}",
+        boot_config::UENV,
+        boot_config::EXTLINUX,
+    );
+
+    let mut ans = FlashingSdLinuxConfig(vec![
+        (BOOT_DIR.into(), Content::Dir),
+        (hook.clone().into(), Content::Data(data.into_bytes().into())),
+    ]);
+    ans.extend([FlashingSdLinuxConfig::boot_config(BootConfig {
+        cmdline: vec![
+            format!("systemd.run={SYSCONF_BOOT_MOUNT}/{hook}").into(),
+            "systemd.run_success_action=reboot".into(),
+            "systemd.run_failure_action=reboot".into(),
+        ],
+        ..Default::default()
+    })]);
+    ans
+}
+
+impl CloudInit {
+    /// Run `script` once on first boot.
+    pub fn add_first_boot_script(
+        &mut self,
+        script: &FirstBootScript,
+    ) -> Result<(), FirstBootScriptError> {
+        script.validate()?;
+
+        if script.early {
+            // `$0` is set to the script name, so arguments start from `$1`.
+            self.bootcmd.push(
+                format!(
+                    "cloud-init-per once bb-imager-{name} sh -c {} {name}{}{}",
+                    shell_quote(&script.content),
+                    if script.args.is_empty() { "" } else { " " },
+                    script.quoted_args(),
+                    name = shell_quote(&script.name),
+                )
+                .into(),
+            );
+        } else {
+            let path = format!("{SCRIPT_DIR}/{}", script.name);
+            self.runcmd.push(script.command(&path).into());
+            self.write_files.push(WriteFile {
+                path: path.into(),
+                content: script.content.clone(),
+                permissions: Some("0755".into()),
+                owner: None,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Quote `x` as a single argument of a systemd `ExecStart=` command line.
+fn systemd_quote(x: &str) -> String {
+    format!(
+        "\"{}\"",
+        x.replace('\\', r"\\")
+            .replace('"', "\\\"")
+            .replace('%', "%%")
+            .replace('$', "$$")
+    )
+}
+
+/// Quote `x` for POSIX shells.
+pub(crate) fn shell_quote(x: &str) -> String {
+    if !x.is_empty()
+        && x.chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/' | '=' | ':'))
+    {
+        x.to_string()
+    } else {
+        format!("'{}'", x.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn script() -> FirstBootScript {
+        FirstBootScript {
+            args: vec!["--id".into(), "lab 1".into(), "it's".into()],
+            ..FirstBootScript::new("register.sh".into(), "#!/bin/sh\necho $@\n".into())
+        }
+    }
+
+    #[test]
+    fn validate() {
+        script().validate().unwrap();
+
+        let invalid = |f: fn(&mut FirstBootScript)| {
+            let mut temp = script();
+            f(&mut temp);
+            temp.validate().unwrap_err()
+        };
+        assert_eq!(
+            invalid(|x| x.name = "../x".into()),
+            FirstBootScriptError::InvalidName("../x".into())
+        );
+        assert_eq!(
+            invalid(|x| x.name = ".hidden".into()),
+            FirstBootScriptError::InvalidName(".hidden".into())
+        );
+        assert_eq!(
+            invalid(|x| x.content = " \n".into()),
+            FirstBootScriptError::Empty
+        );
+        assert_eq!(
+            invalid(|x| x.content = "a".repeat(MAX_SIZE + 1).into()),
+            FirstBootScriptError::TooLarge(MAX_SIZE + 1)
+        );
+        // Fits unquoted, but not once quoted.
+        assert_eq!(
+            invalid(|x| x.content = "'".repeat(MAX_SIZE / 2).into()),
+            FirstBootScriptError::TooLarge(MAX_SIZE * 2 + 2)
+        );
+        assert_eq!(
+            invalid(|x| x.args.push("a\nb".into())),
+            FirstBootScriptError::InvalidArg("a\nb".into())
+        );
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!(shell_quote("/usr/bin/x"), "/usr/bin/x");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("a b"), "'a b'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+        assert_eq!(script().quoted_args(), r"--id 'lab 1' 'it'\''s'");
+    }
+
+    #[test]
+    fn cloud_init_runcmd() {
+        let mut config = CloudInit::default();
+        config.add_first_boot_script(&script()).unwrap();
+
+        assert_eq!(
+            config.runcmd,
+            [r"/var/lib/bb-imager/firstboot/register.sh --id 'lab 1' 'it'\''s'".into()]
+        );
+        assert_eq!(
+            config.write_files,
+            [WriteFile {
+                path: "/var/lib/bb-imager/firstboot/register.sh".into(),
+                content: "#!/bin/sh\necho $@\n".into(),
+                permissions: Some("0755".into()),
+                owner: None,
+            }]
+        );
+        assert!(config.bootcmd.is_empty());
+    }
+
+    #[test]
+    fn cloud_init_bootcmd() {
+        let mut config = CloudInit::default();
+        let script = FirstBootScript {
+            early: true,
+            args: vec!["a".into()],
+            ..FirstBootScript::new("early".into(), "echo \"$1\"".into())
+        };
+        config.add_first_boot_script(&script).unwrap();
+
+        assert_eq!(
+            config.bootcmd,
+            ["cloud-init-per once bb-imager-early sh -c 'echo \"$1\"' early a".into()]
+        );
+        assert!(config.runcmd.is_empty());
+        assert!(config.write_files.is_empty());
+    }
+
+    fn data(config: &FlashingSdLinuxConfig, path: &str) -> String {
+        config
+            .0
+            .iter()
+            .find_map(|(p, d)| match d {
+                Content::Data(x) if p.as_ref() == path => {
+                    Some(String::from_utf8(x.to_vec()).unwrap())
+                }
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn sysconf() {
+        let config = FlashingSdLinuxConfig::sysconfig_first_boot_script(script()).unwrap();
+
+        assert_eq!(
+            data(&config, "bb-imager/register.sh"),
+            "#!/bin/sh\necho $@\n"
+        );
+        let hook = data(&config, "bb-imager/firstboot.sh");
+        assert!(hook.starts_with("#!/bin/sh\nsed -i "), "{hook}");
+        assert!(
+            hook.contains(
+                "cp /boot/firmware/bb-imager/register.sh /var/lib/bb-imager/firstboot/register.sh\n"
+            ),
+            "{hook}"
+        );
+        assert!(
+            hook.contains(
+                r#"ExecStart="/var/lib/bb-imager/firstboot/register.sh" "--id" "lab 1" "it's""#
+            ),
+            "{hook}"
+        );
+        assert!(
+            hook.contains("ln -sf ../bb-imager-register.sh.service /etc/systemd/system/multi-user.target.wants/bb-imager-register.sh.service\n"),
+            "{hook}"
+        );
+
+        let cmdline = config
+            .0
+            .iter()
+            .find_map(|(p, d)| match d {
+                Content::BootConfig(x) if p.as_ref() == boot_config::EXTLINUX => {
+                    Some(x.cmdline.clone())
+                }
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(
+            cmdline,
+            [
+                "systemd.run=/boot/firmware/bb-imager/firstboot.sh".into(),
+                "systemd.run_success_action=reboot".into(),
+                "systemd.run_failure_action=reboot".into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn sysconf_early() {
+        let script = FirstBootScript {
+            early: true,
+            ..script()
+        };
+        let config = FlashingSdLinuxConfig::sysconfig_first_boot_script(script).unwrap();
+
+        assert!(data(&config, "bb-imager/firstboot.sh").ends_with(
+            r"
+sh /boot/firmware/bb-imager/register.sh --id 'lab 1' 'it'\''s'
+rm -f /boot/firmware/bb-imager/register.sh
+"
+        ));
+    }
+
+    #[test]
+    fn armbian() {
+        let config = FlashingSdLinuxConfig::armbian_first_boot_script(script()).unwrap();
+
+        assert_eq!(
+            data(&config, "bb-imager/register.sh"),
+            "#!/bin/sh\necho $@\n"
+        );
+        let commands = data(&config, "armbian_first_run.txt");
+        assert!(
+            commands.contains(
+                "cp /boot/bb-imager/register.sh /var/lib/bb-imager/firstboot/register.sh\n"
+            ),
+            "{commands}"
+        );
+        assert!(
+            commands.ends_with("systemctl --no-block start bb-imager-register.sh.service\n"),
+            "{commands}"
+        );
+    }
+
+    #[test]
+    fn systemd_quoting() {
+        assert_eq!(systemd_quote("a b"), r#""a b""#);
+        assert_eq!(
+            systemd_quote(r#"$HOME "100%" \n"#),
+            r#""$$HOME \"100%%\" \\n""#
+        );
+    }
+}
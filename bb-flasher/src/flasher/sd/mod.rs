@@ -2,15 +2,35 @@
 //!
 //! Post-install customization is only available for [BeagleBoard.org] images
 //!
+//! Customization can also be applied to an already flashed SD card or image using [`Customizer`],
+//! and read back using [`CurrentConfig`].
+//!
 //! [BeagleBoard.org]: https://www.beagleboard.org/
 
-mod cloud_init;
+mod armbian;
+mod boot_config;
+pub mod cloud_init;
+mod customize;
+pub mod first_boot;
+mod password;
+pub mod profile;
+pub mod sysconf;
 
 use bb_helper::cancel::CancellationToken;
 use std::{borrow::Cow, fmt::Display, path::PathBuf};
 
 use crate::common::{BBFlasherTarget, DownloadFlashingStatus};
 
+pub use crate::validate;
+pub use armbian::ArmbianError;
+pub use boot_config::BootConfig;
+pub use cloud_init::CloudInit;
+pub use customize::{CurrentConfig, Customizer};
+pub use first_boot::FirstBootScript;
+pub use password::Password;
+pub use profile::{Profile, ProfileStore};
+pub use sysconf::SysconfError;
+
 /// SD Card
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct Target(bb_flasher_sd::Device);
@@ -63,79 +83,232 @@ impl BBFlasherTarget for Target {
 
 /// Linux Image post-install customization options.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
-pub struct FlashingSdLinuxConfig(Vec<(Box<str>, Box<[u8]>)>);
+pub struct FlashingSdLinuxConfig(Vec<(Box<str>, Content)>);
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Content {
+    /// Appended to the file, creating it if needed.
+    Data(Box<[u8]>),
+    /// Replaces the file contents, creating it if needed.
+    Replace(Box<[u8]>),
+    Dir,
+    BootConfig(BootConfig),
+}
+
+impl Content {
+    fn into_content_type<'a>(self, path: &str) -> bb_flasher_sd::ContentType<'a> {
+        match self {
+            Self::Data(x) => x.into(),
+            Self::Replace(x) => {
+                bb_flasher_sd::ContentType::Reader(Box::new(std::io::Cursor::new(x)))
+            }
+            Self::Dir => bb_flasher_sd::ContentType::Dir,
+            Self::BootConfig(x) => {
+                let path = path.to_string();
+                bb_flasher_sd::ContentType::Modify(Box::new(move |data| x.apply(&path, data)))
+            }
+        }
+    }
+}
+
+fn sysconf_w(sysconf: &mut Vec<u8>, key: &'static str, value: &str) -> Result<(), SysconfError> {
+    if value.chars().any(char::is_control) {
+        return Err(SysconfError::ControlCharacter(key));
+    }
 
-fn sysconf_w(sysconf: &mut Vec<u8>, key: &str, value: &str) {
     sysconf.extend(key.as_bytes());
     sysconf.extend(b"=");
     sysconf.extend(value.as_bytes());
     sysconf.extend(b"\n");
+    Ok(())
 }
 
 impl FlashingSdLinuxConfig {
+    /// `sysconf.txt` for [BeagleBoard.org] images.
+    ///
+    /// Values containing control characters are rejected, since each line of `sysconf.txt` is a
+    /// key. For the same reason, only a single SSH key is supported. The password is passed to
+    /// `bbbio-set-sysconf` as-is, so [`Password::Hashed`] is rejected.
+    ///
+    /// [BeagleBoard.org]: https://www.beagleboard.org/
     pub fn sysconfig(
         hostname: Option<Box<str>>,
         timezone: Option<Box<str>>,
         keymap: Option<Box<str>>,
-        user: Option<(Box<str>, Box<str>)>,
+        user: Option<(Box<str>, Password)>,
         wifi: Option<(Box<str>, Box<str>)>,
         ssh: Option<Box<str>>,
         usb_enable_dhcp: Option<bool>,
-    ) -> Self {
+    ) -> Result<Self, SysconfError> {
         let mut content = Vec::<u8>::new();
 
         if let Some(h) = hostname {
-            sysconf_w(&mut content, "hostname", &h);
+            sysconf_w(&mut content, "hostname", &h)?;
         }
         if let Some(tz) = timezone {
-            sysconf_w(&mut content, "timezone", &tz);
+            sysconf_w(&mut content, "timezone", &tz)?;
         }
         if let Some(k) = keymap {
-            sysconf_w(&mut content, "keymap", &k);
+            sysconf_w(&mut content, "keymap", &k)?;
         }
         if let Some((u, p)) = user {
-            sysconf_w(&mut content, "user_name", &u);
-            sysconf_w(&mut content, "user_password", &p);
+            let p = p.as_plain().ok_or(SysconfError::HashedPassword)?;
+            sysconf_w(&mut content, "user_name", &u)?;
+            sysconf_w(&mut content, "user_password", p)?;
         }
         if let Some(x) = ssh {
-            sysconf_w(&mut content, "user_authorized_key", &x);
+            // Trailing line breaks of key files are fine.
+            let x = x.trim();
+            if x.contains('\n') {
+                return Err(SysconfError::MultipleSshKeys);
+            }
+            sysconf_w(&mut content, "user_authorized_key", x)?;
         }
         if Some(true) == usb_enable_dhcp {
-            sysconf_w(&mut content, "usb_enable_dhcp", "yes");
+            sysconf_w(&mut content, "usb_enable_dhcp", "yes")?;
         }
 
         match wifi {
             Some((ssid, psk)) => {
-                sysconf_w(&mut content, "iwd_psk_file", &format!("{ssid}.psk"));
+                if psk.chars().any(char::is_control) {
+                    return Err(SysconfError::ControlCharacter("Wi-Fi passphrase"));
+                }
+
+                let file_name = sysconf::iwd_file_name(&ssid, "psk");
+                sysconf_w(&mut content, "iwd_psk_file", &file_name)?;
 
-                Self(vec![
-                    ("sysconf.txt".to_string().into(), content.into()),
+                Ok(Self(vec![
+                    ("sysconf.txt".into(), Content::Data(content.into())),
                     (
-                        format!("services/{ssid}.psk").into(),
-                        format!("[Security]\nPassphrase={psk}\n\n[Settings]\nAutoConnect=true")
-                            .into_bytes()
-                            .into(),
+                        format!("services/{file_name}").into(),
+                        Content::Data(
+                            format!("[Security]\nPassphrase={psk}\n\n[Settings]\nAutoConnect=true")
+                                .into_bytes()
+                                .into(),
+                        ),
                     ),
-                ])
+                ]))
             }
-            None => Self(vec![("sysconf.txt".to_string().into(), content.into())]),
+            None => Ok(Self(vec![(
+                "sysconf.txt".into(),
+                Content::Data(content.into()),
+            )])),
+        }
+    }
+
+    /// Advanced network configuration for sysconf based images.
+    ///
+    /// Can be combined with [`Self::sysconfig`]. See [`sysconf`] for how the options are applied.
+    pub fn sysconfig_network(network: sysconf::Network) -> Result<Self, SysconfError> {
+        if network.is_empty() {
+            return Ok(Self::none());
+        }
+        network.check()?;
+
+        let mut ans = Self(
+            network
+                .service_files()
+                .into_iter()
+                .map(|(path, data)| (path.into(), Content::Replace(data.into_bytes().into())))
+                .collect(),
+        );
+        if !ans.0.is_empty() {
+            ans.0
+                .insert(0, (sysconf::SERVICES_DIR.into(), Content::Dir));
         }
+
+        let commands = network.hook_commands();
+        if !commands.is_empty() {
+            ans.extend([first_boot::sysconf_hook(&commands)]);
+        }
+        ans.extend([Self::boot_config(BootConfig {
+            cmdline: network.cmdline(),
+            ..Default::default()
+        })]);
+
+        Ok(ans)
     }
 
     pub fn cloud_init(
         hostname: Option<Box<str>>,
         timezone: Option<Box<str>>,
         keymap: Option<Box<str>>,
-        user: Option<(Box<str>, Box<str>)>,
+        user: Option<(Box<str>, Password)>,
         wifi: Option<(Box<str>, Box<str>)>,
         ssh: Option<Box<str>>,
     ) -> Self {
-        let data = cloud_init::CloudInitConfig::new(hostname, timezone, keymap, user, wifi, ssh);
-        Self(vec![("cloud-init".to_string().into(), data.to_file_data())])
+        let config = CloudInit {
+            hostname,
+            timezone,
+            keymap,
+            user,
+            ssh_authorized_keys: ssh.into_iter().collect(),
+            network: cloud_init::Network {
+                ethernets: Vec::new(),
+                wifis: wifi
+                    .map(|x| {
+                        vec![cloud_init::Wifi {
+                            interface: cloud_init::DEFAULT_WIFI_INTERFACE.into(),
+                            ip: cloud_init::IpConfig::Dhcp,
+                            access_points: vec![x],
+                        }]
+                    })
+                    .unwrap_or_default(),
+            },
+            ..Default::default()
+        };
+
+        Self::cloud_init_seed(config).expect("No fragments to merge")
+    }
+
+    /// cloud-init NoCloud seed (`user-data`, `meta-data` and `network-config`).
+    pub fn cloud_init_seed(config: CloudInit) -> Result<Self, cloud_init::FragmentError> {
+        let seed = config.to_seed()?;
+        Ok(Self(
+            seed.into_iter()
+                .map(|(p, d)| (p.into(), Content::Replace(d)))
+                .collect(),
+        ))
+    }
+
+    /// Armbian first run configuration (`armbian_first_run.txt`).
+    ///
+    /// Armbian only accepts SSH keys as a URL to download them from, so they are not supported.
+    /// The password is passed to Armbian as-is, so [`Password::Hashed`] is rejected.
+    pub fn armbian(
+        hostname: Option<Box<str>>,
+        timezone: Option<Box<str>>,
+        locale: Option<Box<str>>,
+        user: Option<(Box<str>, Password)>,
+        wifi: Option<(Box<str>, Box<str>)>,
+    ) -> Result<Self, ArmbianError> {
+        let data = armbian::ArmbianConfig::new(hostname, timezone, locale, user, wifi)?;
+        Ok(Self(vec![(
+            armbian::FILE_NAME.into(),
+            Content::Data(data.to_file_data()),
+        )]))
     }
 
     pub fn generic_file(file_name: Box<str>, file_content: Box<str>) -> Self {
-        Self(vec![(file_name, file_content.into_boxed_bytes())])
+        Self(vec![(
+            file_name,
+            Content::Data(file_content.into_boxed_bytes()),
+        )])
+    }
+
+    /// Edit boot configuration (`uEnv.txt` or `extlinux/extlinux.conf`) present in the image.
+    pub fn boot_config(config: BootConfig) -> Self {
+        if config.is_empty() {
+            return Self::none();
+        }
+
+        Self(vec![
+            (
+                boot_config::UENV.into(),
+                Content::BootConfig(config.clone()),
+            ),
+            (boot_config::EXTLINUX.into(), Content::BootConfig(config)),
+        ])
     }
 
     pub const fn none() -> Self {
@@ -226,7 +399,10 @@ where
         let customization = if self.customization.0.is_empty() {
             vec![]
         } else {
-            let content = self.customization.0.into_iter().map(|(p, d)| (p, d.into()));
+            let content = self.customization.0.into_iter().map(|(p, d)| {
+                let d = d.into_content_type(&p);
+                (p, d)
+            });
             vec![bb_flasher_sd::Customization {
                 partition: bb_flasher_sd::ParitionType::Boot,
                 content,
@@ -256,8 +432,13 @@ where
             None => None,
         };
 
-        bb_flasher_sd::flash(self.img, self.bmap, self.dst, tx, customization, cancel)
-            .map_err(Into::into)
+        bb_flasher_sd::flash(self.img, self.bmap, self.dst, tx, customization, cancel).map_err(
+            // Surface checksum failures directly instead of as a generic IO error.
+            |e| match crate::img::find_mismatch(&e) {
+                Some(x) => x.clone().into(),
+                None => e.into(),
+            },
+        )
     }
 }
 
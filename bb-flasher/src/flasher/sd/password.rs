@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+
+/// Password of the user account created by customization.
+///
+/// Serialized as `password` or `password_hash` respectively.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Password {
+    /// Plaintext password. Hashed for cloud-init, but written as-is to `sysconf.txt` and Armbian
+    /// configuration, which do not support hashes.
+    #[serde(rename = "password")]
+    Plain(Box<str>),
+    /// Password already hashed in crypt(3) format (e.g. SHA-512 crypt: `$6$...`). Only supported
+    /// by cloud-init.
+    #[serde(rename = "password_hash")]
+    Hashed(Box<str>),
+}
+
+impl Password {
+    /// Convert to [`Password::Hashed`] using SHA-512 crypt with a random salt.
+    pub fn hash(self) -> Self {
+        match self {
+            Self::Plain(x) => Self::Hashed(sha512_crypt(&x)),
+            Self::Hashed(_) => self,
+        }
+    }
+
+    /// crypt(3) hash of the password.
+    pub(crate) fn to_hash(&self) -> Box<str> {
+        match self {
+            Self::Plain(x) => sha512_crypt(x),
+            Self::Hashed(x) => x.clone(),
+        }
+    }
+
+    pub(crate) fn as_plain(&self) -> Option<&str> {
+        match self {
+            Self::Plain(x) => Some(x),
+            Self::Hashed(_) => None,
+        }
+    }
+}
+
+fn sha512_crypt(x: &str) -> Box<str> {
+    pwhash::sha512_crypt::hash(x)
+        .expect("Failed to generate salt")
+        .into()
+}
+
+impl From<Box<str>> for Password {
+    fn from(value: Box<str>) -> Self {
+        Self::Plain(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash() {
+        let hash = Password::Plain("temppwd".into()).to_hash();
+
+        assert!(hash.starts_with("$6$"));
+        assert!(pwhash::sha512_crypt::verify("temppwd", &hash));
+        // Salt should be random.
+        assert_ne!(hash, Password::Plain("temppwd".into()).to_hash());
+    }
+
+    #[test]
+    fn hashed() {
+        let hash = Password::Plain("temppwd".into()).hash();
+        assert_eq!(hash.clone().hash(), hash);
+        assert_eq!(hash.as_plain(), None);
+    }
+}
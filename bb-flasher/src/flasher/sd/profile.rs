@@ -0,0 +1,899 @@
+//! Reusable customization profiles.
+//!
+//! A [`Profile`] covers all post-install customization options along with the init format they
+//! are meant for. Profiles can be saved as TOML or JSON, with the format chosen based on the file
+//! extension.
+//!
+//! ```toml
+//! name = "lab"
+//! init_format = "cloud_init"
+//! hostname = "beagle"
+//!
+//! [user]
+//! name = "beagle"
+//! password_hash = "$6$..."
+//!
+//! [[network.wifis]]
+//! ssid = "lab"
+//! security = { psk = "secret123" }
+//! ```
+//!
+//! [`ProfileStore`] keeps named profiles in a directory.
+
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    ArmbianError, BootConfig, CloudInit, FirstBootScript, FlashingSdLinuxConfig, Password,
+    SysconfError,
+    cloud_init::{self, FragmentError, WriteFile},
+    first_boot::FirstBootScriptError,
+    sysconf::{self, WifiSecurity},
+    validate,
+};
+
+const TOML_EXT: &str = "toml";
+const JSON_EXT: &str = "json";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ProfileError {
+    #[error("Failed to access profile: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to parse profile: {0}")]
+    TomlDe(#[from] toml::de::Error),
+    #[error("Failed to serialize profile: {0}")]
+    TomlSer(#[from] toml::ser::Error),
+    #[error("Invalid JSON profile: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Unsupported profile file. Expected a .toml or .json file")]
+    UnknownFormat,
+    #[error("Invalid profile name: {0:?}")]
+    InvalidName(Box<str>),
+    #[error("Profile not found: {0}")]
+    NotFound(Box<str>),
+    /// A field cannot be used as configured.
+    #[error("{field}: {reason}")]
+    Invalid {
+        field: &'static str,
+        reason: Box<str>,
+    },
+    #[error(transparent)]
+    Fragment(#[from] FragmentError),
+    #[error(transparent)]
+    Validation(#[from] validate::ValidationErrors),
+    #[error(transparent)]
+    FirstBootScript(#[from] FirstBootScriptError),
+    #[error(transparent)]
+    Armbian(#[from] ArmbianError),
+    #[error(transparent)]
+    Sysconf(#[from] SysconfError),
+}
+
+/// Configuration format understood by the image.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum InitFormat {
+    /// `sysconf.txt` used by [BeagleBoard.org] images
+    ///
+    /// [BeagleBoard.org]: https://www.beagleboard.org/
+    #[default]
+    Sysconf,
+    CloudInit,
+    Armbian,
+}
+
+impl Display for InitFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Sysconf => write!(f, "sysconf"),
+            Self::CloudInit => write!(f, "cloud-init"),
+            Self::Armbian => write!(f, "Armbian"),
+        }
+    }
+}
+
+/// Serializable set of customization options.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(default)]
+pub struct Profile {
+    /// Name used by [`ProfileStore`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<Box<str>>,
+    pub init_format: InitFormat,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<Box<str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<Box<str>>,
+    /// Not supported by Armbian
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keymap: Option<Box<str>>,
+    /// Only supported by Armbian
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locale: Option<Box<str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<User>,
+    /// Not supported by Armbian
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ssh_key: Option<Box<str>>,
+    /// Only supported by sysconf
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub usb_enable_dhcp: bool,
+    pub network: sysconf::Network,
+    pub boot: BootConfig,
+    pub cloud_init: CloudInitOptions,
+    /// Additional files to place in BOOT partition
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<File>,
+    /// Script to run once on first boot
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_boot: Option<FirstBootScript>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct User {
+    pub name: Box<str>,
+    #[serde(flatten)]
+    pub password: Password,
+}
+
+/// Options only supported by cloud-init.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(default)]
+pub struct CloudInitOptions {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub packages: Vec<Box<str>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub runcmd: Vec<Box<str>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub write_files: Vec<WriteFile>,
+    /// `#cloud-config` documents merged into generated user-data
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fragments: Vec<Box<str>>,
+    /// Defaults to [`cloud_init::DEFAULT_WIFI_INTERFACE`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wifi_interface: Option<Box<str>>,
+}
+
+impl CloudInitOptions {
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty()
+            && self.runcmd.is_empty()
+            && self.write_files.is_empty()
+            && self.fragments.is_empty()
+            && self.wifi_interface.is_none()
+    }
+}
+
+/// File in BOOT partition.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct File {
+    /// Path relative to BOOT partition
+    pub path: Box<str>,
+    pub content: Box<str>,
+}
+
+fn invalid(field: &'static str, reason: impl Into<Box<str>>) -> ProfileError {
+    ProfileError::Invalid {
+        field,
+        reason: reason.into(),
+    }
+}
+
+fn unsupported(field: &'static str, format: InitFormat) -> ProfileError {
+    invalid(field, format!("Not supported by {format}"))
+}
+
+impl Profile {
+    /// Load profile from a `.toml` or `.json` file.
+    pub fn load(path: &Path) -> Result<Self, ProfileError> {
+        let data = std::fs::read_to_string(path)?;
+
+        match path.extension().and_then(|x| x.to_str()) {
+            Some(TOML_EXT) => toml::from_str(&data).map_err(Into::into),
+            Some(JSON_EXT) => serde_json::from_str(&data).map_err(Into::into),
+            _ => Err(ProfileError::UnknownFormat),
+        }
+    }
+
+    /// Save profile to a `.toml` or `.json` file.
+    ///
+    /// Passwords are saved as-is. Use [`Password::hash`] to avoid saving plaintext passwords.
+    pub fn save(&self, path: &Path) -> Result<(), ProfileError> {
+        let data = match path.extension().and_then(|x| x.to_str()) {
+            Some(TOML_EXT) => toml::to_string_pretty(self)?,
+            Some(JSON_EXT) => serde_json::to_string_pretty(self)?,
+            _ => return Err(ProfileError::UnknownFormat),
+        };
+
+        if let Some(p) = path.parent() {
+            std::fs::create_dir_all(p)?;
+        }
+        std::fs::write(path, data).map_err(Into::into)
+    }
+
+    /// Check that all configured options are valid and supported by [`Profile::init_format`].
+    pub fn validate(&self) -> Result<(), ProfileError> {
+        let format = self.init_format;
+
+        if let Some(name) = &self.name {
+            validate_name(name)?;
+        }
+
+        let mut errors = validate::Inputs {
+            hostname: self.hostname.as_deref(),
+            timezone: self.timezone.as_deref(),
+            keymap: self.keymap.as_deref(),
+            username: self.user.as_ref().map(|x| x.name.as_ref()),
+            password: self.user.as_ref().and_then(|x| x.password.as_plain()),
+            ssh_key: self.ssh_key.as_deref(),
+            ..Default::default()
+        }
+        .errors();
+        errors.0.extend(self.network.errors().0);
+        errors.into_result()?;
+
+        if let Some(user) = &self.user
+            && format != InitFormat::CloudInit
+            && user.password.as_plain().is_none()
+        {
+            return Err(invalid(
+                "user.password_hash",
+                format!("{format} requires a plaintext password"),
+            ));
+        }
+
+        if let Some(script) = &self.first_boot {
+            script.validate()?;
+        }
+
+        for file in &self.files {
+            if file.path.is_empty() || file.path.split(['/', '\\']).any(|x| x == "..") {
+                return Err(invalid(
+                    "files.path",
+                    format!("Invalid path {:?}", file.path),
+                ));
+            }
+        }
+
+        match format {
+            InitFormat::Sysconf => {
+                if self.locale.is_some() {
+                    return Err(unsupported("locale", format));
+                }
+                if self
+                    .ssh_key
+                    .as_deref()
+                    .is_some_and(|x| x.trim().contains('\n'))
+                {
+                    return Err(invalid("ssh_key", "sysconf only supports a single SSH key"));
+                }
+            }
+            InitFormat::CloudInit => {
+                if self.locale.is_some() {
+                    return Err(unsupported("locale", format));
+                }
+                if self.usb_enable_dhcp {
+                    return Err(unsupported("usb_enable_dhcp", format));
+                }
+                if self.network.wifi_country.is_some() {
+                    return Err(unsupported("network.wifi_country", format));
+                }
+                if self
+                    .network
+                    .wifis
+                    .iter()
+                    .any(|x| x.hidden || !matches!(x.security, WifiSecurity::Psk(_)))
+                {
+                    return Err(invalid(
+                        "network.wifis",
+                        "cloud-init only supports visible WPA2-Personal networks",
+                    ));
+                }
+            }
+            InitFormat::Armbian => {
+                if self.keymap.is_some() {
+                    return Err(unsupported("keymap", format));
+                }
+                if self.ssh_key.is_some() {
+                    return Err(unsupported("ssh_key", format));
+                }
+                if self.usb_enable_dhcp {
+                    return Err(unsupported("usb_enable_dhcp", format));
+                }
+                if self.network.wifi_country.is_some() {
+                    return Err(unsupported("network.wifi_country", format));
+                }
+                if self.network.ethernet.is_some() {
+                    return Err(unsupported("network.ethernet", format));
+                }
+                match self.network.wifis.as_slice() {
+                    [] => {}
+                    [x] if !x.hidden && matches!(x.security, WifiSecurity::Psk(_)) => {}
+                    _ => {
+                        return Err(invalid(
+                            "network.wifis",
+                            "Armbian only supports a single visible WPA2-Personal network",
+                        ));
+                    }
+                }
+            }
+        }
+
+        if format != InitFormat::CloudInit && !self.cloud_init.is_empty() {
+            return Err(unsupported("cloud_init", format));
+        }
+
+        Ok(())
+    }
+
+    /// Validate and convert to customization for [`super::Flasher`].
+    pub fn to_customization(&self) -> Result<FlashingSdLinuxConfig, ProfileError> {
+        self.validate()?;
+
+        let user = self
+            .user
+            .clone()
+            .map(|User { name, password }| (name, password));
+        let wifis = || {
+            let mut wifis: Vec<_> = self.network.wifis.iter().collect();
+            wifis.sort_by_key(|x| std::cmp::Reverse(x.priority));
+            wifis.into_iter().filter_map(|x| match &x.security {
+                WifiSecurity::Psk(psk) => Some((x.ssid.clone(), psk.clone())),
+                _ => None,
+            })
+        };
+
+        let first_boot = self.first_boot.clone();
+        let mut customization = match self.init_format {
+            InitFormat::Sysconf => {
+                let mut temp = FlashingSdLinuxConfig::sysconfig(
+                    self.hostname.clone(),
+                    self.timezone.clone(),
+                    self.keymap.clone(),
+                    user,
+                    None,
+                    self.ssh_key.clone(),
+                    Some(self.usb_enable_dhcp),
+                )?;
+                temp.extend([FlashingSdLinuxConfig::sysconfig_network(
+                    self.network.clone(),
+                )?]);
+                if let Some(x) = first_boot {
+                    temp.extend([FlashingSdLinuxConfig::sysconfig_first_boot_script(x)?]);
+                }
+                temp
+            }
+            InitFormat::CloudInit => {
+                let access_points: Vec<_> = wifis().collect();
+                let network = cloud_init::Network {
+                    ethernets: self
+                        .network
+                        .ethernet
+                        .iter()
+                        .map(|x| cloud_init::Ethernet {
+                            interface: x
+                                .interface
+                                .clone()
+                                .unwrap_or(sysconf::DEFAULT_ETHERNET_INTERFACE.into()),
+                            ip: cloud_init::IpConfig::Static(x.ip.clone()),
+                        })
+                        .collect(),
+                    wifis: if access_points.is_empty() {
+                        Vec::new()
+                    } else {
+                        vec![cloud_init::Wifi {
+                            interface: self
+                                .cloud_init
+                                .wifi_interface
+                                .clone()
+                                .unwrap_or(cloud_init::DEFAULT_WIFI_INTERFACE.into()),
+                            ip: cloud_init::IpConfig::Dhcp,
+                            access_points,
+                        }]
+                    },
+                };
+
+                let mut config = CloudInit {
+                    hostname: self.hostname.clone(),
+                    timezone: self.timezone.clone(),
+                    keymap: self.keymap.clone(),
+                    user,
+                    ssh_authorized_keys: self.ssh_key.iter().cloned().collect(),
+                    packages: self.cloud_init.packages.clone(),
+                    bootcmd: Vec::new(),
+                    runcmd: self.cloud_init.runcmd.clone(),
+                    write_files: self.cloud_init.write_files.clone(),
+                    network,
+                    fragments: self.cloud_init.fragments.clone(),
+                };
+                if let Some(x) = &first_boot {
+                    config.add_first_boot_script(x)?;
+                }
+                FlashingSdLinuxConfig::cloud_init_seed(config)?
+            }
+            InitFormat::Armbian => {
+                let mut temp = FlashingSdLinuxConfig::armbian(
+                    self.hostname.clone(),
+                    self.timezone.clone(),
+                    self.locale.clone(),
+                    user,
+                    wifis().next(),
+                )?;
+                if let Some(x) = first_boot {
+                    temp.extend([FlashingSdLinuxConfig::armbian_first_boot_script(x)?]);
+                }
+                temp
+            }
+        };
+
+        customization.extend([FlashingSdLinuxConfig::boot_config(self.boot.clone())]);
+        customization.extend(
+            self.files
+                .iter()
+                .map(|x| FlashingSdLinuxConfig::generic_file(x.path.clone(), x.content.clone())),
+        );
+
+        Ok(customization)
+    }
+}
+
+/// Profile names are used as file names, so only a conservative set of characters is allowed.
+fn validate_name(name: &str) -> Result<(), ProfileError> {
+    if !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|x| x.is_alphanumeric() || matches!(x, '-' | '_' | '.' | ' '))
+    {
+        Ok(())
+    } else {
+        Err(ProfileError::InvalidName(name.into()))
+    }
+}
+
+/// Directory of named profiles, stored as `<name>.toml` or `<name>.json`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ProfileStore(PathBuf);
+
+impl ProfileStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self(dir)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.0
+    }
+
+    /// Names of all profiles in sorted order. A missing directory has no profiles.
+    pub fn list(&self) -> std::io::Result<Vec<Box<str>>> {
+        let entries = match std::fs::read_dir(&self.0) {
+            Ok(x) => x,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut ans = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if !matches!(
+                path.extension().and_then(|x| x.to_str()),
+                Some(TOML_EXT | JSON_EXT)
+            ) {
+                continue;
+            }
+            if let Some(name) = path.file_stem().and_then(|x| x.to_str()) {
+                ans.push(name.into());
+            }
+        }
+
+        ans.sort();
+        ans.dedup();
+        Ok(ans)
+    }
+
+    fn path(&self, name: &str) -> Result<Option<PathBuf>, ProfileError> {
+        validate_name(name)?;
+
+        Ok([TOML_EXT, JSON_EXT]
+            .into_iter()
+            .map(|ext| self.0.join(format!("{name}.{ext}")))
+            .find(|x| x.exists()))
+    }
+
+    /// Load a profile by name. [`Profile::name`] is set to `name`.
+    pub fn load(&self, name: &str) -> Result<Profile, ProfileError> {
+        let path = self
+            .path(name)?
+            .ok_or_else(|| ProfileError::NotFound(name.into()))?;
+
+        let mut profile = Profile::load(&path)?;
+        profile.name = Some(name.into());
+        Ok(profile)
+    }
+
+    /// Save a profile using [`Profile::name`]. Replaces any existing profile with the same name.
+    ///
+    /// Profiles that fail [`Profile::validate`] are not saved.
+    pub fn save(&self, profile: &Profile) -> Result<(), ProfileError> {
+        let name = profile
+            .name
+            .as_deref()
+            .ok_or_else(|| ProfileError::InvalidName("".into()))?;
+        profile.validate()?;
+
+        if let Some(old) = self.path(name)? {
+            std::fs::remove_file(old)?;
+        }
+        profile.save(&self.0.join(format!("{name}.{TOML_EXT}")))
+    }
+
+    pub fn remove(&self, name: &str) -> Result<(), ProfileError> {
+        let path = self
+            .path(name)?
+            .ok_or_else(|| ProfileError::NotFound(name.into()))?;
+        std::fs::remove_file(path).map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile() -> Profile {
+        Profile {
+            name: Some("lab".into()),
+            hostname: Some("beagle".into()),
+            timezone: Some("Asia/Kolkata".into()),
+            keymap: Some("us".into()),
+            user: Some(User {
+                name: "beagle".into(),
+                password: Password::Plain("temppwd".into()),
+            }),
+            ssh_key: Some(
+                "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAABAgMEBQYHCAkKCwwNDg8QERITFBUWFxgZGhscHR4f"
+                    .into(),
+            ),
+            network: sysconf::Network {
+                wifi_country: Some("IN".into()),
+                wifis: vec![
+                    sysconf::Wifi::psk("home".into(), "secret123".into()),
+                    sysconf::Wifi {
+                        ssid: "campus".into(),
+                        security: WifiSecurity::Peap {
+                            identity: "student".into(),
+                            password: "pass".into(),
+                            anonymous_identity: None,
+                            ca_cert: None,
+                        },
+                        hidden: true,
+                        priority: 5,
+                    },
+                ],
+                ethernet: Some(sysconf::Ethernet {
+                    interface: None,
+                    ip: cloud_init::StaticIp {
+                        addresses: vec!["192.168.1.10/24".into()],
+                        gateway4: Some("192.168.1.1".into()),
+                        ..Default::default()
+                    },
+                }),
+            },
+            boot: BootConfig {
+                add_overlays: vec!["BB-UART1-00A0.dtbo".into()],
+                ..Default::default()
+            },
+            files: vec![File {
+                path: "notes.txt".into(),
+                content: "hello".into(),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let p = profile();
+
+        for ext in ["toml", "json"] {
+            let path = dir.path().join(format!("profile.{ext}"));
+            p.save(&path).unwrap();
+            assert_eq!(Profile::load(&path).unwrap(), p);
+        }
+
+        assert!(matches!(
+            p.save(&dir.path().join("profile.yaml")),
+            Err(ProfileError::UnknownFormat)
+        ));
+    }
+
+    #[test]
+    fn toml_format() {
+        let p: Profile = toml::from_str(
+            r#"
+init_format = "cloud_init"
+hostname = "beagle"
+
+[user]
+name = "beagle"
+password = "temppwd"
+
+[[network.wifis]]
+ssid = "home"
+security = { psk = "secret123" }
+
+[cloud_init]
+packages = ["vim"]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(p.init_format, InitFormat::CloudInit);
+        assert_eq!(p.user.unwrap().password, Password::Plain("temppwd".into()));
+        assert_eq!(
+            p.network.wifis,
+            [sysconf::Wifi::psk("home".into(), "secret123".into())]
+        );
+        assert_eq!(p.cloud_init.packages, ["vim".into()]);
+    }
+
+    #[test]
+    fn validate() {
+        assert!(profile().validate().is_ok());
+
+        let mut p = profile();
+        p.user.as_mut().unwrap().name = "root".into();
+        p.network.wifis[0].security = WifiSecurity::Psk("short".into());
+        match p.validate() {
+            Err(ProfileError::Validation(e)) => {
+                assert!(e.get(validate::Field::Username).is_some());
+                assert!(e.get(validate::Field::WifiPassphrase).is_some());
+            }
+            x => panic!("Unexpected {x:?}"),
+        }
+
+        let mut p = profile();
+        p.network.wifi_country = Some("India".into());
+        let ip = &mut p.network.ethernet.as_mut().unwrap().ip;
+        ip.addresses = vec!["192.168.1.10".into()];
+        ip.gateway4 = Some("fd00::1".into());
+        ip.nameservers = vec!["dns.local".into()];
+        match p.validate() {
+            Err(ProfileError::Validation(e)) => {
+                for field in [
+                    validate::Field::WifiCountry,
+                    validate::Field::IpAddress,
+                    validate::Field::Gateway,
+                    validate::Field::Nameserver,
+                ] {
+                    assert!(e.get(field).is_some(), "{field} in {e}");
+                }
+            }
+            x => panic!("Unexpected {x:?}"),
+        }
+
+        let p = Profile {
+            init_format: InitFormat::CloudInit,
+            ..profile()
+        };
+        assert!(matches!(
+            p.validate(),
+            Err(ProfileError::Invalid {
+                field: "network.wifi_country",
+                ..
+            })
+        ));
+
+        let user = Some(User {
+            name: "beagle".into(),
+            password: Password::Hashed("$6$salt$hash".into()),
+        });
+        for init_format in [InitFormat::Sysconf, InitFormat::Armbian] {
+            let p = Profile {
+                init_format,
+                keymap: None,
+                user: user.clone(),
+                ..profile()
+            };
+            assert!(matches!(
+                p.validate(),
+                Err(ProfileError::Invalid {
+                    field: "user.password_hash",
+                    ..
+                })
+            ));
+        }
+        let p = Profile {
+            init_format: InitFormat::CloudInit,
+            user,
+            network: Default::default(),
+            ..profile()
+        };
+        assert!(p.validate().is_ok());
+
+        let p = Profile {
+            init_format: InitFormat::Armbian,
+            keymap: None,
+            user: None,
+            ..profile()
+        };
+        assert!(matches!(
+            p.validate(),
+            Err(ProfileError::Invalid {
+                field: "ssh_key",
+                ..
+            })
+        ));
+
+        let key = profile().ssh_key.unwrap();
+        let p = Profile {
+            ssh_key: Some(format!("{key}\n{key}\n").into()),
+            ..profile()
+        };
+        assert!(matches!(
+            p.validate(),
+            Err(ProfileError::Invalid {
+                field: "ssh_key",
+                ..
+            })
+        ));
+        let p = Profile {
+            init_format: InitFormat::CloudInit,
+            network: Default::default(),
+            ..p
+        };
+        assert!(p.validate().is_ok());
+
+        let p = Profile {
+            cloud_init: CloudInitOptions {
+                packages: vec!["vim".into()],
+                ..Default::default()
+            },
+            ..profile()
+        };
+        assert!(matches!(
+            p.validate(),
+            Err(ProfileError::Invalid {
+                field: "cloud_init",
+                ..
+            })
+        ));
+
+        let p = Profile {
+            name: Some("../lab".into()),
+            ..profile()
+        };
+        assert!(matches!(p.validate(), Err(ProfileError::InvalidName(_))));
+    }
+
+    #[test]
+    fn to_customization() {
+        let files: Vec<_> = profile()
+            .to_customization()
+            .unwrap()
+            .0
+            .into_iter()
+            .map(|(p, _)| p)
+            .collect();
+
+        assert!(files.contains(&"sysconf.txt".into()));
+        assert!(files.contains(&"services/campus.8021x".into()));
+        assert!(files.contains(&"uEnv.txt".into()));
+        assert!(files.contains(&"notes.txt".into()));
+
+        let p = Profile {
+            init_format: InitFormat::CloudInit,
+            network: sysconf::Network {
+                wifis: vec![sysconf::Wifi::psk("home".into(), "secret123".into())],
+                ..Default::default()
+            },
+            ..profile()
+        };
+        let files: Vec<_> = p
+            .to_customization()
+            .unwrap()
+            .0
+            .into_iter()
+            .map(|(p, _)| p)
+            .collect();
+        assert!(files.contains(&"user-data".into()));
+        assert!(files.contains(&"network-config".into()));
+    }
+
+    #[test]
+    fn first_boot() {
+        let script = FirstBootScript::new("register".into(), "echo hi".into());
+        let files = |p: Profile| -> Vec<Box<str>> {
+            p.to_customization()
+                .unwrap()
+                .0
+                .into_iter()
+                .map(|(p, _)| p)
+                .collect()
+        };
+
+        let p = Profile {
+            first_boot: Some(FirstBootScript {
+                early: true,
+                ..script.clone()
+            }),
+            ..profile()
+        };
+        let sysconf = files(p.clone());
+        assert!(sysconf.contains(&"bb-imager/register".into()));
+        assert!(sysconf.contains(&"bb-imager/firstboot.sh".into()));
+
+        let armbian = files(Profile {
+            init_format: InitFormat::Armbian,
+            keymap: None,
+            ssh_key: None,
+            network: Default::default(),
+            first_boot: Some(script),
+            ..profile()
+        });
+        assert!(armbian.contains(&"bb-imager/register".into()));
+        assert!(armbian.contains(&"armbian_first_run.txt".into()));
+
+        let p = Profile {
+            init_format: InitFormat::CloudInit,
+            network: Default::default(),
+            ..p
+        };
+        let customization = p.to_customization().unwrap();
+        let user_data = customization
+            .0
+            .iter()
+            .find_map(|(p, d)| match d {
+                crate::sd::Content::Replace(x) if p.as_ref() == "user-data" => {
+                    Some(String::from_utf8(x.to_vec()).unwrap())
+                }
+                _ => None,
+            })
+            .unwrap();
+        assert!(user_data.contains("bootcmd:"), "{user_data}");
+    }
+
+    #[test]
+    fn store() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ProfileStore::new(dir.path().join("profiles"));
+
+        assert!(store.list().unwrap().is_empty());
+
+        store.save(&profile()).unwrap();
+        store
+            .save(&Profile {
+                name: Some("other".into()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(store.list().unwrap(), ["lab".into(), "other".into()]);
+        assert_eq!(store.load("lab").unwrap(), profile());
+
+        // Invalid profiles are not saved.
+        assert!(matches!(
+            store.save(&Profile {
+                name: Some("armbian".into()),
+                init_format: InitFormat::Armbian,
+                ..profile()
+            }),
+            Err(ProfileError::Invalid { .. })
+        ));
+        assert_eq!(store.list().unwrap(), ["lab".into(), "other".into()]);
+
+        store.remove("other").unwrap();
+        assert_eq!(store.list().unwrap(), ["lab".into()]);
+        assert!(matches!(
+            store.load("other"),
+            Err(ProfileError::NotFound(_))
+        ));
+        assert!(matches!(
+            store.load("../lab"),
+            Err(ProfileError::InvalidName(_))
+        ));
+    }
+}
@@ -0,0 +1,578 @@
+//! Network configuration for sysconf based images.
+//!
+//! `bbbio-set-sysconf` only supports a single WPA2-Personal network (`iwd_psk_file`), so the rest
+//! is configured by the first boot hook described in [`super::first_boot`]:
+//!
+//! - Wi-Fi networks are written as [iwd] service files in `services/` on the boot partition. The
+//!   hook moves them to `/var/lib/iwd`.
+//! - Static ethernet configuration is installed as a [systemd-networkd] `.network` file, which
+//!   takes precedence over the ones shipped in the image.
+//! - The Wi-Fi regulatory domain is set using `cfg80211.ieee80211_regdom` on the kernel command
+//!   line.
+//!
+//! [iwd]: https://iwd.wiki.kernel.org/networkconfigurationsettings
+//! [systemd-networkd]: https://www.freedesktop.org/software/systemd/man/latest/systemd.network.html
+
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    cloud_init::StaticIp,
+    first_boot::{SYSCONF_BOOT_MOUNT, shell_quote},
+};
+use crate::validate::{self, ValidationErrors};
+
+/// Ethernet interface used if none is specified.
+pub const DEFAULT_ETHERNET_INTERFACE: &str = "eth0";
+
+/// Directory in the boot partition containing iwd service files.
+pub(crate) const SERVICES_DIR: &str = "services";
+/// Directory iwd reads known networks from.
+const IWD_DIR: &str = "/var/lib/iwd";
+/// Directory systemd-networkd reads configuration from.
+const NETWORKD_DIR: &str = "/etc/systemd/network";
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SysconfError {
+    /// `sysconf.txt` has one `key=value` per line, so a line break would start a new key.
+    #[error("{0} cannot contain control characters")]
+    ControlCharacter(&'static str),
+    /// `user_authorized_key` holds a single key.
+    #[error("sysconf only supports a single SSH key")]
+    MultipleSshKeys,
+    /// `bbbio-set-sysconf` only reads the plaintext `user_password`.
+    #[error("sysconf requires a plaintext password")]
+    HashedPassword,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(default)]
+pub struct Network {
+    /// ISO 3166-1 alpha-2 Wi-Fi regulatory domain (e.g. "US")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wifi_country: Option<Box<str>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub wifis: Vec<Wifi>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ethernet: Option<Ethernet>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Wifi {
+    pub ssid: Box<str>,
+    pub security: WifiSecurity,
+    /// Network does not broadcast its SSID
+    #[serde(default)]
+    pub hidden: bool,
+    /// Formats that only support a single network use the one with the highest priority. iwd
+    /// ranks known networks on its own.
+    #[serde(default)]
+    pub priority: i32,
+}
+
+impl Wifi {
+    /// WPA2-Personal network.
+    pub fn psk(ssid: Box<str>, passphrase: Box<str>) -> Self {
+        Self {
+            ssid,
+            security: WifiSecurity::Psk(passphrase),
+            hidden: false,
+            priority: 0,
+        }
+    }
+}
+
+/// Wi-Fi authentication. Certificates are in PEM format and embedded in the service file.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum WifiSecurity {
+    Open,
+    /// WPA2-Personal
+    Psk(Box<str>),
+    /// WPA2-Enterprise with PEAP/MSCHAPv2
+    Peap {
+        identity: Box<str>,
+        password: Box<str>,
+        /// Outer identity. Defaults to `identity`
+        anonymous_identity: Option<Box<str>>,
+        ca_cert: Option<Box<str>>,
+    },
+    /// WPA2-Enterprise with EAP-TLS
+    Tls {
+        identity: Box<str>,
+        ca_cert: Option<Box<str>>,
+        client_cert: Box<str>,
+        client_key: Box<str>,
+        client_key_passphrase: Option<Box<str>>,
+    },
+}
+
+impl WifiSecurity {
+    const fn extension(&self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Psk(_) => "psk",
+            Self::Peap { .. } | Self::Tls { .. } => "8021x",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Ethernet {
+    /// Interface name. Defaults to [`DEFAULT_ETHERNET_INTERFACE`]
+    pub interface: Option<Box<str>>,
+    pub ip: StaticIp,
+}
+
+/// iwd service file name for an SSID.
+///
+/// SSIDs containing anything other than alphanumerics, `-` and `_` are hex encoded with a `=`
+/// prefix, as expected by iwd.
+pub(crate) fn iwd_file_name(ssid: &str, ext: &str) -> String {
+    if !ssid.is_empty()
+        && ssid
+            .chars()
+            .all(|x| x.is_ascii_alphanumeric() || x == '-' || x == '_')
+    {
+        format!("{ssid}.{ext}")
+    } else {
+        format!("={}.{ext}", const_hex::encode(ssid))
+    }
+}
+
+impl Network {
+    pub fn is_empty(&self) -> bool {
+        self.wifi_country.is_none() && self.wifis.is_empty() && self.ethernet.is_none()
+    }
+
+    /// Validate SSIDs, passphrases, the regulatory domain and static IP configuration.
+    pub fn errors(&self) -> ValidationErrors {
+        let mut errors = ValidationErrors::default();
+
+        if let Some(x) = &self.wifi_country {
+            errors.push(validate::wifi_country(x));
+        }
+        for wifi in &self.wifis {
+            errors.push(validate::ssid(&wifi.ssid));
+            if let WifiSecurity::Psk(x) = &wifi.security {
+                errors.push(validate::wpa_passphrase(x));
+            }
+        }
+        if let Some(ethernet) = &self.ethernet {
+            let ip = &ethernet.ip;
+            for x in &ip.addresses {
+                errors.push(validate::ip_cidr(x));
+            }
+            if let Some(x) = &ip.gateway4 {
+                errors.push(validate::gateway4(x));
+            }
+            if let Some(x) = &ip.gateway6 {
+                errors.push(validate::gateway6(x));
+            }
+            for x in &ip.nameservers {
+                errors.push(validate::nameserver(x));
+            }
+        }
+
+        errors
+    }
+
+    /// Wi-Fi networks with their service file names.
+    fn wifis(&self) -> impl Iterator<Item = (String, &Wifi)> {
+        self.wifis
+            .iter()
+            .map(|x| (iwd_file_name(&x.ssid, x.security.extension()), x))
+    }
+
+    /// Values are written to line based configuration files, so control characters are rejected.
+    /// Certificates are the exception, since PEM is multi-line.
+    pub(crate) fn check(&self) -> Result<(), SysconfError> {
+        let check = |name, x: &str| {
+            if x.chars().any(char::is_control) {
+                Err(SysconfError::ControlCharacter(name))
+            } else {
+                Ok(())
+            }
+        };
+
+        if let Some(x) = &self.wifi_country {
+            check("Wi-Fi country", x)?;
+        }
+
+        for wifi in &self.wifis {
+            match &wifi.security {
+                WifiSecurity::Open => {}
+                WifiSecurity::Psk(x) => check("Wi-Fi passphrase", x)?,
+                WifiSecurity::Peap {
+                    identity,
+                    password,
+                    anonymous_identity,
+                    ..
+                } => {
+                    check("Wi-Fi identity", identity)?;
+                    check("Wi-Fi password", password)?;
+                    if let Some(x) = anonymous_identity {
+                        check("Wi-Fi anonymous identity", x)?;
+                    }
+                }
+                WifiSecurity::Tls {
+                    identity,
+                    client_key_passphrase,
+                    ..
+                } => {
+                    check("Wi-Fi identity", identity)?;
+                    if let Some(x) = client_key_passphrase {
+                        check("Wi-Fi client key passphrase", x)?;
+                    }
+                }
+            }
+        }
+
+        if let Some(eth) = &self.ethernet {
+            for x in eth
+                .interface
+                .iter()
+                .chain(&eth.ip.addresses)
+                .chain(&eth.ip.gateway4)
+                .chain(&eth.ip.gateway6)
+                .chain(&eth.ip.nameservers)
+            {
+                check("ethernet", x)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// iwd service files (path in the boot partition, content).
+    pub(crate) fn service_files(&self) -> Vec<(String, String)> {
+        self.wifis()
+            .map(|(name, wifi)| (format!("{SERVICES_DIR}/{name}"), wifi.service_file()))
+            .collect()
+    }
+
+    /// Kernel command line arguments.
+    pub(crate) fn cmdline(&self) -> Vec<Box<str>> {
+        self.wifi_country
+            .iter()
+            .map(|x| format!("cfg80211.ieee80211_regdom={x}").into())
+            .collect()
+    }
+
+    /// Shell commands for the first boot hook, which install the service files and ethernet
+    /// configuration.
+    pub(crate) fn hook_commands(&self) -> String {
+        let mut temp = String::new();
+
+        if !self.wifis.is_empty() {
+            writeln!(temp, "mkdir -p {IWD_DIR}").unwrap();
+        }
+        for (name, _) in self.wifis() {
+            let src = shell_quote(&format!("{SYSCONF_BOOT_MOUNT}/{SERVICES_DIR}/{name}"));
+            writeln!(
+                temp,
+                "install -m 600 {src} {}\nrm -f {src}",
+                shell_quote(&format!("{IWD_DIR}/{name}"))
+            )
+            .unwrap();
+        }
+
+        if let Some(eth) = &self.ethernet {
+            let interface = eth
+                .interface
+                .as_deref()
+                .unwrap_or(DEFAULT_ETHERNET_INTERFACE);
+            let path = shell_quote(&format!("{NETWORKD_DIR}/10-bb-imager-{interface}.network"));
+
+            writeln!(
+                temp,
+                "mkdir -p {NETWORKD_DIR}\ncat > {path} << 'BB_IMAGER_EOF'"
+            )
+            .unwrap();
+            temp.push_str(&eth.networkd_file());
+            temp.push_str("BB_IMAGER_EOF\n");
+        }
+
+        temp
+    }
+}
+
+impl Ethernet {
+    fn networkd_file(&self) -> String {
+        let mut temp = format!(
+            "[Match]\nName={}\n\n[Network]\n",
+            self.interface
+                .as_deref()
+                .unwrap_or(DEFAULT_ETHERNET_INTERFACE)
+        );
+
+        for x in &self.ip.addresses {
+            writeln!(temp, "Address={x}").unwrap();
+        }
+        for x in self.ip.gateway4.iter().chain(&self.ip.gateway6) {
+            writeln!(temp, "Gateway={x}").unwrap();
+        }
+        for x in &self.ip.nameservers {
+            writeln!(temp, "DNS={x}").unwrap();
+        }
+
+        temp
+    }
+}
+
+impl Wifi {
+    fn service_file(&self) -> String {
+        let mut temp = String::new();
+
+        match &self.security {
+            WifiSecurity::Open => {}
+            WifiSecurity::Psk(x) => {
+                writeln!(temp, "[Security]\nPassphrase={x}\n").unwrap();
+            }
+            WifiSecurity::Peap {
+                identity,
+                password,
+                anonymous_identity,
+                ca_cert,
+            } => {
+                temp.push_str("[Security]\nEAP-Method=PEAP\n");
+                writeln!(
+                    temp,
+                    "EAP-Identity={}",
+                    anonymous_identity.as_deref().unwrap_or(identity)
+                )
+                .unwrap();
+                if ca_cert.is_some() {
+                    temp.push_str("EAP-PEAP-CACert=embed:ca_cert\n");
+                }
+                writeln!(
+                    temp,
+                    "EAP-PEAP-Phase2-Method=MSCHAPV2\nEAP-PEAP-Phase2-Identity={identity}\nEAP-PEAP-Phase2-Password={password}\n"
+                )
+                .unwrap();
+            }
+            WifiSecurity::Tls {
+                identity,
+                ca_cert,
+                client_key_passphrase,
+                ..
+            } => {
+                temp.push_str("[Security]\nEAP-Method=TLS\n");
+                writeln!(temp, "EAP-Identity={identity}").unwrap();
+                if ca_cert.is_some() {
+                    temp.push_str("EAP-TLS-CACert=embed:ca_cert\n");
+                }
+                temp.push_str("EAP-TLS-ClientCert=embed:client_cert\n");
+                temp.push_str("EAP-TLS-ClientKey=embed:client_key\n");
+                if let Some(x) = client_key_passphrase {
+                    writeln!(temp, "EAP-TLS-ClientKeyPassphrase={x}").unwrap();
+                }
+                temp.push('\n');
+            }
+        }
+
+        temp.push_str("[Settings]\nAutoConnect=true\n");
+        if self.hidden {
+            temp.push_str("Hidden=true\n");
+        }
+
+        for (name, pem) in self.embedded_pems() {
+            write!(temp, "\n[@pem@{name}]\n{}\n", pem.trim_end()).unwrap();
+        }
+
+        temp
+    }
+
+    fn embedded_pems(&self) -> Vec<(&'static str, &str)> {
+        match &self.security {
+            WifiSecurity::Open | WifiSecurity::Psk(_) => Vec::new(),
+            WifiSecurity::Peap { ca_cert, .. } => {
+                ca_cert.iter().map(|x| ("ca_cert", x.as_ref())).collect()
+            }
+            WifiSecurity::Tls {
+                ca_cert,
+                client_cert,
+                client_key,
+                ..
+            } => ca_cert
+                .iter()
+                .map(|x| ("ca_cert", x.as_ref()))
+                .chain([
+                    ("client_cert", client_cert.as_ref()),
+                    ("client_key", client_key.as_ref()),
+                ])
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_name() {
+        assert_eq!(iwd_file_name("home-net_1", "psk"), "home-net_1.psk");
+        assert_eq!(iwd_file_name("My Net", "open"), "=4d79204e6574.open");
+    }
+
+    #[test]
+    fn psk_hidden() {
+        let wifi = Wifi {
+            hidden: true,
+            ..Wifi::psk("home".into(), "secret".into())
+        };
+
+        assert_eq!(
+            wifi.service_file(),
+            "[Security]\nPassphrase=secret\n\n[Settings]\nAutoConnect=true\nHidden=true\n"
+        );
+    }
+
+    #[test]
+    fn peap() {
+        let wifi = Wifi {
+            ssid: "campus".into(),
+            security: WifiSecurity::Peap {
+                identity: "student".into(),
+                password: "pass".into(),
+                anonymous_identity: Some("anonymous".into()),
+                ca_cert: Some(
+                    "-----BEGIN CERTIFICATE-----\nabc\n-----END CERTIFICATE-----\n".into(),
+                ),
+            },
+            hidden: false,
+            priority: 0,
+        };
+        let expected = "[Security]
+EAP-Method=PEAP
+EAP-Identity=anonymous
+EAP-PEAP-CACert=embed:ca_cert
+EAP-PEAP-Phase2-Method=MSCHAPV2
+EAP-PEAP-Phase2-Identity=student
+EAP-PEAP-Phase2-Password=pass
+
+[Settings]
+AutoConnect=true
+
+[@pem@ca_cert]
+-----BEGIN CERTIFICATE-----
+abc
+-----END CERTIFICATE-----
+";
+
+        assert_eq!(wifi.service_file(), expected);
+    }
+
+    #[test]
+    fn tls() {
+        let wifi = Wifi {
+            ssid: "factory".into(),
+            security: WifiSecurity::Tls {
+                identity: "device".into(),
+                ca_cert: None,
+                client_cert: "CERT".into(),
+                client_key: "KEY".into(),
+                client_key_passphrase: Some("pw".into()),
+            },
+            hidden: false,
+            priority: 0,
+        };
+        let expected = "[Security]
+EAP-Method=TLS
+EAP-Identity=device
+EAP-TLS-ClientCert=embed:client_cert
+EAP-TLS-ClientKey=embed:client_key
+EAP-TLS-ClientKeyPassphrase=pw
+
+[Settings]
+AutoConnect=true
+
+[@pem@client_cert]
+CERT
+
+[@pem@client_key]
+KEY
+";
+
+        assert_eq!(wifi.service_file(), expected);
+    }
+
+    #[test]
+    fn network() {
+        let network = Network {
+            wifi_country: Some("DE".into()),
+            wifis: vec![
+                Wifi::psk("low".into(), "a".into()),
+                Wifi {
+                    priority: 10,
+                    security: WifiSecurity::Open,
+                    ..Wifi::psk("high".into(), "b".into())
+                },
+            ],
+            ethernet: Some(Ethernet {
+                interface: None,
+                ip: StaticIp {
+                    addresses: vec!["192.168.1.10/24".into(), "fd00::10/64".into()],
+                    gateway4: Some("192.168.1.1".into()),
+                    gateway6: None,
+                    nameservers: vec!["1.1.1.1".into()],
+                },
+            }),
+        };
+        let expected = "mkdir -p /var/lib/iwd
+install -m 600 /boot/firmware/services/low.psk /var/lib/iwd/low.psk
+rm -f /boot/firmware/services/low.psk
+install -m 600 /boot/firmware/services/high.open /var/lib/iwd/high.open
+rm -f /boot/firmware/services/high.open
+mkdir -p /etc/systemd/network
+cat > /etc/systemd/network/10-bb-imager-eth0.network << 'BB_IMAGER_EOF'
+[Match]
+Name=eth0
+
+[Network]
+Address=192.168.1.10/24
+Address=fd00::10/64
+Gateway=192.168.1.1
+DNS=1.1.1.1
+BB_IMAGER_EOF
+";
+
+        network.check().unwrap();
+        assert_eq!(network.hook_commands(), expected);
+        assert_eq!(network.cmdline(), ["cfg80211.ieee80211_regdom=DE".into()]);
+        assert_eq!(
+            network
+                .service_files()
+                .iter()
+                .map(|(x, _)| x.as_str())
+                .collect::<Vec<_>>(),
+            ["services/low.psk", "services/high.open"]
+        );
+    }
+
+    #[test]
+    fn control_characters() {
+        let network = Network {
+            wifis: vec![Wifi::psk("home".into(), "secret\n[Settings]".into())],
+            ..Default::default()
+        };
+        assert_eq!(
+            network.check(),
+            Err(SysconfError::ControlCharacter("Wi-Fi passphrase"))
+        );
+
+        let network = Network {
+            ethernet: Some(Ethernet {
+                interface: Some("eth0\nName=*".into()),
+                ip: Default::default(),
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            network.check(),
+            Err(SysconfError::ControlCharacter("ethernet"))
+        );
+    }
+}
@@ -0,0 +1,305 @@
+//! SHA-256 verification of local images.
+//!
+//! The hash is computed over the image file as stored on disk (i.e. the compressed input), which
+//! is what `.sha256` sidecar files and `SHA256SUMS` lists describe.
+
+use sha2::{Digest, Sha256};
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// Names of checksum lists which can contain entries for multiple files in a directory.
+const CHECKSUM_LISTS: &[&str] = &["SHA256SUMS", "sha256sums", "sha256sums.txt"];
+/// Extensions of sidecar files which describe a single file.
+const SIDECAR_EXTENSIONS: &[&str] = &["sha256", "sha256sum"];
+
+/// SHA-256 of the image does not match the expected value.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("Image checksum mismatch. Expected SHA-256 {}, but got {}.", const_hex::encode(.expected), const_hex::encode(.actual))]
+pub struct ChecksumMismatch {
+    pub expected: [u8; 32],
+    pub actual: [u8; 32],
+}
+
+impl From<ChecksumMismatch> for io::Error {
+    fn from(value: ChecksumMismatch) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, value)
+    }
+}
+
+/// Find [`ChecksumMismatch`] in the error chain.
+#[cfg(feature = "sd")]
+pub(crate) fn find_mismatch<'a>(
+    err: &'a (dyn std::error::Error + 'static),
+) -> Option<&'a ChecksumMismatch> {
+    let mut cur = Some(err);
+
+    while let Some(e) = cur {
+        if let Some(x) = e.downcast_ref::<ChecksumMismatch>() {
+            return Some(x);
+        }
+        // `io::Error::source` skips the wrapped custom error.
+        if let Some(x) = e
+            .downcast_ref::<io::Error>()
+            .and_then(|x| x.get_ref())
+            .and_then(|x| x.downcast_ref::<ChecksumMismatch>())
+        {
+            return Some(x);
+        }
+        cur = e.source();
+    }
+
+    None
+}
+
+/// Look for the SHA-256 of `path` in a sidecar checksum file.
+///
+/// The following files are checked in order:
+///
+/// - `<file>.sha256` / `<file>.sha256sum`: Either just the hash, or `sha256sum` output.
+/// - `SHA256SUMS` / `sha256sums` / `sha256sums.txt` in the same directory: `sha256sum` output.
+///
+/// Both GNU (`<hash>  <file>`) and BSD (`SHA256 (<file>) = <hash>`) line formats are supported.
+/// Unreadable or malformed sidecar files are ignored.
+pub fn sidecar_sha256(path: &Path) -> Option<[u8; 32]> {
+    let file_name = path.file_name()?.to_str()?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+
+    let sidecars = SIDECAR_EXTENSIONS
+        .iter()
+        .map(|ext| (dir.join(format!("{file_name}.{ext}")), true));
+    let lists = CHECKSUM_LISTS.iter().map(|x| (dir.join(x), false));
+
+    sidecars.chain(lists).find_map(|(p, single)| {
+        let content = std::fs::read_to_string(&p).ok()?;
+        let res = parse_checksum_file(&content, file_name, single);
+
+        match res {
+            Some(_) => tracing::info!("Using checksum from {}", p.display()),
+            None => tracing::debug!("No checksum for {file_name} in {}", p.display()),
+        }
+
+        res
+    })
+}
+
+fn parse_checksum_file(content: &str, file_name: &str, single: bool) -> Option<[u8; 32]> {
+    let lines: Vec<&str> = content
+        .lines()
+        .map(str::trim)
+        .filter(|x| !x.is_empty() && !x.starts_with('#'))
+        .collect();
+
+    // A sidecar for a single file can contain just the hash.
+    if single
+        && let [line] = lines.as_slice()
+        && let Some(x) = parse_hash(line)
+    {
+        return Some(x);
+    }
+
+    lines.into_iter().find_map(|line| {
+        let (hash, name) = parse_line(line)?;
+        // Entries might contain relative paths.
+        let name = name.rsplit(['/', '\\']).next()?;
+        (name == file_name).then_some(hash)
+    })
+}
+
+fn parse_line(line: &str) -> Option<([u8; 32], &str)> {
+    // BSD: `SHA256 (file) = hash`
+    if let Some(rest) = line.strip_prefix("SHA256 (") {
+        let (name, hash) = rest.rsplit_once(") = ")?;
+        return Some((parse_hash(hash)?, name));
+    }
+
+    // GNU: `hash  file` or `hash *file`
+    let (hash, name) = line.split_once(char::is_whitespace)?;
+    let name = name.trim_start();
+    let name = name.strip_prefix('*').unwrap_or(name);
+
+    Some((parse_hash(hash)?, name))
+}
+
+fn parse_hash(x: &str) -> Option<[u8; 32]> {
+    const_hex::decode_to_array(x.trim()).ok()
+}
+
+#[derive(Debug, Default)]
+struct HashState {
+    hasher: Sha256,
+    /// Number of bytes from the start of the file that have been hashed.
+    hashed: u64,
+}
+
+/// Reader which computes SHA-256 of the underlying source while it is streamed.
+///
+/// Only bytes read sequentially from the start of the source are hashed, so seeking (e.g. while
+/// probing for format) is fine. Anything not seen at the end is hashed by [`Verifier::finish`].
+pub(crate) struct HashingReader<R> {
+    inner: R,
+    pos: u64,
+    state: Option<Arc<Mutex<HashState>>>,
+}
+
+impl<R> HashingReader<R> {
+    /// Reader which does not perform any hashing.
+    pub(crate) const fn passthrough(inner: R) -> Self {
+        Self {
+            inner,
+            pos: 0,
+            state: None,
+        }
+    }
+
+    pub(crate) fn with_verifier(inner: R, path: PathBuf, expected: [u8; 32]) -> (Self, Verifier) {
+        let state = Arc::new(Mutex::new(HashState::default()));
+        let reader = Self {
+            inner,
+            pos: 0,
+            state: Some(state.clone()),
+        };
+
+        (
+            reader,
+            Verifier {
+                state,
+                path,
+                expected,
+            },
+        )
+    }
+
+    pub(crate) const fn get_ref(&self) -> &R {
+        &self.inner
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.inner.read(buf)?;
+
+        if let Some(state) = &self.state {
+            let mut state = state.lock().unwrap();
+            let end = self.pos + count as u64;
+
+            if (self.pos..end).contains(&state.hashed) {
+                let start = usize::try_from(state.hashed - self.pos).unwrap();
+                state.hasher.update(&buf[start..count]);
+                state.hashed = end;
+            }
+        }
+
+        self.pos += count as u64;
+        Ok(count)
+    }
+}
+
+impl<R: Seek> Seek for HashingReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.pos = self.inner.seek(pos)?;
+        Ok(self.pos)
+    }
+}
+
+/// Compares the hash computed by [`HashingReader`] with the expected value.
+pub(crate) struct Verifier {
+    state: Arc<Mutex<HashState>>,
+    path: PathBuf,
+    expected: [u8; 32],
+}
+
+impl Verifier {
+    /// Hash whatever part of the file was not streamed and compare with the expected value.
+    pub(crate) fn finish(self) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(state.hashed))?;
+        let count = io::copy(&mut file, &mut state.hasher)?;
+        state.hashed += count;
+
+        let actual: [u8; 32] = std::mem::take(&mut state.hasher).finalize().into();
+        if actual == self.expected {
+            tracing::info!("Image checksum verified");
+            Ok(())
+        } else {
+            Err(ChecksumMismatch {
+                expected: self.expected,
+                actual,
+            }
+            .into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+
+    fn hash() -> [u8; 32] {
+        const_hex::decode_to_array(HASH).unwrap()
+    }
+
+    #[test]
+    fn parse_bare_sidecar() {
+        assert_eq!(
+            parse_checksum_file(&format!("{HASH}\n"), "a.img.xz", true),
+            Some(hash())
+        );
+        // A list must name the file.
+        assert_eq!(parse_checksum_file(HASH, "a.img.xz", false), None);
+    }
+
+    #[test]
+    fn parse_gnu_list() {
+        let content = format!(
+            "{}  other.img.xz\n{HASH} *images/a.img.xz\n",
+            "00".repeat(32)
+        );
+        assert_eq!(
+            parse_checksum_file(&content, "a.img.xz", false),
+            Some(hash())
+        );
+        assert_eq!(parse_checksum_file(&content, "b.img.xz", false), None);
+    }
+
+    #[test]
+    fn parse_bsd_list() {
+        let content = format!("SHA256 (a.img.xz) = {HASH}\n");
+        assert_eq!(
+            parse_checksum_file(&content, "a.img.xz", false),
+            Some(hash())
+        );
+    }
+
+    #[test]
+    fn hashing_reader_with_seeks() {
+        let data: Vec<u8> = (0..10000u32).map(|x| (x % 251) as u8).collect();
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, &data).unwrap();
+
+        let expected: [u8; 32] = Sha256::digest(&data).into();
+        let (mut reader, verifier) = HashingReader::with_verifier(
+            File::open(file.path()).unwrap(),
+            file.path().to_path_buf(),
+            expected,
+        );
+
+        // Probe, jump around and only read part of the file.
+        let mut buf = [0u8; 100];
+        reader.read_exact(&mut buf).unwrap();
+        reader.seek(SeekFrom::End(-50)).unwrap();
+        reader.read_exact(&mut buf[..50]).unwrap();
+        reader.rewind().unwrap();
+        let mut buf = vec![0u8; 4000];
+        reader.read_exact(&mut buf).unwrap();
+
+        verifier.finish().unwrap();
+    }
+}
@@ -14,9 +14,15 @@ use std::{
 #[cfg(feature = "piped_image")]
 use tokio_util::task::AbortOnDropHandle;
 
+mod checksum;
 #[cfg(test)]
 mod test;
 
+#[cfg(feature = "sd")]
+pub(crate) use checksum::find_mismatch;
+pub use checksum::{ChecksumMismatch, sidecar_sha256};
+use checksum::{HashingReader, Verifier};
+
 const XZ_MAGIC: [u8; 6] = [0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00];
 
 #[cfg(feature = "sd")]
@@ -123,13 +129,33 @@ impl OsArchiveCompression {
 
 pub struct OsImage {
     size: u64,
-    img: OsImageCompression<OsImageSource>,
+    img: OsImageCompression<HashingReader<OsImageSource>>,
+    verifier: Option<Verifier>,
 }
 
 impl OsImage {
     pub fn from_path(path: &Path) -> io::Result<Self> {
-        let file = std::fs::File::open(path)?;
-        let mut img = OsImageCompression::new(OsImageSource::from(file))?;
+        Self::from_path_internal(path, None)
+    }
+
+    /// Open image and verify that the SHA-256 of the file matches `sha256`.
+    ///
+    /// The hash is computed while the image is being read. Reaching the end of the image returns
+    /// an error with [`ChecksumMismatch`] in case of mismatch.
+    pub fn from_path_with_sha256(path: &Path, sha256: [u8; 32]) -> io::Result<Self> {
+        Self::from_path_internal(path, Some(sha256))
+    }
+
+    fn from_path_internal(path: &Path, sha256: Option<[u8; 32]>) -> io::Result<Self> {
+        let file = OsImageSource::from(std::fs::File::open(path)?);
+        let (file, verifier) = match sha256 {
+            Some(x) => {
+                let (file, verifier) = HashingReader::with_verifier(file, path.to_path_buf(), x);
+                (file, Some(verifier))
+            }
+            None => (HashingReader::passthrough(file), None),
+        };
+        let mut img = OsImageCompression::new(file)?;
 
         let size = match &mut img {
             OsImageCompression::Xz(x) => {
@@ -138,7 +164,7 @@ impl OsImage {
                 size
             }
             OsImageCompression::Zip(x) => x.entry().uncompressed_size,
-            OsImageCompression::Uncompressed(x) => match x.get_ref() {
+            OsImageCompression::Uncompressed(x) => match x.get_ref().get_ref() {
                 OsImageSource::File(file) => file.metadata()?.len(),
                 #[cfg(feature = "piped_image")]
                 OsImageSource::FileStream { .. } => unreachable!(),
@@ -146,7 +172,11 @@ impl OsImage {
             OsImageCompression::QCow2(x) => x.virtual_disk_size(),
         };
 
-        Ok(Self { size, img })
+        Ok(Self {
+            size,
+            img,
+            verifier,
+        })
     }
 
     #[cfg(feature = "piped_image")]
@@ -157,10 +187,11 @@ impl OsImage {
     ) -> io::Result<Self> {
         Ok(Self {
             size,
-            img: OsImageCompression::new(OsImageSource::FileStream {
+            img: OsImageCompression::new(HashingReader::passthrough(OsImageSource::FileStream {
                 reader: img,
                 _background,
-            })?,
+            }))?,
+            verifier: None,
         })
     }
 
@@ -171,12 +202,23 @@ impl OsImage {
 
 impl Read for OsImage {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        match &mut self.img {
-            OsImageCompression::Xz(x) => x.read(buf),
-            OsImageCompression::Zip(x) => x.read(buf),
-            OsImageCompression::Uncompressed(x) => x.read(buf),
-            OsImageCompression::QCow2(x) => x.read(buf),
+        let count = self.img.read(buf)?;
+
+        if count == 0
+            && !buf.is_empty()
+            && let Some(v) = self.verifier.take()
+        {
+            v.finish()?;
         }
+
+        Ok(count)
+    }
+}
+
+#[cfg(feature = "sd")]
+impl bb_flasher_sd::Image for OsImage {
+    fn needs_full_read(&self) -> bool {
+        self.verifier.is_some()
     }
 }
 
@@ -15,7 +15,8 @@
 //!     let img = bb_flasher::LocalImage::new(PathBuf::from("/tmp/abc.img.xz").into());
 //!     let target = PathBuf::from("/tmp/target").try_into().unwrap();
 //!     let customization =
-//!         bb_flasher::sd::FlashingSdLinuxConfig::sysconfig(None, None, None, None, None, None, None);
+//!         bb_flasher::sd::FlashingSdLinuxConfig::sysconfig(None, None, None, None, None, None, None)
+//!             .unwrap();
 //!
 //!     let flasher = bb_flasher::sd::Flasher::without_bmap(img.into_image_future(), target, customization, None)
 //!         .flash(None)
@@ -36,10 +37,13 @@
 //!   USB to UART bridge.
 //! - `pb2_mspm0`: Provides support to flash PocketBeagle 2 MSPM0. Needs root permissions.
 //! - `pb2_mspm0_dbus`: Use bb-imager-serivce to flash PocketBeagle 2 as a normal user.
+//! - `validate`: Validation of Linux image customization inputs. Enabled by `sd`.
 
 mod common;
 mod flasher;
 pub mod img;
+#[cfg(feature = "validate")]
+pub mod validate;
 
 use std::path::Path;
 
@@ -47,27 +51,46 @@ pub use common::*;
 pub use flasher::*;
 
 /// An Os Image present in the local filesystem
+///
+/// # Checksum
+///
+/// The image is verified against a SHA-256, which is either provided explicitly using
+/// [`LocalImage::with_sha256`], or discovered from a sidecar checksum file next to the image (see
+/// [`img::sidecar_sha256`]). Reading the image fails with [`img::ChecksumMismatch`] if it does not
+/// match.
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize))]
-pub struct LocalImage(Box<Path>);
+pub struct LocalImage {
+    path: Box<Path>,
+    sha256: Option<[u8; 32]>,
+}
 
 impl LocalImage {
     /// Construct a new local image from path.
     pub const fn new(path: Box<Path>) -> Self {
-        Self(path)
+        Self { path, sha256: None }
+    }
+
+    /// Expected SHA-256 of the image file. Takes precedence over sidecar checksum files.
+    pub const fn with_sha256(mut self, sha256: [u8; 32]) -> Self {
+        self.sha256 = Some(sha256);
+        self
     }
 
     pub fn path(&self) -> &Path {
-        &self.0
+        &self.path
     }
 
     pub fn file_name(&self) -> &std::ffi::OsStr {
-        self.0.file_name().unwrap()
+        self.path.file_name().unwrap()
     }
 
     pub fn into_image_fn(self) -> impl FnOnce() -> std::io::Result<(img::OsImage, u64)> {
         move || {
-            let img = img::OsImage::from_path(&self.0)?;
+            let img = match self.sha256.or_else(|| img::sidecar_sha256(&self.path)) {
+                Some(x) => img::OsImage::from_path_with_sha256(&self.path, x)?,
+                None => img::OsImage::from_path(&self.path)?,
+            };
             let size = img.size();
 
             Ok((img, size))
@@ -79,7 +102,7 @@ impl LocalImage {
         self,
         tx: Option<std::sync::mpsc::SyncSender<f32>>,
     ) -> impl FnOnce() -> std::io::Result<img::OsArchive> {
-        move || img::OsArchive::from_path(&self.0, tx)
+        move || img::OsArchive::from_path(&self.path, tx)
     }
 }
 
@@ -88,7 +111,7 @@ impl std::fmt::Display for LocalImage {
         write!(
             f,
             "{}",
-            self.0
+            self.path
                 .file_name()
                 .expect("image cannot be a directory")
                 .to_string_lossy()
@@ -0,0 +1,537 @@
+//! Validation of Linux image customization inputs.
+//!
+//! Values end up in configuration files such as `sysconf.txt`, which are parsed by shell scripts
+//! on first boot, so invalid values are rejected before flashing instead of failing silently on
+//! the device.
+//!
+//! Also available as `bb_flasher::sd::validate`.
+
+use std::{
+    fmt::Display,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+
+use base64::Engine;
+
+/// Keyboard layouts supported by customization, in byte order.
+pub const KEYMAP_LAYOUTS: &[&str] = &[
+    "af", "al", "am", "ara", "at", "au", "az", "ba", "bd", "be", "bg", "br", "brai", "bt", "bw",
+    "by", "ca", "cd", "ch", "cm", "cn", "cz", "de", "dk", "dz", "ee", "epo", "es", "et", "fi",
+    "fo", "fr", "gb", "ge", "gh", "gn", "gr", "hr", "hu", "id", "ie", "il", "in", "iq", "ir", "is",
+    "it", "jp", "jv", "ke", "kg", "kh", "kr", "kz", "la", "latam", "lk", "lt", "lv", "ma", "mao",
+    "md", "me", "mk", "ml", "mm", "mn", "mt", "mv", "my", "ng", "nl", "no", "np", "ph", "pk", "pl",
+    "pt", "ro", "rs", "ru", "se", "si", "sk", "sn", "sy", "tg", "th", "tj", "tm", "tr", "tw", "tz",
+    "ua", "us", "uz", "vn", "za",
+];
+
+/// Public key algorithms accepted by OpenSSH.
+const SSH_KEY_TYPES: &[&str] = &[
+    "ecdsa-sha2-nistp256",
+    "ecdsa-sha2-nistp384",
+    "ecdsa-sha2-nistp521",
+    "sk-ecdsa-sha2-nistp256@openssh.com",
+    "sk-ssh-ed25519@openssh.com",
+    "ssh-dss",
+    "ssh-ed25519",
+    "ssh-rsa",
+];
+
+/// Characters with special meaning in shell scripts.
+const SHELL_UNSAFE: &[char] = &['"', '\'', '`', '$', '\\'];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Field {
+    Hostname,
+    Timezone,
+    Keymap,
+    Username,
+    Password,
+    Ssid,
+    WifiPassphrase,
+    SshKey,
+    WifiCountry,
+    IpAddress,
+    Gateway,
+    Nameserver,
+}
+
+impl Display for Field {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Hostname => write!(f, "hostname"),
+            Self::Timezone => write!(f, "timezone"),
+            Self::Keymap => write!(f, "keymap"),
+            Self::Username => write!(f, "username"),
+            Self::Password => write!(f, "password"),
+            Self::Ssid => write!(f, "SSID"),
+            Self::WifiPassphrase => write!(f, "Wi-Fi passphrase"),
+            Self::SshKey => write!(f, "SSH key"),
+            Self::WifiCountry => write!(f, "Wi-Fi country"),
+            Self::IpAddress => write!(f, "IP address"),
+            Self::Gateway => write!(f, "gateway"),
+            Self::Nameserver => write!(f, "DNS server"),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq, Hash)]
+#[error("Invalid {field}: {reason}")]
+pub struct FieldError {
+    pub field: Field,
+    pub reason: Box<str>,
+}
+
+impl FieldError {
+    fn new(field: Field, reason: impl Into<Box<str>>) -> Self {
+        Self {
+            field,
+            reason: reason.into(),
+        }
+    }
+}
+
+/// All fields that failed validation.
+#[derive(thiserror::Error, Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ValidationErrors(pub Vec<FieldError>);
+
+impl Display for ValidationErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, e) in self.0.iter().enumerate() {
+            if i != 0 {
+                write!(f, "; ")?;
+            }
+            e.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl ValidationErrors {
+    pub fn push(&mut self, res: Result<(), FieldError>) {
+        if let Err(e) = res {
+            self.0.push(e);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// First error for `field`.
+    pub fn get(&self, field: Field) -> Option<&FieldError> {
+        self.0.iter().find(|x| x.field == field)
+    }
+
+    pub fn into_result(self) -> Result<(), Self> {
+        if self.is_empty() { Ok(()) } else { Err(self) }
+    }
+}
+
+type Validator = fn(&str) -> Result<(), FieldError>;
+
+/// Customization inputs to validate together. Fields set to `None` are skipped.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Inputs<'a> {
+    pub hostname: Option<&'a str>,
+    pub timezone: Option<&'a str>,
+    pub keymap: Option<&'a str>,
+    pub username: Option<&'a str>,
+    /// Plaintext password. Password hashes are not validated.
+    pub password: Option<&'a str>,
+    pub ssid: Option<&'a str>,
+    pub wifi_passphrase: Option<&'a str>,
+    pub ssh_key: Option<&'a str>,
+}
+
+impl Inputs<'_> {
+    pub fn errors(&self) -> ValidationErrors {
+        let mut errors = ValidationErrors::default();
+
+        let checks: [(Option<&str>, Validator); 8] = [
+            (self.hostname, hostname),
+            (self.timezone, timezone),
+            (self.keymap, keymap),
+            (self.username, username),
+            (self.password, password),
+            (self.ssid, ssid),
+            (self.wifi_passphrase, wpa_passphrase),
+            (self.ssh_key, ssh_public_key),
+        ];
+        for (val, check) in checks {
+            if let Some(x) = val {
+                errors.push(check(x));
+            }
+        }
+
+        errors
+    }
+
+    pub fn validate(&self) -> Result<(), ValidationErrors> {
+        self.errors().into_result()
+    }
+}
+
+/// Hostname as per [RFC 1123]: dot separated labels of 1-63 alphanumerics or `-`, not starting or
+/// ending with `-`, with at most 253 characters in total.
+///
+/// [RFC 1123]: https://datatracker.ietf.org/doc/html/rfc1123#page-13
+pub fn hostname(x: &str) -> Result<(), FieldError> {
+    let err = |reason: &str| Err(FieldError::new(Field::Hostname, reason));
+
+    if x.is_empty() {
+        return err("Hostname cannot be empty");
+    }
+    if x.len() > 253 {
+        return err("Hostname cannot be longer than 253 characters");
+    }
+
+    for label in x.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return err("Each label must be between 1 and 63 characters");
+        }
+        if !label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return err("Only letters, digits and '-' are allowed");
+        }
+        if label.starts_with('-') || label.ends_with('-') {
+            return err("Labels cannot start or end with '-'");
+        }
+    }
+
+    Ok(())
+}
+
+/// IANA time zone name, e.g. `America/New_York`.
+pub fn timezone(x: &str) -> Result<(), FieldError> {
+    x.parse::<chrono_tz::Tz>()
+        .map(|_| ())
+        .map_err(|_| FieldError::new(Field::Timezone, format!("Unknown time zone {x:?}")))
+}
+
+/// Keyboard layout from [`KEYMAP_LAYOUTS`].
+pub fn keymap(x: &str) -> Result<(), FieldError> {
+    KEYMAP_LAYOUTS
+        .binary_search(&x)
+        .map(|_| ())
+        .map_err(|_| FieldError::new(Field::Keymap, format!("Unknown keymap {x:?}")))
+}
+
+/// Portable Linux username: lowercase letter or `_`, followed by up to 31 lowercase letters,
+/// digits, `_` or `-`. `root` is not allowed.
+pub fn username(x: &str) -> Result<(), FieldError> {
+    let err = |reason: &str| Err(FieldError::new(Field::Username, reason));
+
+    if x.is_empty() {
+        return err("Username cannot be empty");
+    }
+    if x == "root" {
+        return err("Username cannot be root");
+    }
+    if x.len() > 32 {
+        return err("Username cannot be longer than 32 characters");
+    }
+    if !x.starts_with(|c: char| c.is_ascii_lowercase() || c == '_') {
+        return err("Username must start with a lowercase letter or '_'");
+    }
+    if !x
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+    {
+        return err("Only lowercase letters, digits, '_' and '-' are allowed");
+    }
+
+    Ok(())
+}
+
+/// Plaintext user password without control characters.
+pub fn password(x: &str) -> Result<(), FieldError> {
+    let err = |reason: &str| Err(FieldError::new(Field::Password, reason));
+
+    if x.is_empty() {
+        return err("Password cannot be empty");
+    }
+    if x.chars().any(char::is_control) {
+        return err("Password cannot contain control characters");
+    }
+
+    Ok(())
+}
+
+/// Wi-Fi SSID: 1-32 bytes without control or shell special characters.
+pub fn ssid(x: &str) -> Result<(), FieldError> {
+    let err = |reason: &str| Err(FieldError::new(Field::Ssid, reason));
+
+    if x.is_empty() {
+        return err("SSID cannot be empty");
+    }
+    if x.len() > 32 {
+        return err("SSID cannot be longer than 32 bytes");
+    }
+    if x.chars()
+        .any(|c| c.is_control() || SHELL_UNSAFE.contains(&c))
+    {
+        return err("SSID cannot contain control characters or any of \" ' ` $ \\");
+    }
+
+    Ok(())
+}
+
+/// WPA passphrase: 8-63 printable ASCII characters, or a raw PSK of 64 hex digits.
+pub fn wpa_passphrase(x: &str) -> Result<(), FieldError> {
+    let err = |reason: &str| Err(FieldError::new(Field::WifiPassphrase, reason));
+
+    if x.len() == 64 {
+        return if x.chars().all(|c| c.is_ascii_hexdigit()) {
+            Ok(())
+        } else {
+            err("64 character passphrases must be hex encoded PSKs")
+        };
+    }
+    if !(8..=63).contains(&x.len()) {
+        return err("Passphrase must be between 8 and 63 characters");
+    }
+    if !x.chars().all(|c| c == ' ' || c.is_ascii_graphic()) {
+        return err("Passphrase can only contain printable ASCII characters");
+    }
+
+    Ok(())
+}
+
+/// Wi-Fi regulatory domain as an ISO 3166-1 alpha-2 country code, e.g. `US`.
+pub fn wifi_country(x: &str) -> Result<(), FieldError> {
+    if x.len() == 2 && x.chars().all(|c| c.is_ascii_uppercase()) {
+        Ok(())
+    } else {
+        Err(FieldError::new(
+            Field::WifiCountry,
+            format!("Expected a two letter uppercase country code, got {x:?}"),
+        ))
+    }
+}
+
+/// IPv4 or IPv6 address with prefix length in CIDR notation, e.g. `192.168.1.10/24`.
+pub fn ip_cidr(x: &str) -> Result<(), FieldError> {
+    let err = || {
+        Err(FieldError::new(
+            Field::IpAddress,
+            format!("Expected an address with prefix length like 192.168.1.10/24, got {x:?}"),
+        ))
+    };
+
+    let Some((addr, prefix)) = x.split_once('/') else {
+        return err();
+    };
+    let (Ok(addr), Ok(prefix)) = (addr.parse::<IpAddr>(), prefix.parse::<u8>()) else {
+        return err();
+    };
+    let max = if addr.is_ipv4() { 32 } else { 128 };
+    if prefix > max {
+        return err();
+    }
+
+    Ok(())
+}
+
+/// IPv4 default gateway.
+pub fn gateway4(x: &str) -> Result<(), FieldError> {
+    x.parse::<Ipv4Addr>()
+        .map(|_| ())
+        .map_err(|_| FieldError::new(Field::Gateway, format!("Invalid IPv4 address {x:?}")))
+}
+
+/// IPv6 default gateway.
+pub fn gateway6(x: &str) -> Result<(), FieldError> {
+    x.parse::<Ipv6Addr>()
+        .map(|_| ())
+        .map_err(|_| FieldError::new(Field::Gateway, format!("Invalid IPv6 address {x:?}")))
+}
+
+/// IPv4 or IPv6 DNS server address.
+pub fn nameserver(x: &str) -> Result<(), FieldError> {
+    x.parse::<IpAddr>()
+        .map(|_| ())
+        .map_err(|_| FieldError::new(Field::Nameserver, format!("Invalid IP address {x:?}")))
+}
+
+/// OpenSSH public keys (`<type> <base64 key> [comment]`), one per line.
+///
+/// Formats that only support a single key (e.g. sysconf) reject multiple lines when generating
+/// the configuration.
+pub fn ssh_public_key(x: &str) -> Result<(), FieldError> {
+    let err = |reason: String| Err(FieldError::new(Field::SshKey, reason));
+
+    let mut lines = x
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .peekable();
+    if lines.peek().is_none() {
+        return err("SSH key cannot be empty".to_string());
+    }
+
+    for line in lines {
+        if line.chars().any(|c| c.is_control() && c != '\t') {
+            return err("SSH key cannot contain control characters".to_string());
+        }
+
+        let mut parts = line.split_ascii_whitespace();
+        let (Some(kind), Some(data)) = (parts.next(), parts.next()) else {
+            return err("Expected \"<type> <base64 key> [comment]\"".to_string());
+        };
+
+        if !SSH_KEY_TYPES.contains(&kind) {
+            return err(format!("Unsupported key type {kind:?}"));
+        }
+
+        let Ok(blob) = base64::engine::general_purpose::STANDARD.decode(data) else {
+            return err(format!("Key data for {kind} is not valid base64"));
+        };
+
+        // The key blob starts with the key type as a length prefixed string.
+        let embedded = blob
+            .get(..4)
+            .map(|x| u32::from_be_bytes(x.try_into().unwrap()) as usize)
+            .and_then(|len| blob.get(4..4 + len));
+        if embedded != Some(kind.as_bytes()) {
+            return err(format!("Key data does not match key type {kind}"));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ED25519: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAABAgMEBQYHCAkKCwwNDg8QERITFBUWFxgZGhscHR4f user@host";
+
+    #[test]
+    fn keymap_layouts_sorted() {
+        assert!(KEYMAP_LAYOUTS.is_sorted());
+    }
+
+    #[test]
+    fn hostnames() {
+        assert!(hostname("beaglebone").is_ok());
+        assert!(hostname("bb-1.local").is_ok());
+        assert!(hostname("").is_err());
+        assert!(hostname("-beagle").is_err());
+        assert!(hostname("beagle_bone").is_err());
+        assert!(hostname("a..b").is_err());
+        assert!(hostname(&"a".repeat(64)).is_err());
+        assert!(hostname("beagle\nuser_name=x").is_err());
+    }
+
+    #[test]
+    fn timezones() {
+        assert!(timezone("America/New_York").is_ok());
+        assert!(timezone("UTC").is_ok());
+        assert!(timezone("Mars/Olympus").is_err());
+    }
+
+    #[test]
+    fn keymaps() {
+        assert!(keymap("us").is_ok());
+        assert!(keymap("US").is_err());
+    }
+
+    #[test]
+    fn usernames() {
+        assert!(username("beagle").is_ok());
+        assert!(username("_svc-1").is_ok());
+        assert_eq!(
+            username("root").unwrap_err().reason.as_ref(),
+            "Username cannot be root"
+        );
+        assert!(username("Beagle").is_err());
+        assert!(username("1beagle").is_err());
+        assert!(username("bea gle").is_err());
+        assert!(username("beagle\n").is_err());
+    }
+
+    #[test]
+    fn passwords() {
+        assert!(password("temp pwd!").is_ok());
+        assert!(password("").is_err());
+        assert!(password("temppwd\nuser_name=root").is_err());
+        assert!(password("temp\tpwd").is_err());
+    }
+
+    #[test]
+    fn ssids() {
+        assert!(ssid("My Home Wi-Fi").is_ok());
+        assert!(ssid("").is_err());
+        assert!(ssid("net$(reboot)").is_err());
+        assert!(ssid("a\nb").is_err());
+        assert!(ssid(&"a".repeat(33)).is_err());
+    }
+
+    #[test]
+    fn passphrases() {
+        assert!(wpa_passphrase("12345678").is_ok());
+        assert!(wpa_passphrase(&"ab".repeat(32)).is_ok());
+        assert!(wpa_passphrase("1234567").is_err());
+        assert!(wpa_passphrase(&"x".repeat(64)).is_err());
+        assert!(wpa_passphrase("pässword").is_err());
+    }
+
+    #[test]
+    fn ssh_keys() {
+        assert!(ssh_public_key(ED25519).is_ok());
+        assert!(ssh_public_key(&format!("{ED25519}\n\n{ED25519}")).is_ok());
+        assert!(ssh_public_key("").is_err());
+        assert!(ssh_public_key("ssh-ed25519").is_err());
+        assert!(ssh_public_key(&format!("{ED25519}\x1b[2J")).is_err());
+        assert!(ssh_public_key("ssh-foo AAAA").is_err());
+        assert!(ssh_public_key("ssh-ed25519 not-base64!").is_err());
+        // Valid base64, but the key is an ed25519 key.
+        assert!(ssh_public_key(&ED25519.replace("ssh-ed25519", "ssh-rsa")).is_err());
+    }
+
+    #[test]
+    fn wifi_countries() {
+        assert!(wifi_country("IN").is_ok());
+        assert!(wifi_country("in").is_err());
+        assert!(wifi_country("IND").is_err());
+        assert!(wifi_country("I\n").is_err());
+    }
+
+    #[test]
+    fn ip_addresses() {
+        assert!(ip_cidr("192.168.1.10/24").is_ok());
+        assert!(ip_cidr("fd00::10/64").is_ok());
+        assert!(ip_cidr("192.168.1.10").is_err());
+        assert!(ip_cidr("192.168.1.10/33").is_err());
+        assert!(ip_cidr("fd00::10/129").is_err());
+        assert!(ip_cidr("beagle/24").is_err());
+
+        assert!(gateway4("192.168.1.1").is_ok());
+        assert!(gateway4("fd00::1").is_err());
+        assert!(gateway6("fd00::1").is_ok());
+        assert!(gateway6("192.168.1.1").is_err());
+
+        assert!(nameserver("1.1.1.1").is_ok());
+        assert!(nameserver("2606:4700:4700::1111").is_ok());
+        assert!(nameserver("one.one.one.one").is_err());
+    }
+
+    #[test]
+    fn inputs() {
+        assert!(Inputs::default().validate().is_ok());
+
+        let errors = Inputs {
+            hostname: Some("beagle"),
+            username: Some("root"),
+            wifi_passphrase: Some("short"),
+            ..Default::default()
+        }
+        .validate()
+        .unwrap_err();
+
+        assert_eq!(errors.0.len(), 2);
+        assert!(errors.get(Field::Username).is_some());
+        assert!(errors.get(Field::WifiPassphrase).is_some());
+        assert!(errors.get(Field::Hostname).is_none());
+        assert!(errors.to_string().starts_with("Invalid username: "));
+    }
+}
@@ -43,6 +43,35 @@ fn local_image_into_image_fn_reads_uncompressed_contents() {
     assert_eq!(out, data);
 }
 
+#[test]
+fn local_image_verifies_sidecar_checksum() {
+    const IMAGE_SHA256: &str = "7955cab91c15dc53fee26fd692e06473adf752fafe4c7c9e87886361b97edd89";
+    const SIDECAR_SHA256: &str = "23ddd9e04b8a9f0c8ea6fbbfb4ea04b4d1ab7f8f5b8a2c9b9e8e85ee5e68d0de";
+
+    let dir = tempfile::tempdir().unwrap();
+    let data = b"raw uncompressed image payload";
+    let path = write_temp(dir.path(), "os.img", data);
+    let sums = format!("{IMAGE_SHA256}  other.img\n{SIDECAR_SHA256}  os.img\n");
+    write_temp(dir.path(), "SHA256SUMS", sums.as_bytes());
+
+    let sha256 = bb_flasher::img::sidecar_sha256(&path).unwrap();
+    assert_eq!(const_hex::encode(sha256), SIDECAR_SHA256);
+
+    // Mismatch is reported once the whole image has been read.
+    let (mut img, _) = LocalImage::new(path.clone().into_boxed_path()).into_image_fn()().unwrap();
+    let err = img.read_to_end(&mut Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    // Explicit checksum takes precedence over the sidecar.
+    let (mut img, _) = LocalImage::new(path.into_boxed_path())
+        .with_sha256(const_hex::decode_to_array(IMAGE_SHA256).unwrap())
+        .into_image_fn()()
+    .unwrap();
+    let mut out = Vec::new();
+    img.read_to_end(&mut out).unwrap();
+    assert_eq!(out, data);
+}
+
 /// Build an uncompressed tar containing one directory and one file.
 fn build_tar() -> Vec<u8> {
     let mut bytes = Vec::new();
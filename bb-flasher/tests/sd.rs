@@ -103,8 +103,141 @@ fn flash_cancel() {
     assert!(res.is_err());
 }
 
+#[test]
+fn flash_checksum_mismatch() {
+    let sd = NamedTempFile::new().unwrap();
+    let mut img = NamedTempFile::new().unwrap();
+    img.write_all(&mock_img_data()).unwrap();
+    img.flush().unwrap();
+
+    let res = bb_flasher::sd::Flasher::with_file_dest(
+        bb_flasher::LocalImage::new(img.path().into())
+            .with_sha256([0; 32])
+            .into_image_fn(),
+        None::<Box<dyn FnOnce() -> std::io::Result<Box<str>> + Send>>,
+        sd.path().to_path_buf(),
+        FlashingSdLinuxConfig::none(),
+    )
+    .flash(None, None);
+
+    let err = res.unwrap_err();
+    let err = err
+        .downcast_ref::<bb_flasher::img::ChecksumMismatch>()
+        .unwrap();
+    assert_eq!(err.expected, [0; 32]);
+}
+
 #[test]
 fn destinations() {
     let temp = bb_flasher::sd::Target::destinations(false);
     assert!(!temp.count() > 0);
 }
+
+#[test]
+fn customize_flashed_card() {
+    let sd = bb_flasher_sd::mock_sd::MockSd::new();
+
+    let customize = |config| {
+        bb_flasher::sd::Customizer::with_file_dest(sd.path().to_path_buf(), config)
+            .customize(None)
+            .unwrap()
+    };
+    customize(
+        FlashingSdLinuxConfig::sysconfig(
+            Some("first".into()),
+            None,
+            Some("us".into()),
+            None,
+            Some(("home".into(), "password1".into())),
+            None,
+            None,
+        )
+        .unwrap(),
+    );
+    // Change the Wi-Fi credentials of the already customized card.
+    customize(
+        FlashingSdLinuxConfig::sysconfig(
+            Some("second".into()),
+            None,
+            None,
+            None,
+            Some(("home".into(), "password2".into())),
+            None,
+            None,
+        )
+        .unwrap(),
+    );
+
+    let current = bb_flasher::sd::CurrentConfig::read_file_dest(sd.path().to_path_buf()).unwrap();
+    assert_eq!(
+        current.file("sysconf.txt"),
+        Some("keymap=us\nhostname=second\niwd_psk_file=home.psk\n")
+    );
+    assert_eq!(current.sysconf("hostname"), Some("second"));
+    assert_eq!(
+        current.file("services/home.psk"),
+        Some("[Security]\nPassphrase=password2\n\n[Settings]\nAutoConnect=true")
+    );
+    assert_eq!(current.file("user-data"), None);
+}
+
+#[test]
+fn sysconfig_rejects_line_breaks() {
+    const KEY: &str =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAABAgMEBQYHCAkKCwwNDg8QERITFBUWFxgZGhscHR4f";
+
+    let sysconfig = |hostname: &str, ssh: &str| {
+        FlashingSdLinuxConfig::sysconfig(
+            Some(hostname.into()),
+            None,
+            None,
+            Some((
+                "beagle".into(),
+                bb_flasher::sd::Password::Plain("temppwd".into()),
+            )),
+            None,
+            Some(ssh.into()),
+            None,
+        )
+    };
+
+    assert!(sysconfig("beagle", &format!("{KEY}\n")).is_ok());
+    assert_eq!(
+        sysconfig("beagle\nuser_password=x", KEY),
+        Err(bb_flasher::sd::SysconfError::ControlCharacter("hostname"))
+    );
+    assert_eq!(
+        sysconfig("beagle", &format!("{KEY}\n{KEY}")),
+        Err(bb_flasher::sd::SysconfError::MultipleSshKeys)
+    );
+}
+
+#[test]
+fn sysconfig_rejects_hashed_password() {
+    let user = |password| Some(("beagle".into(), password));
+
+    assert_eq!(
+        FlashingSdLinuxConfig::sysconfig(
+            None,
+            None,
+            None,
+            user(bb_flasher::sd::Password::Plain("temppwd".into()).hash()),
+            None,
+            None,
+            None,
+        ),
+        Err(bb_flasher::sd::SysconfError::HashedPassword)
+    );
+    assert!(
+        FlashingSdLinuxConfig::sysconfig(
+            None,
+            None,
+            None,
+            user(bb_flasher::sd::Password::Plain("temppwd".into())),
+            None,
+            None,
+            None,
+        )
+        .is_ok()
+    );
+}
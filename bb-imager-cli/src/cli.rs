@@ -51,6 +51,42 @@ pub enum Commands {
         quiet: bool,
     },
 
+    /// Command to import a directory of images into the download cache of BeagleBoard Imager
+    ///
+    /// Imported images are treated as already downloaded, and are pinned so that they are not
+    /// evicted. Useful for preparing machines which will be used without internet access.
+    ImportImages {
+        /// Directory containing the images. Searched recursively.
+        dir: PathBuf,
+
+        #[arg(long)]
+        /// Config file (distros.json) listing the images. Remote configs and remote sublists are
+        /// not followed.
+        config: PathBuf,
+
+        #[arg(long)]
+        /// Cache directory to import into. Defaults to the cache directory of BeagleBoard Imager.
+        cache_dir: Option<PathBuf>,
+
+        #[arg(long)]
+        /// Hard link the images instead of copying them, when possible.
+        hard_link: bool,
+
+        #[arg(long)]
+        /// Suppress standard output messages for a quieter experience.
+        quiet: bool,
+    },
+
+    /// Command to manage the download cache of BeagleBoard Imager
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+
+        #[arg(long, global = true)]
+        /// Cache directory. Defaults to the cache directory of BeagleBoard Imager.
+        cache_dir: Option<PathBuf>,
+    },
+
     /// Command to generate shell completion
     GenerateCompletion {
         /// Specifies the target shell type for completion
@@ -59,6 +95,30 @@ pub enum Commands {
 }
 
 #[derive(Subcommand, Debug)]
+pub enum CacheCommands {
+    /// List cached files, least recently used first.
+    List,
+    /// Pin a cached file. Pinned files are never evicted or purged.
+    Pin {
+        /// Name of the file in cache, as shown by `list`.
+        name: Box<str>,
+    },
+    /// Unpin a cached file.
+    Unpin {
+        /// Name of the file in cache, as shown by `list`.
+        name: Box<str>,
+    },
+    /// Remove a file from cache, even if it is pinned.
+    Remove {
+        /// Name of the file in cache, as shown by `list`.
+        name: Box<str>,
+    },
+    /// Remove all files which are not pinned, along with partial downloads.
+    Purge,
+}
+
+#[derive(Subcommand, Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum TargetCommands {
     /// Flash BeagleConnect Freedom.
     #[cfg(feature = "bcf_cc1352p7")]
@@ -81,6 +141,11 @@ pub enum TargetCommands {
         /// The destination device (e.g., `/dev/sdX` or specific device identifiers).
         dst: PathBuf,
 
+        #[arg(long, verbatim_doc_comment)]
+        /// Load customization from a profile (`.toml` or `.json`).
+        /// Other customization flags override values from the profile.
+        profile: Option<PathBuf>,
+
         #[arg(long)]
         /// Set a custom hostname for the device (e.g., "beaglebone").
         hostname: Option<Box<str>>,
@@ -93,6 +158,10 @@ pub enum TargetCommands {
         /// Set the keyboard layout/keymap (e.g., "us" for the US layout).
         keymap: Option<Box<str>>,
 
+        #[arg(long)]
+        /// Set the system locale (e.g., "en_US.UTF-8"). Only used by Armbian config.
+        locale: Option<Box<str>>,
+
         #[arg(long, requires = "user_password", verbatim_doc_comment)]
         /// Set a username for the default user. Cannot be `root`. Requires `user_password`.
         /// Required to enter GUI session due to regulatory requirements.
@@ -111,26 +180,123 @@ pub enum TargetCommands {
         /// Set the password for the specified Wi-Fi SSID. Requires `wifi_ssid`.
         wifi_password: Option<Box<str>>,
 
-        #[arg(long)]
-        /// Set SSH public key for authentication
+        #[arg(long, requires = "wifi_ssid", conflicts_with_all = ["armbian", "cloud_init"])]
+        /// The specified Wi-Fi SSID is hidden. Requires `wifi_ssid`. Only supported by sysconfig.
+        wifi_hidden: bool,
+
+        #[arg(long, conflicts_with_all = ["armbian", "cloud_init"])]
+        /// Add a Wi-Fi network from a TOML file, using the same fields as `[[network.wifis]]` in
+        /// profiles. Supports hidden, open and WPA2-Enterprise networks. Can be specified multiple
+        /// times. Only supported by sysconfig.
+        wifi_network: Vec<PathBuf>,
+
+        #[arg(long, conflicts_with_all = ["armbian", "cloud_init"])]
+        /// Set the Wi-Fi regulatory country code (e.g. "US"). Only supported by sysconfig.
+        wifi_country: Option<Box<str>>,
+
+        #[arg(long, conflicts_with = "armbian")]
+        /// Set SSH public key for authentication. Not supported by Armbian.
         ssh_key: Option<Box<str>>,
 
-        #[arg(long)]
-        /// Enable USB DHCP
+        #[arg(long, conflicts_with_all = ["armbian", "cloud_init"])]
+        /// Ethernet interface to configure static IP on (default: "eth0"). Only supported by
+        /// sysconfig.
+        eth_interface: Option<Box<str>>,
+
+        #[arg(long, conflicts_with_all = ["armbian", "cloud_init"])]
+        /// Static IPv4/IPv6 address for ethernet in CIDR notation (e.g. "192.168.1.10/24"). Can be
+        /// specified multiple times. Only supported by sysconfig.
+        eth_address: Vec<Box<str>>,
+
+        #[arg(long, requires = "eth_address")]
+        /// IPv4 gateway for ethernet. Requires `eth_address`.
+        eth_gateway4: Option<Box<str>>,
+
+        #[arg(long, requires = "eth_address")]
+        /// IPv6 gateway for ethernet. Requires `eth_address`.
+        eth_gateway6: Option<Box<str>>,
+
+        #[arg(long, requires = "eth_address")]
+        /// DNS server for ethernet. Can be specified multiple times. Requires `eth_address`.
+        eth_dns: Vec<Box<str>>,
+
+        #[arg(long, conflicts_with_all = ["armbian", "cloud_init"])]
+        /// Enable USB DHCP. Only supported by sysconfig.
         usb_enable_dhcp: bool,
+
+        #[arg(long)]
+        /// Enable a device tree overlay in boot config (e.g. "BB-UART1-00A0.dtbo"). Can be
+        /// specified multiple times.
+        overlay: Vec<Box<str>>,
+
+        #[arg(long)]
+        /// Disable a device tree overlay in boot config. Can be specified multiple times.
+        remove_overlay: Vec<Box<str>>,
+
+        #[arg(long)]
+        /// Set a kernel command line argument (e.g. "console=ttyS2,115200n8"). Can be specified
+        /// multiple times.
+        cmdline_arg: Vec<Box<str>>,
+
+        #[arg(long)]
+        /// Set the default label to boot in extlinux.conf.
+        default_label: Option<Box<str>>,
         /// Provide the bmap file for the image
         #[arg(long)]
         bmap: Option<Box<Path>>,
 
+        #[arg(long, value_parser = parse_sha256)]
+        /// Expected SHA-256 of the image file. If not provided, a sidecar checksum file (e.g.
+        /// `<img>.sha256` or `SHA256SUMS`) next to the image is used when present.
+        sha256: Option<[u8; 32]>,
+
         #[arg(long)]
         /// Generate clound-init config.
         cloud_init: bool,
 
+        #[arg(long, requires = "cloud_init")]
+        /// Package to install on first boot. Can be specified multiple times. Requires
+        /// `cloud_init`.
+        package: Vec<Box<str>>,
+
+        #[arg(long, requires = "cloud_init")]
+        /// Command to run on first boot. Can be specified multiple times. Requires `cloud_init`.
+        runcmd: Vec<Box<str>>,
+
+        #[arg(long, requires = "cloud_init")]
+        /// Path to a cloud-config file to merge into generated user-data. Can be specified
+        /// multiple times. Requires `cloud_init`.
+        cloud_init_fragment: Vec<PathBuf>,
+
+        #[arg(long, requires = "cloud_init")]
+        /// Wi-Fi interface to configure in cloud-init network-config (default: "wlo1"). Requires
+        /// `cloud_init`.
+        wifi_interface: Option<Box<str>>,
+
+        #[arg(long)]
+        /// Script to run once on first boot. Uses the first boot mechanism of the selected config
+        /// format.
+        first_boot_script: Option<PathBuf>,
+
+        #[arg(long, requires = "first_boot_script", allow_hyphen_values = true)]
+        /// Argument to pass to the first boot script. Can be specified multiple times. Requires
+        /// `first_boot_script`.
+        first_boot_arg: Vec<Box<str>>,
+
+        #[arg(long, requires = "first_boot_script")]
+        /// Run the first boot script with `sh` before networking is configured. Requires
+        /// `first_boot_script`.
+        first_boot_early: bool,
+
         #[arg(long)]
-        /// Generate sysconfig. Currently, sysconfig will be generated regardless if this flag is
-        /// provides. However, this will change in future. So best to explicitly set the flag.
+        /// Generate sysconfig. Currently, sysconfig will be generated if no other config format
+        /// is provided. However, this will change in future. So best to explicitly set the flag.
         sysconfig: bool,
 
+        #[arg(long)]
+        /// Generate Armbian first run config.
+        armbian: bool,
+
         /// The destination is a file instead of SD Card
         #[arg(long)]
         file_destination: bool,
@@ -192,6 +358,10 @@ pub enum TargetCommands {
     },
 }
 
+fn parse_sha256(s: &str) -> Result<[u8; 32], String> {
+    const_hex::decode_to_array(s).map_err(|e| format!("invalid SHA-256: {e}"))
+}
+
 #[derive(ValueEnum, Clone, Copy, Debug)]
 pub enum DestinationsTarget {
     /// BeagleConnect Freedom targets.
@@ -277,6 +447,81 @@ mod tests {
         }
     }
 
+    #[test]
+    fn flash_sd_sha256_parses() {
+        let sha256 = "ab".repeat(32);
+        let opt = Opt::try_parse_from([
+            "bb-imager-cli",
+            "flash",
+            "sd",
+            "img.xz",
+            "/dev/sdX",
+            "--sha256",
+            &sha256,
+        ])
+        .expect("valid sha256");
+        match opt.command {
+            Commands::Flash { target, .. } => match *target {
+                TargetCommands::Sd { sha256, .. } => assert_eq!(sha256, Some([0xab; 32])),
+                other => panic!("expected Sd, got {other:?}"),
+            },
+            other => panic!("expected Flash, got {other:?}"),
+        }
+
+        assert!(
+            Opt::try_parse_from([
+                "bb-imager-cli",
+                "flash",
+                "sd",
+                "i",
+                "/d",
+                "--sha256",
+                "abcd",
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn flash_sd_boot_config_flags_parse() {
+        let opt = Opt::try_parse_from([
+            "bb-imager-cli",
+            "flash",
+            "sd",
+            "img.xz",
+            "/dev/sdX",
+            "--overlay",
+            "a.dtbo",
+            "--overlay",
+            "b.dtbo",
+            "--remove-overlay",
+            "c.dtbo",
+            "--cmdline-arg",
+            "quiet",
+            "--default-label",
+            "Linux eMMC",
+        ])
+        .expect("valid boot config flags");
+        match opt.command {
+            Commands::Flash { target, .. } => match *target {
+                TargetCommands::Sd {
+                    overlay,
+                    remove_overlay,
+                    cmdline_arg,
+                    default_label,
+                    ..
+                } => {
+                    assert_eq!(overlay, vec!["a.dtbo".into(), "b.dtbo".into()]);
+                    assert_eq!(remove_overlay, vec!["c.dtbo".into()]);
+                    assert_eq!(cmdline_arg, vec!["quiet".into()]);
+                    assert_eq!(default_label.as_deref(), Some("Linux eMMC"));
+                }
+                other => panic!("expected Sd, got {other:?}"),
+            },
+            other => panic!("expected Flash, got {other:?}"),
+        }
+    }
+
     #[test]
     fn user_name_requires_password() {
         // `--user-name` declares `requires = "user_password"`.
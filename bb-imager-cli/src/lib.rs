@@ -1,13 +1,17 @@
 pub mod cli;
 mod helpers;
 
+use anyhow::Context;
 use bb_flasher::{BBFlasherTarget, DownloadFlashingStatus, LocalImage};
 use clap::CommandFactory;
-use cli::{Commands, DestinationsTarget, Opt, TargetCommands};
+use cli::{CacheCommands, Commands, DestinationsTarget, Opt, TargetCommands};
 use helpers::LocalStringFile;
 use std::path::PathBuf;
 use std::sync::mpsc;
 
+/// Qualifier of BeagleBoard Imager, used to find its cache directory.
+const GUI_QUALIFIER: (&str, &str, &str) = ("org", "beagleboard", "imagingutility");
+
 pub fn run(opt: Opt) {
     match opt.command {
         Commands::Flash { target, quiet } => flash(*target, quiet),
@@ -17,6 +21,16 @@ pub fn run(opt: Opt) {
             no_frills,
             no_filter,
         } => list_destinations(target, no_frills, no_filter),
+        Commands::ImportImages {
+            dir,
+            config,
+            cache_dir,
+            hard_link,
+            quiet,
+        } => import_images(dir, config, cache_dir, hard_link, quiet).expect("Failed to import"),
+        Commands::Cache { command, cache_dir } => {
+            cache(command, cache_dir).expect("Failed to manage cache")
+        }
         Commands::GenerateCompletion { shell } => generate_completion(shell),
     }
 }
@@ -111,53 +125,257 @@ fn flash_internal(
     match target {
         TargetCommands::Sd {
             dst,
+            profile,
             hostname,
             timezone,
             keymap,
+            locale,
             user_name,
             user_password,
             wifi_ssid,
             wifi_password,
+            wifi_hidden,
+            wifi_network,
             img,
+            wifi_country,
             ssh_key,
+            eth_interface,
+            eth_address,
+            eth_gateway4,
+            eth_gateway6,
+            eth_dns,
             usb_enable_dhcp,
+            overlay,
+            remove_overlay,
+            cmdline_arg,
+            default_label,
             bmap,
+            sha256,
             sysconfig,
             cloud_init,
+            package,
+            runcmd,
+            cloud_init_fragment,
+            wifi_interface,
+            first_boot_script,
+            first_boot_arg,
+            first_boot_early,
+            armbian,
             file_destination,
         } => {
             // TODO: Remove fallback in the future.
-            if !sysconfig && !cloud_init {
+            if profile.is_none() && !sysconfig && !cloud_init && !armbian {
                 tracing::warn!("No config format specified. Using sysconfig by default");
             }
 
-            let user = user_name.map(|x| (x, user_password.unwrap()));
+            let wifi_networks: Vec<bb_flasher::sd::sysconf::Wifi> = wifi_network
+                .iter()
+                .map(|x| {
+                    std::fs::read_to_string(x)
+                        .map_err(anyhow::Error::from)
+                        .and_then(|data| toml::from_str(&data).map_err(Into::into))
+                        .with_context(|| format!("Invalid Wi-Fi network {}", x.display()))
+                })
+                .collect::<anyhow::Result<_>>()?;
+
             let wifi = wifi_ssid.map(|x| (x, wifi_password.unwrap()));
+            let flag_wifi = wifi
+                .clone()
+                .map(|(ssid, psk)| bb_flasher::sd::sysconf::Wifi {
+                    hidden: wifi_hidden,
+                    ..bb_flasher::sd::sysconf::Wifi::psk(ssid, psk)
+                });
 
             let dst = check_macos_device_path(dst);
 
-            let customization = if hostname.is_some()
+            // `sysconf.txt` cannot mark a network as hidden, so hidden networks are configured along
+            // with the other networks.
+            let network = bb_flasher::sd::sysconf::Network {
+                wifi_country,
+                wifis: flag_wifi
+                    .clone()
+                    .filter(|x| x.hidden)
+                    .into_iter()
+                    .chain(wifi_networks.iter().cloned())
+                    .collect(),
+                ethernet: (!eth_address.is_empty()).then_some(bb_flasher::sd::sysconf::Ethernet {
+                    interface: eth_interface,
+                    ip: bb_flasher::sd::cloud_init::StaticIp {
+                        addresses: eth_address,
+                        gateway4: eth_gateway4,
+                        gateway6: eth_gateway6,
+                        nameservers: eth_dns,
+                    },
+                }),
+            };
+
+            // Hidden networks are validated as part of `network`.
+            let visible_wifi = wifi.as_ref().filter(|_| !wifi_hidden);
+            let mut errors = bb_flasher::sd::validate::Inputs {
+                hostname: hostname.as_deref(),
+                timezone: timezone.as_deref(),
+                keymap: keymap.as_deref(),
+                username: user_name.as_deref(),
+                password: user_password.as_deref(),
+                ssid: visible_wifi.map(|x| x.0.as_ref()),
+                wifi_passphrase: visible_wifi.map(|x| x.1.as_ref()),
+                ssh_key: ssh_key.as_deref(),
+            }
+            .errors();
+            errors.0.extend(network.errors().0);
+            errors.into_result()?;
+
+            let user = user_name.map(|x| (x, user_password.unwrap().into()));
+
+            let fragments: Vec<Box<str>> = cloud_init_fragment
+                .iter()
+                .map(|x| std::fs::read_to_string(x).map(Into::into))
+                .collect::<std::io::Result<_>>()?;
+
+            let first_boot = match first_boot_script {
+                Some(path) => Some(bb_flasher::sd::FirstBootScript {
+                    args: first_boot_arg,
+                    early: first_boot_early,
+                    ..bb_flasher::sd::FirstBootScript::new(
+                        path.file_name()
+                            .context("Invalid first boot script path")?
+                            .to_string_lossy()
+                            .into(),
+                        std::fs::read_to_string(&path)?.into(),
+                    )
+                }),
+                None => None,
+            };
+
+            let mut customization = if let Some(path) = profile {
+                let mut profile = bb_flasher::sd::Profile::load(&path)?;
+
+                // Flags take precedence over the profile.
+                if armbian {
+                    profile.init_format = bb_flasher::sd::profile::InitFormat::Armbian;
+                } else if cloud_init {
+                    profile.init_format = bb_flasher::sd::profile::InitFormat::CloudInit;
+                } else if sysconfig {
+                    profile.init_format = bb_flasher::sd::profile::InitFormat::Sysconf;
+                }
+                profile.hostname = hostname.or(profile.hostname);
+                profile.timezone = timezone.or(profile.timezone);
+                profile.keymap = keymap.or(profile.keymap);
+                profile.locale = locale.or(profile.locale);
+                profile.ssh_key = ssh_key.or(profile.ssh_key);
+                profile.usb_enable_dhcp |= usb_enable_dhcp;
+                if let Some((name, password)) = user {
+                    profile.user = Some(bb_flasher::sd::profile::User { name, password });
+                }
+                if let Some(x) = flag_wifi {
+                    profile.network.wifis = vec![x];
+                }
+                profile.network.wifis.extend(wifi_networks);
+                profile.network.wifi_country =
+                    network.wifi_country.or(profile.network.wifi_country);
+                profile.network.ethernet = network.ethernet.or(profile.network.ethernet);
+                profile.cloud_init.packages.extend(package);
+                profile.cloud_init.runcmd.extend(runcmd);
+                profile.cloud_init.fragments.extend(fragments);
+                profile.cloud_init.wifi_interface =
+                    wifi_interface.or(profile.cloud_init.wifi_interface);
+                profile.first_boot = first_boot.or(profile.first_boot);
+
+                profile.to_customization()?
+            } else if hostname.is_some()
                 || timezone.is_some()
                 || keymap.is_some()
+                || locale.is_some()
                 || user.is_some()
                 || wifi.is_some()
                 || ssh_key.is_some()
                 || usb_enable_dhcp
+                || !network.is_empty()
+                || !package.is_empty()
+                || !runcmd.is_empty()
+                || !fragments.is_empty()
+                || first_boot.is_some()
             {
-                let mut customization = bb_flasher::sd::FlashingSdLinuxConfig::sysconfig(
-                    hostname.clone(),
-                    timezone.clone(),
-                    keymap.clone(),
-                    user.clone(),
-                    wifi.clone(),
-                    ssh_key.clone(),
-                    Some(usb_enable_dhcp),
-                );
+                let mut customization = bb_flasher::sd::FlashingSdLinuxConfig::none();
+
+                // sysconfig is the fallback if no config format is specified.
+                if sysconfig || (!armbian && !cloud_init) {
+                    customization.extend([bb_flasher::sd::FlashingSdLinuxConfig::sysconfig(
+                        hostname.clone(),
+                        timezone.clone(),
+                        keymap.clone(),
+                        user.clone(),
+                        wifi.clone().filter(|_| !wifi_hidden),
+                        ssh_key.clone(),
+                        Some(usb_enable_dhcp),
+                    )?]);
+                    customization.extend([
+                        bb_flasher::sd::FlashingSdLinuxConfig::sysconfig_network(network)?,
+                    ]);
+                }
+
+                // The script should only run once, so it is only added to a single format.
+                let (armbian_first_boot, cloud_init_first_boot, sysconfig_first_boot) =
+                    match first_boot {
+                        Some(x) if armbian => (Some(x), None, None),
+                        Some(x) if cloud_init => (None, Some(x), None),
+                        x => (None, None, x),
+                    };
+                if let Some(x) = sysconfig_first_boot {
+                    customization.extend([
+                        bb_flasher::sd::FlashingSdLinuxConfig::sysconfig_first_boot_script(x)?,
+                    ]);
+                }
+
+                if armbian {
+                    customization.extend([bb_flasher::sd::FlashingSdLinuxConfig::armbian(
+                        hostname.clone(),
+                        timezone.clone(),
+                        locale,
+                        user.clone(),
+                        wifi.clone(),
+                    )?]);
+                }
+                if let Some(x) = armbian_first_boot {
+                    customization.extend([
+                        bb_flasher::sd::FlashingSdLinuxConfig::armbian_first_boot_script(x)?,
+                    ]);
+                }
 
                 if cloud_init {
-                    customization.extend([bb_flasher::sd::FlashingSdLinuxConfig::cloud_init(
-                        hostname, timezone, keymap, user, wifi, ssh_key,
-                    )]);
+                    let wifi_interface = wifi_interface
+                        .unwrap_or(bb_flasher::sd::cloud_init::DEFAULT_WIFI_INTERFACE.into());
+
+                    let mut config = bb_flasher::sd::CloudInit {
+                        hostname,
+                        timezone,
+                        keymap,
+                        user,
+                        ssh_authorized_keys: ssh_key.into_iter().collect(),
+                        packages: package,
+                        runcmd,
+                        network: bb_flasher::sd::cloud_init::Network {
+                            ethernets: Vec::new(),
+                            wifis: wifi
+                                .map(|x| {
+                                    vec![bb_flasher::sd::cloud_init::Wifi {
+                                        interface: wifi_interface,
+                                        ip: bb_flasher::sd::cloud_init::IpConfig::Dhcp,
+                                        access_points: vec![x],
+                                    }]
+                                })
+                                .unwrap_or_default(),
+                        },
+                        fragments,
+                        ..Default::default()
+                    };
+                    if let Some(x) = cloud_init_first_boot {
+                        config.add_first_boot_script(&x)?;
+                    }
+
+                    customization.extend([bb_flasher::sd::FlashingSdLinuxConfig::cloud_init_seed(
+                        config,
+                    )?]);
                 }
 
                 customization
@@ -165,18 +383,32 @@ fn flash_internal(
                 bb_flasher::sd::FlashingSdLinuxConfig::none()
             };
 
+            customization.extend([bb_flasher::sd::FlashingSdLinuxConfig::boot_config(
+                bb_flasher::sd::BootConfig {
+                    add_overlays: overlay,
+                    remove_overlays: remove_overlay,
+                    cmdline: cmdline_arg,
+                    default_label,
+                },
+            )]);
+
             tracing::info!("Customization: {:#?}", customization);
 
+            let img = match sha256 {
+                Some(x) => LocalImage::new(img).with_sha256(x),
+                None => LocalImage::new(img),
+            };
+
             if file_destination {
                 bb_flasher::sd::Flasher::with_file_dest(
-                    LocalImage::new(img).into_image_fn(),
+                    img.into_image_fn(),
                     bmap.map(LocalStringFile::new).map(|x| x.into_fn()),
                     dst,
                     customization,
                 )
             } else {
                 bb_flasher::sd::Flasher::new(
-                    LocalImage::new(img).into_image_fn(),
+                    img.into_image_fn(),
                     bmap.map(LocalStringFile::new).map(|x| x.into_fn()),
                     dst.try_into().unwrap(),
                     customization,
@@ -355,6 +587,79 @@ fn format(dst: PathBuf, quiet: bool) {
     }
 }
 
+fn import_images(
+    dir: PathBuf,
+    config: PathBuf,
+    cache_dir: Option<PathBuf>,
+    hard_link: bool,
+    quiet: bool,
+) -> anyhow::Result<()> {
+    let term = console::Term::stdout();
+
+    let config = std::fs::read(&config).with_context(|| format!("Failed to read {config:?}"))?;
+    let config: bb_config::Config =
+        serde_json::from_slice(&config).context("Failed to parse config")?;
+
+    let downloader = bb_downloader::Downloader::new(cache_dir_or_default(cache_dir)?)?;
+
+    let mode = if hard_link {
+        bb_downloader::ImportMode::HardLink
+    } else {
+        bb_downloader::ImportMode::Copy
+    };
+    let sha256s = config.images().map(|x| x.image_download_sha256);
+    let imported = downloader.import_dir(&dir, sha256s, mode)?;
+
+    if !quiet {
+        for x in &imported {
+            term.write_line(&format!("Imported {}", x.source.display()))?;
+        }
+        term.write_line(&format!("Imported {} image(s)", imported.len()))?;
+    }
+
+    Ok(())
+}
+
+/// `cache_dir`, or the cache directory of the GUI if not set.
+fn cache_dir_or_default(cache_dir: Option<PathBuf>) -> anyhow::Result<PathBuf> {
+    match cache_dir {
+        Some(x) => Ok(x),
+        None => directories::ProjectDirs::from(GUI_QUALIFIER.0, GUI_QUALIFIER.1, GUI_QUALIFIER.2)
+            .context("Failed to find cache directory")
+            .map(|x| x.cache_dir().to_path_buf()),
+    }
+}
+
+fn cache(command: CacheCommands, cache_dir: Option<PathBuf>) -> anyhow::Result<()> {
+    let term = console::Term::stdout();
+    let cache_dir = cache_dir_or_default(cache_dir)?;
+    let downloader = bb_downloader::Downloader::new(&cache_dir)?;
+    let path = |name: &str| cache_dir.join(name);
+
+    match command {
+        CacheCommands::List => {
+            for x in downloader.cache_entries()? {
+                let name = x.path.file_name().unwrap_or_default().to_string_lossy();
+                let url = x.url.as_ref().map(|x| x.as_str()).unwrap_or_default();
+                term.write_line(&format!(
+                    "{name}\t{}\t{}\t{url}",
+                    indicatif::HumanBytes(x.size),
+                    if x.pinned { "pinned" } else { "-" },
+                ))?;
+            }
+        }
+        CacheCommands::Pin { name } => downloader.set_pinned(&path(&name), true)?,
+        CacheCommands::Unpin { name } => downloader.set_pinned(&path(&name), false)?,
+        CacheCommands::Remove { name } => downloader.remove_from_cache(&path(&name))?,
+        CacheCommands::Purge => {
+            let freed = downloader.purge_cache()?;
+            term.write_line(&format!("Freed {}", indicatif::HumanBytes(freed)))?;
+        }
+    }
+
+    Ok(())
+}
+
 fn no_frills_list_destinations<T: BBFlasherTarget + Send + 'static>(no_filter: bool) {
     let term = console::Term::stdout();
     let dsts = T::destinations(!no_filter);
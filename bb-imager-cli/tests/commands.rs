@@ -60,3 +60,81 @@ fn list_destinations_zepto_runs() {
     run_cli(&["bb-imager-cli", "list-destinations", "zepto"]);
     run_cli(&["bb-imager-cli", "list-destinations", "zepto", "--no-frills"]);
 }
+
+/// Images in the directory matching the config are copied into the cache under their SHA256.
+#[test]
+fn import_images_copies_matching_images() {
+    const SHA256: &str = "a854f481d4cb2479ff869f3a33763e4c3e8d6b5727f909d774fdd5f37690baeb";
+
+    let staged = tempfile::tempdir().unwrap();
+    let cache = tempfile::tempdir().unwrap();
+    std::fs::write(staged.path().join("a.img.xz"), b"image a").unwrap();
+    std::fs::write(staged.path().join("other.img.xz"), b"other").unwrap();
+
+    let config = staged.path().join("distros.json");
+    std::fs::write(
+        &config,
+        format!(
+            r#"{{ "os_list": [{{
+                "name": "Test Image",
+                "description": "an image",
+                "icon": "https://example.com/icon.png",
+                "url": "https://example.com/a.img.xz",
+                "image_download_size": 7,
+                "image_download_sha256": "{SHA256}",
+                "extract_size": 7,
+                "release_date": "2024-01-02",
+                "devices": []
+            }}] }}"#
+        ),
+    )
+    .unwrap();
+
+    run_cli(&[
+        "bb-imager-cli",
+        "import-images",
+        staged.path().to_str().unwrap(),
+        "--config",
+        config.to_str().unwrap(),
+        "--cache-dir",
+        cache.path().to_str().unwrap(),
+        "--quiet",
+    ]);
+
+    assert_eq!(
+        std::fs::read(cache.path().join(SHA256)).unwrap(),
+        b"image a"
+    );
+}
+
+/// Pinned files survive `cache purge`, while the rest are removed.
+#[test]
+fn cache_pin_and_purge() {
+    let cache = tempfile::tempdir().unwrap();
+    let cache_dir = cache.path().to_str().unwrap();
+    std::fs::write(cache.path().join("a"), b"a").unwrap();
+    std::fs::write(cache.path().join("b"), b"b").unwrap();
+
+    run_cli(&[
+        "bb-imager-cli",
+        "cache",
+        "pin",
+        "a",
+        "--cache-dir",
+        cache_dir,
+    ]);
+    run_cli(&["bb-imager-cli", "cache", "list", "--cache-dir", cache_dir]);
+    run_cli(&["bb-imager-cli", "cache", "purge", "--cache-dir", cache_dir]);
+    assert!(cache.path().join("a").exists());
+    assert!(!cache.path().join("b").exists());
+
+    run_cli(&[
+        "bb-imager-cli",
+        "cache",
+        "remove",
+        "a",
+        "--cache-dir",
+        cache_dir,
+    ]);
+    assert!(!cache.path().join("a").exists());
+}
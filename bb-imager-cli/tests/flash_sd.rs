@@ -16,6 +16,9 @@ use bb_imager_cli::cli::Opt;
 use clap::Parser;
 use tempfile::NamedTempFile;
 
+const SSH_KEY: &str =
+    "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAABAgMEBQYHCAkKCwwNDg8QERITFBUWFxgZGhscHR4f";
+
 /// Run the CLI exactly as `main` would, from an argv.
 fn run_cli<const N: usize>(args: [&str; N]) {
     let opt = Opt::try_parse_from(args).expect("argv should parse");
@@ -49,13 +52,23 @@ impl SdFixture {
     /// create missing parent directories, so any nested target needs its parent
     /// to exist in the image beforehand.
     fn with_boot_dirs(dirs: &[&str]) -> Self {
+        Self::with_boot_contents(dirs, &[])
+    }
+
+    /// Like [`Self::with_boot_dirs`], but also creates `files` (name, contents)
+    /// in the image's boot partition.
+    fn with_boot_contents(dirs: &[&str], files: &[(&str, &str)]) -> Self {
         let mut mock = MockSd::new();
 
-        if !dirs.is_empty() {
+        if !dirs.is_empty() || !files.is_empty() {
             let fs = mock.open_boot();
             for dir in dirs {
                 fs.root_dir().create_dir(dir).unwrap();
             }
+            for (name, contents) in files {
+                let mut f = fs.root_dir().create_file(name).unwrap();
+                f.write_all(contents.as_bytes()).unwrap();
+            }
             fs.unmount().unwrap();
         }
 
@@ -198,19 +211,21 @@ fn flash_sd_sysconfig_writes_every_supplied_key() {
         "--user-password",
         "hunter2",
         "--ssh-key",
-        "ssh-ed25519 AAAA",
+        SSH_KEY,
         "--usb-enable-dhcp",
     ]);
 
     assert_eq!(
         fixture.boot_file("sysconf.txt").unwrap(),
-        "hostname=beagle\n\
-         timezone=Asia/Kolkata\n\
-         keymap=us\n\
-         user_name=bob\n\
-         user_password=hunter2\n\
-         user_authorized_key=ssh-ed25519 AAAA\n\
-         usb_enable_dhcp=yes\n"
+        format!(
+            "hostname=beagle\n\
+             timezone=Asia/Kolkata\n\
+             keymap=us\n\
+             user_name=bob\n\
+             user_password=hunter2\n\
+             user_authorized_key={SSH_KEY}\n\
+             usb_enable_dhcp=yes\n"
+        )
     );
 }
 
@@ -284,7 +299,7 @@ fn flash_sd_wifi_writes_psk_file_next_to_sysconfig() {
         "--wifi-ssid",
         "mynet",
         "--wifi-password",
-        "hunter2",
+        "hunter22",
     ]);
 
     assert_eq!(
@@ -293,15 +308,180 @@ fn flash_sd_wifi_writes_psk_file_next_to_sysconfig() {
     );
     assert_eq!(
         fixture.boot_file("services/mynet.psk").unwrap(),
-        "[Security]\nPassphrase=hunter2\n\n[Settings]\nAutoConnect=true"
+        "[Security]\nPassphrase=hunter22\n\n[Settings]\nAutoConnect=true"
+    );
+}
+
+/// SSIDs that are not valid file names are hex encoded, as expected by iwd.
+#[test]
+fn flash_sd_wifi_encodes_psk_file_name() {
+    let mut fixture = SdFixture::with_boot_dirs(&["services"]);
+
+    run_cli([
+        "bb-imager-cli",
+        "flash",
+        "--quiet",
+        "sd",
+        fixture.img(),
+        fixture.dst(),
+        "--file-destination",
+        "--sysconfig",
+        "--wifi-ssid",
+        "my net",
+        "--wifi-password",
+        "hunter22",
+    ]);
+
+    assert_eq!(
+        fixture.boot_file("sysconf.txt").unwrap(),
+        "iwd_psk_file==6d79206e6574.psk\n"
+    );
+    assert!(fixture.boot_file("services/=6d79206e6574.psk").is_ok());
+}
+
+/// `sysconf.txt` has no keys for the regulatory domain or static ethernet, so
+/// they are set on the kernel command line and by the first boot hook instead.
+#[test]
+fn flash_sd_network_flags_use_first_boot_hook() {
+    let mut fixture = SdFixture::with_boot_contents(&[], &[("uEnv.txt", "cmdline=quiet\n")]);
+
+    run_cli([
+        "bb-imager-cli",
+        "flash",
+        "--quiet",
+        "sd",
+        fixture.img(),
+        fixture.dst(),
+        "--file-destination",
+        "--sysconfig",
+        "--hostname",
+        "beagle",
+        "--wifi-country",
+        "IN",
+        "--eth-address",
+        "192.168.7.2/24",
+        "--eth-gateway4",
+        "192.168.7.1",
+        "--eth-dns",
+        "1.1.1.1",
+        "--eth-dns",
+        "8.8.8.8",
+    ]);
+
+    assert_eq!(
+        fixture.boot_file("sysconf.txt").unwrap(),
+        "hostname=beagle\n"
+    );
+    let uenv = fixture.boot_file("uEnv.txt").unwrap();
+    assert!(
+        uenv.starts_with("cmdline=quiet systemd.run=/boot/firmware/bb-imager/firstboot.sh "),
+        "{uenv}"
+    );
+    assert!(uenv.contains(" cfg80211.ieee80211_regdom=IN"), "{uenv}");
+    let hook = fixture.boot_file("bb-imager/firstboot.sh").unwrap();
+    assert!(
+        hook.contains(
+            "[Match]\nName=eth0\n\n[Network]\nAddress=192.168.7.2/24\nGateway=192.168.7.1\n\
+             DNS=1.1.1.1\nDNS=8.8.8.8\n"
+        ),
+        "{hook}"
+    );
+}
+
+/// Hidden and WPA2-Enterprise networks are written as iwd service files, which
+/// the first boot hook installs.
+#[test]
+fn flash_sd_wifi_networks() {
+    let mut fixture = SdFixture::new();
+    let mut network = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+    network
+        .write_all(
+            br#"
+ssid = "campus"
+security = { peap = { identity = "student", password = "pass" } }
+"#,
+        )
+        .unwrap();
+    network.flush().unwrap();
+
+    run_cli([
+        "bb-imager-cli",
+        "flash",
+        "--quiet",
+        "sd",
+        fixture.img(),
+        fixture.dst(),
+        "--file-destination",
+        "--sysconfig",
+        "--wifi-ssid",
+        "home",
+        "--wifi-password",
+        "hunter22",
+        "--wifi-hidden",
+        "--wifi-network",
+        network.path().to_str().unwrap(),
+    ]);
+
+    assert_eq!(fixture.boot_file("sysconf.txt").unwrap(), "");
+    assert_eq!(
+        fixture.boot_file("services/home.psk").unwrap(),
+        "[Security]\nPassphrase=hunter22\n\n[Settings]\nAutoConnect=true\nHidden=true\n"
+    );
+    assert!(
+        fixture
+            .boot_file("services/campus.8021x")
+            .unwrap()
+            .contains("EAP-PEAP-Phase2-Identity=student\n")
+    );
+    let hook = fixture.boot_file("bb-imager/firstboot.sh").unwrap();
+    for name in ["home.psk", "campus.8021x"] {
+        assert!(
+            hook.contains(&format!(
+                "install -m 600 /boot/firmware/services/{name} /var/lib/iwd/{name}\n"
+            )),
+            "{hook}"
+        );
+    }
+}
+
+/// Boot config flags edit the `uEnv.txt` already present in the image, and do
+/// not create an `extlinux.conf` that the image does not have.
+#[test]
+fn flash_sd_boot_config_edits_uenv() {
+    let mut fixture = SdFixture::with_boot_contents(
+        &[],
+        &[(
+            "uEnv.txt",
+            "#uboot_overlay_addr4=<file4>.dtbo\ncmdline=coherent_pool=1M quiet\n",
+        )],
+    );
+
+    run_cli([
+        "bb-imager-cli",
+        "flash",
+        "--quiet",
+        "sd",
+        fixture.img(),
+        fixture.dst(),
+        "--file-destination",
+        "--overlay",
+        "BB-UART1-00A0.dtbo",
+        "--cmdline-arg",
+        "coherent_pool=2M",
+    ]);
+
+    assert_eq!(
+        fixture.boot_file("uEnv.txt").unwrap(),
+        "uboot_overlay_addr4=BB-UART1-00A0.dtbo\ncmdline=coherent_pool=2M quiet\nenable_uboot_overlays=1\n"
     );
+    assert!(fixture.boot_file("extlinux/extlinux.conf").is_err());
 }
 
-/// `--cloud-init` adds a cloud-init document *in addition to* sysconfig: the
-/// CLI still generates sysconfig unconditionally (see the fallback TODO in
-/// `flash_internal`). This test pins that current behaviour.
+/// `--cloud-init` writes a cloud-init NoCloud seed. sysconfig is only used as
+/// the fallback when no config format is specified, so the account is not
+/// provisioned twice.
 #[test]
-fn flash_sd_cloud_init_emits_both_configs() {
+fn flash_sd_cloud_init_writes_only_seed() {
     let mut fixture = SdFixture::new();
 
     run_cli([
@@ -321,20 +501,376 @@ fn flash_sd_cloud_init_emits_both_configs() {
         "hunter2",
     ]);
 
-    let cloud_init = fixture.boot_file("cloud-init").unwrap();
+    let cloud_init = fixture.boot_file("user-data").unwrap();
     assert!(
         cloud_init.starts_with("#cloud-config\n"),
-        "cloud-init must carry the cloud-config header, got: {cloud_init}"
+        "user-data must carry the cloud-config header, got: {cloud_init}"
     );
     assert!(
         cloud_init.contains("beagle"),
-        "cloud-init should carry the hostname, got: {cloud_init}"
+        "user-data should carry the hostname, got: {cloud_init}"
+    );
+
+    let meta_data = fixture.boot_file("meta-data").unwrap();
+    assert!(
+        meta_data.contains("instance-id: "),
+        "meta-data must carry an instance-id, got: {meta_data}"
+    );
+    assert!(
+        fixture.boot_file("network-config").is_err(),
+        "network-config is only written when networking is configured"
+    );
+
+    assert!(
+        fixture.boot_file("sysconf.txt").is_err(),
+        "sysconfig must not be generated alongside cloud-init"
+    );
+}
+
+/// Packages, commands, fragments and Wi-Fi interface all end up in the seed.
+#[test]
+fn flash_sd_cloud_init_seed_options() {
+    // The sysconfig fallback writes the Wi-Fi psk into `services/`.
+    let mut fixture = SdFixture::with_boot_dirs(&["services"]);
+    let mut fragment = NamedTempFile::new().unwrap();
+    fragment
+        .write_all(b"#cloud-config\npackages: [vim]\n")
+        .unwrap();
+
+    run_cli([
+        "bb-imager-cli",
+        "flash",
+        "--quiet",
+        "sd",
+        fixture.img(),
+        fixture.dst(),
+        "--file-destination",
+        "--cloud-init",
+        "--package",
+        "git",
+        "--runcmd",
+        "echo hello",
+        "--cloud-init-fragment",
+        fragment.path().to_str().unwrap(),
+        "--wifi-ssid",
+        "home",
+        "--wifi-password",
+        "secret123",
+        "--wifi-interface",
+        "wlan0",
+    ]);
+
+    let user_data = fixture.boot_file("user-data").unwrap();
+    assert!(
+        user_data.contains("packages:\n- git\n- vim\n"),
+        "fragment packages should be appended, got: {user_data}"
+    );
+    assert!(user_data.contains("runcmd:\n- echo hello\n"), "{user_data}");
+
+    let network = fixture.boot_file("network-config").unwrap();
+    assert!(network.contains("wlan0:"), "{network}");
+    assert!(network.contains("home:"), "{network}");
+}
+
+/// Flags that only make sense for cloud-init are rejected without `--cloud-init`.
+#[test]
+fn flash_sd_cloud_init_options_require_flag() {
+    assert!(
+        Opt::try_parse_from([
+            "bb-imager-cli",
+            "flash",
+            "sd",
+            "a.img",
+            "/dev/null",
+            "--package",
+            "git",
+        ])
+        .is_err()
+    );
+}
+
+/// Unlike `--cloud-init`, `--armbian` replaces the sysconfig fallback: Armbian
+/// images do not read `sysconf.txt`.
+#[test]
+fn flash_sd_armbian_writes_first_run_config() {
+    let mut fixture = SdFixture::new();
+
+    run_cli([
+        "bb-imager-cli",
+        "flash",
+        "--quiet",
+        "sd",
+        fixture.img(),
+        fixture.dst(),
+        "--file-destination",
+        "--armbian",
+        "--hostname",
+        "beagle",
+        "--locale",
+        "en_US.UTF-8",
+        "--wifi-ssid",
+        "home",
+        "--wifi-password",
+        "secret123",
+    ]);
+
+    let config = fixture.boot_file("armbian_first_run.txt").unwrap();
+    for line in [
+        "FR_net_wifi_enabled='1'",
+        "FR_net_wifi_ssid='home'",
+        "FR_net_wifi_key='secret123'",
+        r"printf '%s\n' 'PRESET_HOSTNAME='\''beagle'\''' >> /root/.not_logged_in_yet",
+        r"printf '%s\n' 'PRESET_LOCALE='\''en_US.UTF-8'\''' >> /root/.not_logged_in_yet",
+    ] {
+        assert!(
+            config.lines().any(|x| x == line),
+            "missing {line} in {config}"
+        );
+    }
+
+    assert!(fixture.boot_file("sysconf.txt").is_err());
+}
+
+/// Armbian only accepts SSH keys as a URL, so `--ssh-key` is rejected.
+#[test]
+fn flash_sd_armbian_rejects_ssh_key() {
+    assert!(
+        Opt::try_parse_from([
+            "bb-imager-cli",
+            "flash",
+            "sd",
+            "a.img",
+            "/dev/null",
+            "--armbian",
+            "--ssh-key",
+            "ssh-ed25519 AAAA",
+        ])
+        .is_err()
+    );
+}
+
+/// Regulatory domain, ethernet, extra Wi-Fi network and USB DHCP flags are only
+/// supported by sysconfig.
+#[test]
+fn flash_sd_network_flags_conflict_with_other_formats() {
+    for format in ["--armbian", "--cloud-init"] {
+        for flags in [
+            &["--wifi-country", "US"][..],
+            &["--eth-interface", "eth1"],
+            &["--eth-address", "192.168.1.10/24"],
+            &["--usb-enable-dhcp"],
+            &["--wifi-network", "a.toml"],
+            &[
+                "--wifi-ssid",
+                "a",
+                "--wifi-password",
+                "12345678",
+                "--wifi-hidden",
+            ],
+        ] {
+            let args = ["bb-imager-cli", "flash", "sd", "a.img", "/dev/null", format];
+            assert!(
+                Opt::try_parse_from(args.iter().chain(flags)).is_err(),
+                "{format} {flags:?}"
+            );
+        }
+    }
+}
+
+fn first_boot_script() -> NamedTempFile {
+    let mut script = tempfile::Builder::new()
+        .prefix("register-")
+        .suffix(".sh")
+        .tempfile()
+        .unwrap();
+    script.write_all(b"#!/bin/sh\necho \"$@\"\n").unwrap();
+    script.flush().unwrap();
+    script
+}
+
+/// Without a config format, the first boot script is copied into the boot
+/// partition and run by a hook that `systemd.run` on the kernel command line
+/// starts on first boot.
+#[test]
+fn flash_sd_first_boot_script_uses_sysconfig_hook() {
+    let mut fixture =
+        SdFixture::with_boot_contents(&[], &[("uEnv.txt", "cmdline=coherent_pool=1M quiet\n")]);
+    let script = first_boot_script();
+    let name = script.path().file_name().unwrap().to_str().unwrap();
+
+    run_cli([
+        "bb-imager-cli",
+        "flash",
+        "--quiet",
+        "sd",
+        fixture.img(),
+        fixture.dst(),
+        "--file-destination",
+        "--first-boot-script",
+        script.path().to_str().unwrap(),
+        "--first-boot-arg",
+        "--site",
+        "--first-boot-arg",
+        "lab 1",
+    ]);
+
+    assert_eq!(
+        fixture.boot_file(&format!("bb-imager/{name}")).unwrap(),
+        "#!/bin/sh\necho \"$@\"\n"
+    );
+    let hook = fixture.boot_file("bb-imager/firstboot.sh").unwrap();
+    assert!(
+        hook.contains(&format!(
+            "ExecStart=\"/var/lib/bb-imager/firstboot/{name}\" \"--site\" \"lab 1\"\n"
+        )),
+        "{hook}"
+    );
+    assert_eq!(
+        fixture.boot_file("uEnv.txt").unwrap(),
+        "cmdline=coherent_pool=1M quiet systemd.run=/boot/firmware/bb-imager/firstboot.sh \
+         systemd.run_success_action=reboot systemd.run_failure_action=reboot\n"
+    );
+    assert!(
+        !fixture
+            .boot_file("sysconf.txt")
+            .unwrap_or_default()
+            .contains("firstboot")
+    );
+}
+
+/// With `--armbian`, the first boot script is run from `armbian_first_run.txt`.
+/// Early scripts are run directly with `sh`.
+#[test]
+fn flash_sd_first_boot_script_uses_armbian_first_run() {
+    let mut fixture = SdFixture::new();
+    let script = first_boot_script();
+    let name = script.path().file_name().unwrap().to_str().unwrap();
+
+    run_cli([
+        "bb-imager-cli",
+        "flash",
+        "--quiet",
+        "sd",
+        fixture.img(),
+        fixture.dst(),
+        "--file-destination",
+        "--armbian",
+        "--hostname",
+        "beagle",
+        "--first-boot-script",
+        script.path().to_str().unwrap(),
+        "--first-boot-arg",
+        "lab 1",
+        "--first-boot-early",
+    ]);
+
+    let first_run = fixture.boot_file("armbian_first_run.txt").unwrap();
+    assert!(
+        first_run.ends_with(&format!(
+            "sh /boot/bb-imager/{name} 'lab 1'\nrm -f /boot/bb-imager/{name}\n"
+        )),
+        "{first_run}"
+    );
+    assert!(fixture.boot_file(&format!("bb-imager/{name}")).is_ok());
+    assert!(fixture.boot_file("bb-imager/firstboot.sh").is_err());
+}
+
+/// The first boot script is written by cloud-init and run from `runcmd`, with
+/// arguments shell quoted.
+#[test]
+fn flash_sd_first_boot_script_uses_cloud_init_runcmd() {
+    let mut fixture = SdFixture::new();
+    let script = first_boot_script();
+    let name = script.path().file_name().unwrap().to_str().unwrap();
+
+    run_cli([
+        "bb-imager-cli",
+        "flash",
+        "--quiet",
+        "sd",
+        fixture.img(),
+        fixture.dst(),
+        "--file-destination",
+        "--cloud-init",
+        "--first-boot-script",
+        script.path().to_str().unwrap(),
+        "--first-boot-arg",
+        "--site",
+        "--first-boot-arg",
+        "lab 1",
+    ]);
+
+    let user_data = fixture.boot_file("user-data").unwrap();
+    assert!(
+        user_data.contains(&format!(
+            "/var/lib/bb-imager/firstboot/{name} --site 'lab 1'"
+        )),
+        "{user_data}"
     );
+    assert!(fixture.boot_file(&format!("bb-imager/{name}")).is_err());
+    assert!(fixture.boot_file("sysconf.txt").is_err());
+}
 
+/// First boot script options require a script.
+#[test]
+fn flash_sd_first_boot_options_require_script() {
+    let base = ["bb-imager-cli", "flash", "sd", "a.img", "/dev/null"];
+    for args in [&["--first-boot-early"][..], &["--first-boot-arg", "a"]] {
+        assert!(
+            Opt::try_parse_from(base.iter().chain(args)).is_err(),
+            "{args:?}"
+        );
+    }
     assert!(
-        fixture.boot_file("sysconf.txt").is_ok(),
-        "sysconfig is still generated alongside cloud-init"
+        Opt::try_parse_from(base.iter().chain(&[
+            "--armbian",
+            "--first-boot-script",
+            "a.sh",
+            "--first-boot-early"
+        ]))
+        .is_ok()
+    );
+}
+
+/// Flags override values loaded from `--profile`, while the rest of the
+/// profile is kept.
+#[test]
+fn flash_sd_profile_merges_with_flags() {
+    let mut fixture = SdFixture::new();
+    let mut profile = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+    profile
+        .write_all(
+            br#"
+hostname = "from-profile"
+keymap = "de"
+
+[[files]]
+path = "notes.txt"
+content = "hello"
+"#,
+        )
+        .unwrap();
+    profile.flush().unwrap();
+
+    run_cli([
+        "bb-imager-cli",
+        "flash",
+        "--quiet",
+        "sd",
+        fixture.img(),
+        fixture.dst(),
+        "--file-destination",
+        "--profile",
+        profile.path().to_str().unwrap(),
+        "--hostname",
+        "from-flag",
+    ]);
+
+    assert_eq!(
+        fixture.boot_file("sysconf.txt").unwrap(),
+        "hostname=from-flag\nkeymap=de\n"
     );
+    assert_eq!(fixture.boot_file("notes.txt").unwrap(), "hello");
 }
 
 /// Customization is only applied to images with a readable boot partition;
@@ -360,6 +896,45 @@ fn flash_sd_customization_on_partitionless_image_fails() {
     ]);
 }
 
+/// Invalid customization values are rejected before anything is written.
+#[test]
+#[should_panic(expected = "Invalid hostname")]
+fn flash_sd_rejects_invalid_hostname() {
+    let fixture = SdFixture::new();
+
+    run_cli([
+        "bb-imager-cli",
+        "flash",
+        "--quiet",
+        "sd",
+        fixture.img(),
+        fixture.dst(),
+        "--file-destination",
+        "--sysconfig",
+        "--hostname",
+        "bad_host",
+    ]);
+}
+
+#[test]
+#[should_panic(expected = "Invalid IP address")]
+fn flash_sd_rejects_address_without_prefix() {
+    let fixture = SdFixture::new();
+
+    run_cli([
+        "bb-imager-cli",
+        "flash",
+        "--quiet",
+        "sd",
+        fixture.img(),
+        fixture.dst(),
+        "--file-destination",
+        "--sysconfig",
+        "--eth-address",
+        "192.168.7.2",
+    ]);
+}
+
 #[test]
 #[should_panic(expected = "Failed to flash")]
 fn flash_sd_missing_image_fails() {
@@ -4,7 +4,9 @@ CREATE TABLE remote_configs
         id INTEGER PRIMARY KEY ASC,
 	url TEXT NOT NULL UNIQUE,
 	-- Flag to indicate if the config has been resolved
-	fetched INTEGER NOT NULL DEFAULT 0
+	fetched INTEGER NOT NULL DEFAULT 0,
+	-- Minisign public key. Only set if declared by a trusted config
+	public_key TEXT
 ) STRICT;
 
 CREATE TABLE boards
@@ -40,6 +42,8 @@ CREATE TABLE os_sublists (
 	
 	-- NULL = remote sublist
 	subitems_url TEXT DEFAULT NULL,
+	-- Minisign public key for subitems_url. Only set if declared by a trusted config
+	public_key TEXT DEFAULT NULL,
         -- NULL = Not from remote config. Can be from a remote subitem.
         remote_config_id INTEGER DEFAULT NULL,
 	
@@ -63,6 +67,7 @@ CREATE TABLE os_images(
 	description TEXT NOT NULL,
 	icon TEXT NOT NULL,
 	url TEXT NOT NULL,
+	mirrors BLOB,
 	image_download_size INTEGER,
 	image_download_sha256 BLOB NOT NULL,
 	extract_size INTEGER NOT NULL,
@@ -72,6 +77,8 @@ CREATE TABLE os_images(
 	info_text TEXT,
         support TEXT,
         remote_config_id INTEGER DEFAULT NULL,
+	-- 0 = from an unsigned remote config or sublist
+	verified INTEGER NOT NULL DEFAULT 1,
 
 	FOREIGN KEY (parent_id) REFERENCES os_sublists(id) ON DELETE CASCADE,
         FOREIGN KEY (remote_config_id) REFERENCES remote_configs(id) ON DELETE CASCADE
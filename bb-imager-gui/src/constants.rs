@@ -42,7 +42,8 @@ pub(crate) const FONT_BOLD: iced::Font = {
 };
 
 // Base Fonts
-pub(crate) const FONT_NORMAL_BYTES: &[u8] = include_bytes!("../assets/fonts/Nunito-Regular-subset.ttf");
+pub(crate) const FONT_NORMAL_BYTES: &[u8] =
+    include_bytes!("../assets/fonts/Nunito-Regular-subset.ttf");
 pub(crate) const FONT_BOLD_BYTES: &[u8] = include_bytes!("../assets/fonts/Nunito-Bold-subset.ttf");
 
 // Theme
@@ -53,15 +54,7 @@ pub(crate) const BACKGROUND: iced::Color = color!(30, 30, 30);
 pub(crate) const CARD: iced::Color = color!(45, 45, 45);
 pub(crate) const DANGER: iced::Color = color!(255, 0, 0);
 
-pub(crate) const KEYMAP_LAYOUTS: &[&str] = &[
-    "af", "al", "am", "ara", "at", "au", "az", "ba", "bd", "be", "bg", "br", "brai", "bt", "bw",
-    "by", "ca", "cd", "ch", "cm", "cn", "cz", "de", "dk", "dz", "ee", "epo", "es", "et", "fi",
-    "fo", "fr", "gb", "ge", "gh", "gn", "gr", "hr", "hu", "id", "ie", "il", "in", "iq", "ir", "is",
-    "it", "jp", "jv", "ke", "kg", "kh", "kr", "kz", "la", "latam", "lk", "lt", "lv", "ma", "mao",
-    "md", "me", "mk", "ml", "mm", "mn", "mt", "mv", "my", "ng", "nl", "no", "np", "ph", "pk", "pl",
-    "pt", "ro", "rs", "ru", "se", "si", "sk", "sn", "sy", "tg", "th", "tj", "tm", "tr", "tw", "tz",
-    "ua", "us", "uz", "vn", "za",
-];
+pub(crate) use bb_flasher::validate::KEYMAP_LAYOUTS;
 
 /// Resolve a keymap name to its entry in [`KEYMAP_LAYOUTS`].
 ///
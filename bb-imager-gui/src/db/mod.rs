@@ -106,6 +106,7 @@ pub(crate) struct OsImage {
     pub(crate) description: String,
     pub(crate) icon: Arc<Url>,
     pub(crate) url: Url,
+    pub(crate) mirrors: Vec<Url>,
     pub(crate) image_download_size: Option<i64>,
     pub(crate) image_download_sha256: [u8; 32],
     pub(crate) extract_size: i64,
@@ -114,6 +115,8 @@ pub(crate) struct OsImage {
     pub(crate) bmap: Option<Url>,
     pub(crate) info_text: Option<String>,
     pub(crate) support: Option<Url>,
+    /// `false` if the image comes from an unsigned remote config or sublist.
+    pub(crate) verified: bool,
 }
 
 impl OsImage {
@@ -124,6 +127,7 @@ impl OsImage {
             icon: Arc::new(value.get("icon")?),
             description: value.get("description")?,
             url: value.get("url")?,
+            mirrors: mirrors_from_row(value)?,
             image_download_size: value.get("image_download_size")?,
             image_download_sha256: value.get("image_download_sha256")?,
             extract_size: value.get("extract_size")?,
@@ -132,10 +136,37 @@ impl OsImage {
             bmap: value.get("bmap")?,
             info_text: value.get("info_text")?,
             support: value.get("support")?,
+            verified: value.get("verified")?,
         })
     }
 }
 
+/// A remote config or sublist which needs to be fetched.
+#[derive(Debug, Clone)]
+pub(crate) struct RemoteSource {
+    pub(crate) id: i64,
+    pub(crate) url: Url,
+    /// Key to verify the signature with. Only set if declared by a trusted config.
+    pub(crate) public_key: Option<String>,
+}
+
+impl RemoteSource {
+    fn from_row(value: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: value.get("id")?,
+            url: value.get("url")?,
+            public_key: value.get("public_key")?,
+        })
+    }
+}
+
+fn mirrors_from_row(value: &rusqlite::Row<'_>) -> rusqlite::Result<Vec<Url>> {
+    let mirrors: Option<Vec<u8>> = value.get("mirrors")?;
+    Ok(mirrors
+        .map(|x| serde_json::from_slice(&x).unwrap())
+        .unwrap_or_default())
+}
+
 const MIGRATIONS: &str = include_str!("../../migrations/20260316134019_init.sql");
 
 impl Db {
@@ -161,22 +192,27 @@ impl Db {
         // Run migrations
         db.execute_batch(MIGRATIONS)?;
 
-        Self::add_config_internal(&mut db, cfg, None)
+        // The embedded config is trusted.
+        Self::add_config_internal(&mut db, cfg, None, true)
     }
 
+    /// Add a config. Configs which are not `verified` cannot vouch for the keys of their remotes,
+    /// and their images are marked as unverified.
     pub(crate) fn add_config(
         &self,
         cfg: config::Config,
         remote_config_id: Option<i64>,
+        verified: bool,
     ) -> rusqlite::Result<()> {
         let mut db = self.db.lock().unwrap();
-        Self::add_config_internal(&mut db, cfg, remote_config_id)
+        Self::add_config_internal(&mut db, cfg, remote_config_id, verified)
     }
 
     fn add_config_internal(
         db: &mut Connection,
         cfg: config::Config,
         remote_config_id: Option<i64>,
+        verified: bool,
     ) -> rusqlite::Result<()> {
         let tx = db.transaction()?;
 
@@ -184,7 +220,7 @@ impl Db {
             Self::remote_config_fetched(&tx, x)?;
         }
 
-        Self::insert_remote_config(&tx, cfg.imager.remote_configs.iter())?;
+        Self::insert_remote_config(&tx, &cfg.imager, verified)?;
 
         for dev in cfg
             .imager
@@ -195,23 +231,25 @@ impl Db {
             Self::insert_board(&tx, dev)?;
         }
 
-        Self::insert_os_list_items(&tx, &cfg.os_list, None, remote_config_id)?;
+        Self::insert_os_list_items(&tx, &cfg.os_list, None, remote_config_id, verified)?;
 
         tx.commit()
     }
 
-    fn insert_remote_config<'a>(
+    fn insert_remote_config(
         exec: &Connection,
-        remote_configs: impl Iterator<Item = &'a Url>,
+        imager: &config::Imager,
+        verified: bool,
     ) -> rusqlite::Result<()> {
         let mut stmt = exec.prepare_cached(
             r#"
-                INSERT INTO remote_configs(url) VALUES ($1) 
+                INSERT INTO remote_configs(url, public_key) VALUES ($1, $2) 
                 ON CONFLICT DO NOTHING
                 "#,
         )?;
-        for u in remote_configs {
-            stmt.execute([u])?;
+        for u in &imager.remote_configs {
+            let key = imager.remote_config_keys.get(u).filter(|_| verified);
+            stmt.execute(rusqlite::params![u, key])?;
         }
 
         Ok(())
@@ -225,17 +263,12 @@ impl Db {
     }
 
     // Not cached: runs exactly once per process, from the `DbInitSuccess` handler.
-    pub(crate) fn remote_configs(&self) -> rusqlite::Result<Vec<(i64, Url)>> {
+    pub(crate) fn remote_configs(&self) -> rusqlite::Result<Vec<RemoteSource>> {
         let db = self.db.lock().unwrap();
 
         let res = db
-            .prepare("SELECT id, url FROM remote_configs WHERE fetched = FALSE")?
-            .query_map([], |r| {
-                let id: i64 = r.get("id")?;
-                let u: Url = r.get("url")?;
-
-                Ok((id, u))
-            })?
+            .prepare("SELECT id, url, public_key FROM remote_configs WHERE fetched = FALSE")?
+            .query_map([], RemoteSource::from_row)?
             .map(|x| x.unwrap())
             .collect();
 
@@ -246,6 +279,7 @@ impl Db {
         &self,
         id: i64,
         subitems: &[bb_config::config::OsListItem],
+        verified: bool,
     ) -> rusqlite::Result<()> {
         let mut db = self.db.lock().unwrap();
         let tx = db.transaction()?;
@@ -256,7 +290,7 @@ impl Db {
             stmt.execute([id])?;
         }
 
-        Self::insert_os_list_items(&tx, subitems, Some(id), None)?;
+        Self::insert_os_list_items(&tx, subitems, Some(id), None, verified)?;
 
         tx.commit()
     }
@@ -266,13 +300,14 @@ impl Db {
         items: &[config::OsListItem],
         start_pid: Option<i64>,
         remote_config_id: Option<i64>,
+        verified: bool,
     ) -> rusqlite::Result<()> {
         let mut imgs = Vec::from_iter(items.iter().map(|x| (start_pid, x)));
 
         while let Some((pid, img)) = imgs.pop() {
             match img {
                 config::OsListItem::Image(os_image) => {
-                    let id = Self::insert_image(exec, os_image, pid, remote_config_id)?;
+                    let id = Self::insert_image(exec, os_image, pid, remote_config_id, verified)?;
                     if let Some(p) = pid {
                         Self::insert_sublist_boards(exec, p, id)?
                     }
@@ -290,6 +325,7 @@ impl Db {
                             os_remote_sub_list,
                             pid,
                             remote_config_id,
+                            verified,
                         )?;
                         Self::insert_remote_sublist_boards(exec, id)?;
                     }
@@ -440,15 +476,18 @@ impl Db {
         img: &config::OsImage,
         parent_id: Option<i64>,
         remote_config_id: Option<i64>,
+        verified: bool,
     ) -> rusqlite::Result<i64> {
         let mut stmt = exec.prepare_cached(
             r#"
             INSERT INTO os_images(name, parent_id, description, icon, url,
                 image_download_size, image_download_sha256, extract_size,
-                release_date, init_format, bmap, info_text, remote_config_id, support)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+                release_date, init_format, bmap, info_text, remote_config_id, support, mirrors,
+                verified)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
             "#,
         )?;
+        let mirrors = serde_json::to_vec(&img.mirrors).unwrap();
         let id = stmt.insert(rusqlite::params![
             img.name,
             parent_id,
@@ -463,7 +502,9 @@ impl Db {
             img.bmap,
             img.info_text,
             remote_config_id,
-            img.support
+            img.support,
+            mirrors,
+            verified
         ])?;
 
         let mut stmt = exec.prepare_cached(
@@ -486,12 +527,13 @@ impl Db {
         img: &config::OsRemoteSubList,
         parent_id: Option<i64>,
         remote_config_id: Option<i64>,
+        verified: bool,
     ) -> rusqlite::Result<i64> {
         let mut stmt = exec.prepare_cached(
             r#"
             INSERT INTO os_sublists(parent_id, name, description, icon, 
-                flasher, subitems_url, remote_config_id)
-            VALUES ($1, $2, $3, $4, $5, $6, $7)
+                flasher, subitems_url, remote_config_id, public_key)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
             "#,
         )?;
         let id = stmt.insert(rusqlite::params![
@@ -501,7 +543,8 @@ impl Db {
             img.icon,
             img.flasher,
             img.subitems_url,
-            remote_config_id
+            remote_config_id,
+            img.public_key.as_ref().filter(|_| verified)
         ])?;
 
         let mut stmt = exec.prepare_cached(
@@ -562,9 +605,9 @@ impl Db {
         let db = self.db.lock().unwrap();
         let mut stmt = db.prepare_cached(
             r#"
-            SELECT id, name, description, icon, url, image_download_size,
+            SELECT id, name, description, icon, url, mirrors, image_download_size,
                 image_download_sha256, extract_size, release_date, init_format,
-                bmap, info_text, support
+                bmap, info_text, support, verified
             FROM os_images WHERE id = $1"#,
         )?;
         stmt.query_row([id], OsImage::from_row)
@@ -646,11 +689,11 @@ impl Db {
         &self,
         board_id: i64,
         parent_id: Option<i64>,
-    ) -> rusqlite::Result<Vec<(i64, Url)>> {
+    ) -> rusqlite::Result<Vec<RemoteSource>> {
         let db = self.db.lock().unwrap();
         let mut stmt = db.prepare_cached(
             r#"
-            SELECT s.id, s.subitems_url
+            SELECT s.id, s.subitems_url AS url, s.public_key
             FROM os_sublists s
             JOIN os_sublist_boards sb ON sb.sublist_id = s.id
             WHERE sb.board_id = $1
@@ -661,9 +704,10 @@ impl Db {
                 )"#,
         )?;
         let res = stmt
-            .query_map(rusqlite::params![board_id, parent_id], |r| {
-                Ok((r.get(0)?, r.get(1)?))
-            })?
+            .query_map(
+                rusqlite::params![board_id, parent_id],
+                RemoteSource::from_row,
+            )?
             .map(|x| x.unwrap())
             .collect();
 
@@ -706,11 +750,11 @@ impl Db {
         &self,
         board_id: i64,
         remote_config_id: i64,
-    ) -> rusqlite::Result<Vec<(i64, Url)>> {
+    ) -> rusqlite::Result<Vec<RemoteSource>> {
         let db = self.db.lock().unwrap();
         let mut stmt = db.prepare_cached(
             r#"
-            SELECT s.id, s.subitems_url
+            SELECT s.id, s.subitems_url AS url, s.public_key
             FROM os_sublists s
             JOIN os_sublist_boards sb ON sb.sublist_id = s.id
             WHERE sb.board_id = $1 
@@ -718,7 +762,7 @@ impl Db {
                 AND s.remote_config_id = $2"#,
         )?;
         let res = stmt
-            .query_map([board_id, remote_config_id], |r| Ok((r.get(0)?, r.get(1)?)))?
+            .query_map([board_id, remote_config_id], RemoteSource::from_row)?
             .map(|x| x.unwrap())
             .collect();
 
@@ -728,17 +772,17 @@ impl Db {
     pub(crate) fn os_remote_sublists_by_board(
         &self,
         board_id: i64,
-    ) -> rusqlite::Result<Vec<(i64, Url)>> {
+    ) -> rusqlite::Result<Vec<RemoteSource>> {
         let db = self.db.lock().unwrap();
         let mut stmt = db.prepare_cached(
             r#"
-            SELECT s.id, s.subitems_url
+            SELECT s.id, s.subitems_url AS url, s.public_key
             FROM os_sublists s
             JOIN os_sublist_boards sb ON sb.sublist_id = s.id
             WHERE sb.board_id = $1 AND s.subitems_url IS NOT NULL"#,
         )?;
         let res = stmt
-            .query_map([board_id], |r| Ok((r.get(0)?, r.get(1)?)))?
+            .query_map([board_id], RemoteSource::from_row)?
             .map(|x| x.unwrap())
             .collect();
 
@@ -827,8 +871,9 @@ impl Db {
 
         let mut stmt = db.prepare(
             r#"
-            SELECT name, description, icon, url, image_download_size, image_download_sha256,
-                extract_size, release_date, init_format, bmap, info_text, support
+            SELECT name, description, icon, url, mirrors, image_download_size,
+                image_download_sha256, extract_size, release_date, init_format, bmap, info_text,
+                support
             FROM os_images WHERE id = $1"#,
         )?;
 
@@ -841,6 +886,7 @@ impl Db {
                 description: value.get("description")?,
                 icon: value.get("icon")?,
                 url: value.get("url")?,
+                mirrors: mirrors_from_row(value)?,
                 image_download_size: image_download_size.map(|x| x as u64),
                 image_download_sha256: value.get("image_download_sha256")?,
                 extract_size: extract_size as u64,
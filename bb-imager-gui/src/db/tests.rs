@@ -40,8 +40,8 @@ fn init_loads_all_default_remote_configs() {
     );
 
     assert_eq!(expected_urls.len(), urls.len());
-    for (_, u) in urls {
-        assert!(expected_urls.contains(&u));
+    for x in urls {
+        assert!(expected_urls.contains(&x.url));
     }
 }
 
@@ -80,13 +80,14 @@ fn add_config_inserts_new_remote_configs() {
                     .try_into()
                     .unwrap(),
             ],
+            remote_config_keys: Default::default(),
             devices: vec![],
         },
         os_list: vec![],
     };
 
     // Add new config
-    db.add_config(new_config, None)
+    db.add_config(new_config, None, true)
         .expect("add_config should succeed");
 
     let updated_urls = db
@@ -102,13 +103,13 @@ fn add_config_inserts_new_remote_configs() {
     assert!(
         updated_urls
             .iter()
-            .any(|(_, u)| u.as_str() == "https://example.com/test-os-list.json")
+            .any(|x| x.url.as_str() == "https://example.com/test-os-list.json")
     );
 
     assert!(
         updated_urls
             .iter()
-            .any(|(_, u)| u.as_str() == "https://example.com/another-os-list.json")
+            .any(|x| x.url.as_str() == "https://example.com/another-os-list.json")
     );
 }
 
@@ -137,7 +138,7 @@ fn add_config_does_not_duplicate_remote_configs() {
 
     assert!(!initial_urls.is_empty());
 
-    let (_, existing_url) = initial_urls.first().unwrap().clone();
+    let existing_url = initial_urls.first().unwrap().url.clone();
 
     let initial_count = initial_urls.len();
 
@@ -150,7 +151,7 @@ fn add_config_does_not_duplicate_remote_configs() {
         os_list: vec![],
     };
 
-    db.add_config(new_config, None)
+    db.add_config(new_config, None, true)
         .expect("add_config should succeed");
 
     let updated_urls = db
@@ -210,7 +211,7 @@ fn add_config_inserts_device_into_board_list() {
         os_list: vec![],
     };
 
-    db.add_config(new_config, None)
+    db.add_config(new_config, None, true)
         .expect("add_config should succeed");
 
     let updated_boards = db
@@ -272,6 +273,7 @@ fn add_config_updates_existing_device_with_same_name() {
             os_list: vec![],
         },
         None,
+        true,
     )
     .expect("First add_config should succeed");
 
@@ -310,6 +312,7 @@ fn add_config_updates_existing_device_with_same_name() {
             os_list: vec![],
         },
         None,
+        true,
     )
     .expect("Second add_config should succeed");
 
@@ -378,6 +381,7 @@ fn add_config_inserts_os_image_for_board() {
         description: "Test OS description".to_string(),
         icon: "https://example.com/icon.png".try_into().unwrap(),
         url: "https://example.com/os.img.xz".try_into().unwrap(),
+        mirrors: Vec::new(),
         image_download_size: Some(1024),
         image_download_sha256: [1; 32],
         extract_size: 2048,
@@ -392,12 +396,13 @@ fn add_config_inserts_os_image_for_board() {
     let config = Config {
         imager: bb_config::config::Imager {
             remote_configs: Default::default(),
+            remote_config_keys: Default::default(),
             devices: vec![board.clone()],
         },
         os_list: vec![bb_config::config::OsListItem::Image(image.clone())],
     };
 
-    db.add_config(config, None)
+    db.add_config(config, None, true)
         .expect("add_config should succeed");
 
     let boards = db.board_list("").unwrap();
@@ -455,6 +460,7 @@ fn os_image_by_id_returns_correct_data() {
         description: "Test OS description".to_string(),
         icon: "https://example.com/icon.png".try_into().unwrap(),
         url: "https://example.com/os.img.xz".try_into().unwrap(),
+        mirrors: Vec::new(),
         image_download_size: Some(1024),
         image_download_sha256: [7; 32],
         extract_size: 4096,
@@ -473,12 +479,13 @@ fn os_image_by_id_returns_correct_data() {
     let config = Config {
         imager: bb_config::config::Imager {
             remote_configs: Default::default(),
+            remote_config_keys: Default::default(),
             devices: vec![board],
         },
         os_list: vec![bb_config::config::OsListItem::Image(image.clone())],
     };
 
-    db.add_config(config, None)
+    db.add_config(config, None, true)
         .expect("add_config should succeed");
 
     let boards = db.board_list("").unwrap();
@@ -555,6 +562,7 @@ fn add_config_inserts_os_sublist_for_board() {
         description: "Test OS description".to_string(),
         icon: "https://example.com/icon.png".try_into().unwrap(),
         url: "https://example.com/os.img.xz".try_into().unwrap(),
+        mirrors: Vec::new(),
         image_download_size: Some(1024),
         image_download_sha256: [1; 32],
         extract_size: 2048,
@@ -577,12 +585,13 @@ fn add_config_inserts_os_sublist_for_board() {
     let config = Config {
         imager: bb_config::config::Imager {
             remote_configs: Default::default(),
+            remote_config_keys: Default::default(),
             devices: vec![board],
         },
         os_list: vec![bb_config::config::OsListItem::SubList(sublist)],
     };
 
-    db.add_config(config, None)
+    db.add_config(config, None, true)
         .expect("add_config should succeed");
 
     let boards = db.board_list("").unwrap();
@@ -639,6 +648,7 @@ fn nested_os_sublists_propagate_board_support() {
         description: "Nested OS description".to_string(),
         icon: "https://example.com/icon.png".try_into().unwrap(),
         url: "https://example.com/os.img.xz".try_into().unwrap(),
+        mirrors: Vec::new(),
         image_download_size: Some(1024),
         image_download_sha256: [1; 32],
         extract_size: 2048,
@@ -669,12 +679,13 @@ fn nested_os_sublists_propagate_board_support() {
     let config = Config {
         imager: bb_config::config::Imager {
             remote_configs: Default::default(),
+            remote_config_keys: Default::default(),
             devices: vec![board],
         },
         os_list: vec![bb_config::config::OsListItem::SubList(parent_sublist)],
     };
 
-    db.add_config(config, None)
+    db.add_config(config, None, true)
         .expect("add_config should succeed");
 
     let board_id = db
@@ -739,18 +750,20 @@ fn remote_os_sublist_is_returned_for_board() {
         icon: "https://example.com/remote.png".try_into().unwrap(),
         flasher: bb_config::config::Flasher::SdCard,
         subitems_url: "https://example.com/os-list.json".try_into().unwrap(),
+        public_key: None,
         devices: Box::new(["test_board".into()]),
     };
 
     let config = Config {
         imager: bb_config::config::Imager {
             remote_configs: Default::default(),
+            remote_config_keys: Default::default(),
             devices: vec![board],
         },
         os_list: vec![bb_config::config::OsListItem::RemoteSubList(remote_sublist)],
     };
 
-    db.add_config(config, None)
+    db.add_config(config, None, true)
         .expect("add_config should succeed");
 
     let board_id = db
@@ -767,7 +780,7 @@ fn remote_os_sublist_is_returned_for_board() {
 
     assert_eq!(remote_lists.len(), 1);
     assert_eq!(
-        remote_lists[0].1.as_str(),
+        remote_lists[0].url.as_str(),
         "https://example.com/os-list.json"
     );
 }
@@ -824,18 +837,20 @@ fn remote_os_sublist_resolve_inserts_child_items_and_clears_url() {
         icon: "https://example.com/remote.png".try_into().unwrap(),
         flasher: bb_config::config::Flasher::SdCard,
         subitems_url: "https://example.com/os-list.json".try_into().unwrap(),
+        public_key: None,
         devices: Box::new(["test_board".into()]),
     };
 
     let config = Config {
         imager: bb_config::config::Imager {
             remote_configs: Default::default(),
+            remote_config_keys: Default::default(),
             devices: vec![board],
         },
         os_list: vec![bb_config::config::OsListItem::RemoteSubList(remote_sublist)],
     };
 
-    db.add_config(config, None)
+    db.add_config(config, None, true)
         .expect("add_config should succeed");
 
     let board_id = db
@@ -850,13 +865,14 @@ fn remote_os_sublist_resolve_inserts_child_items_and_clears_url() {
 
     assert_eq!(remote_lists.len(), 1);
 
-    let sublist_id = remote_lists[0].0;
+    let sublist_id = remote_lists[0].id;
 
     let child_image = bb_config::config::OsImage {
         name: "Fetched OS".to_string(),
         description: "Fetched OS description".to_string(),
         icon: "https://example.com/icon.png".try_into().unwrap(),
         url: "https://example.com/os.img.xz".try_into().unwrap(),
+        mirrors: Vec::new(),
         image_download_size: Some(1024),
         image_download_sha256: [1; 32],
         extract_size: 2048,
@@ -871,6 +887,7 @@ fn remote_os_sublist_resolve_inserts_child_items_and_clears_url() {
     db.os_remote_sublist_resolve(
         sublist_id,
         &[bb_config::config::OsListItem::Image(child_image)],
+        true,
     )
     .expect("resolve should succeed");
 
@@ -929,18 +946,20 @@ fn duplicate_remote_sublist_resolve_does_not_duplicate_os_items() {
         icon: "https://example.com/remote.png".try_into().unwrap(),
         flasher: bb_config::config::Flasher::SdCard,
         subitems_url: "https://example.com/os-list.json".try_into().unwrap(),
+        public_key: None,
         devices: Box::new(["test_board".into()]),
     };
 
     let config = Config {
         imager: bb_config::config::Imager {
             remote_configs: Default::default(),
+            remote_config_keys: Default::default(),
             devices: vec![board],
         },
         os_list: vec![bb_config::config::OsListItem::RemoteSubList(remote_sublist)],
     };
 
-    db.add_config(config, None)
+    db.add_config(config, None, true)
         .expect("add_config should succeed");
 
     let board_id = db
@@ -955,13 +974,14 @@ fn duplicate_remote_sublist_resolve_does_not_duplicate_os_items() {
 
     assert_eq!(remote_lists.len(), 1);
 
-    let sublist_id = remote_lists[0].0;
+    let sublist_id = remote_lists[0].id;
 
     let child_image = bb_config::config::OsImage {
         name: "Fetched OS".to_string(),
         description: "Fetched OS description".to_string(),
         icon: "https://example.com/icon.png".try_into().unwrap(),
         url: "https://example.com/os.img.xz".try_into().unwrap(),
+        mirrors: Vec::new(),
         image_download_size: Some(1024),
         image_download_sha256: [1; 32],
         extract_size: 2048,
@@ -977,6 +997,7 @@ fn duplicate_remote_sublist_resolve_does_not_duplicate_os_items() {
     db.os_remote_sublist_resolve(
         sublist_id,
         &[bb_config::config::OsListItem::Image(child_image.clone())],
+        true,
     )
     .expect("first resolve should succeed");
 
@@ -984,6 +1005,7 @@ fn duplicate_remote_sublist_resolve_does_not_duplicate_os_items() {
     let second = db.os_remote_sublist_resolve(
         sublist_id,
         &[bb_config::config::OsListItem::Image(child_image)],
+        true,
     );
     assert!(second.is_err());
 
@@ -1060,12 +1082,13 @@ fn board_list_search_filters_boards_case_insensitive() {
     let config = Config {
         imager: bb_config::config::Imager {
             remote_configs: Default::default(),
+            remote_config_keys: Default::default(),
             devices: vec![board1, board2, board3],
         },
         os_list: vec![],
     };
 
-    db.add_config(config, None)
+    db.add_config(config, None, true)
         .expect("add_config should succeed");
 
     let results = db.board_list("test").expect("search should succeed");
@@ -1089,11 +1112,13 @@ fn insert_board_helper(db: &Db, board: bb_config::config::Device) -> i64 {
         Config {
             imager: bb_config::config::Imager {
                 remote_configs: Default::default(),
+                remote_config_keys: Default::default(),
                 devices: vec![board],
             },
             os_list: vec![],
         },
         None,
+        true,
     )
     .expect("add_config should succeed");
 
@@ -1255,11 +1280,13 @@ fn insert_image_helper(
         Config {
             imager: bb_config::config::Imager {
                 remote_configs: Default::default(),
+                remote_config_keys: Default::default(),
                 devices: vec![board.clone()],
             },
             os_list: vec![bb_config::config::OsListItem::Image(image)],
         },
         None,
+        true,
     )
     .expect("add_config should succeed");
 
@@ -1322,6 +1349,7 @@ fn os_image_json_by_id_round_trips_image() {
         description: "Test OS description".to_string(),
         icon: "https://example.com/icon.png".try_into().unwrap(),
         url: "https://example.com/os.img.xz".try_into().unwrap(),
+        mirrors: vec!["https://mirror.example.com/os.img.xz".try_into().unwrap()],
         image_download_size: Some(1024),
         image_download_sha256: [7; 32],
         extract_size: 4096,
@@ -1378,6 +1406,7 @@ fn os_image_json_by_id_handles_image_without_optional_fields() {
         description: "Minimal OS description".to_string(),
         icon: "https://example.com/icon.png".try_into().unwrap(),
         url: "https://example.com/os.img.xz".try_into().unwrap(),
+        mirrors: Vec::new(),
         image_download_size: None,
         image_download_sha256: [0; 32],
         extract_size: 1,
@@ -1432,6 +1461,7 @@ fn os_image_json_by_id_devices_come_from_linked_boards() {
         description: "Test OS description".to_string(),
         icon: "https://example.com/icon.png".try_into().unwrap(),
         url: "https://example.com/os.img.xz".try_into().unwrap(),
+        mirrors: Vec::new(),
         image_download_size: None,
         image_download_sha256: [1; 32],
         extract_size: 1,
@@ -1499,3 +1529,148 @@ fn os_board_json_by_id_unknown_id_returns_no_rows() {
         "Unknown board id should return QueryReturnedNoRows, got {res:?}"
     );
 }
+
+/// Config with a keyed remote config, a keyed remote sublist and an image, all for one board.
+fn signed_remotes_config() -> Config {
+    let board = bb_config::config::Device {
+        name: "Test Board".to_string(),
+        description: "Test Board description".to_string(),
+        icon: None,
+        flasher: bb_config::config::Flasher::SdCard,
+        instructions: None,
+        oshw: None,
+        specification: vec![],
+        documentation: None,
+        tags: Box::new(["test_board".into()]),
+    };
+
+    let image = bb_config::config::OsImage {
+        name: "Test OS".to_string(),
+        description: "Test OS description".to_string(),
+        icon: "https://example.com/icon.png".try_into().unwrap(),
+        url: "https://example.com/os.img.xz".try_into().unwrap(),
+        mirrors: Vec::new(),
+        image_download_size: None,
+        image_download_sha256: [1; 32],
+        extract_size: 2048,
+        release_date: chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+        devices: Box::new(["test_board".into()]),
+        init_format: bb_config::config::InitFormat::None,
+        bmap: None,
+        info_text: None,
+        support: None,
+    };
+
+    let remote_sublist = bb_config::config::OsRemoteSubList {
+        name: "Remote OS List".to_string(),
+        description: "Remote description".to_string(),
+        icon: "https://example.com/remote.png".try_into().unwrap(),
+        flasher: bb_config::config::Flasher::SdCard,
+        subitems_url: "https://example.com/os-list.json".try_into().unwrap(),
+        public_key: Some("sublist key".to_string()),
+        devices: Box::new(["test_board".into()]),
+    };
+
+    let remote_config: Url = "https://example.com/signed.json".try_into().unwrap();
+
+    Config {
+        imager: bb_config::config::Imager {
+            remote_configs: vec![remote_config.clone()],
+            remote_config_keys: [(remote_config, "config key".to_string())].into(),
+            devices: vec![board],
+        },
+        os_list: vec![
+            bb_config::config::OsListItem::Image(image),
+            bb_config::config::OsListItem::RemoteSubList(remote_sublist),
+        ],
+    }
+}
+
+/// Look up the keys stored for the remotes in [`signed_remotes_config`], and whether its image
+/// is verified.
+fn signed_remotes_state(db: &Db) -> (Option<String>, Option<String>, bool) {
+    let board_id = db
+        .board_list("")
+        .unwrap()
+        .into_iter()
+        .find(|b| b.name == "Test Board")
+        .unwrap()
+        .id;
+
+    let config_key = db
+        .remote_configs()
+        .unwrap()
+        .into_iter()
+        .find(|x| x.url.as_str() == "https://example.com/signed.json")
+        .unwrap()
+        .public_key;
+    let sublist_key = db.os_remote_sublists(board_id, None).unwrap()[0]
+        .public_key
+        .clone();
+
+    let image_id = db
+        .os_image_items(board_id, None)
+        .unwrap()
+        .into_iter()
+        .find_map(|x| match x.id {
+            crate::helpers::OsImageId::OsImage(id) => Some(id),
+            _ => None,
+        })
+        .unwrap();
+    let verified = db.os_image_by_id(image_id).unwrap().verified;
+
+    (config_key, sublist_key, verified)
+}
+
+/// This test verifies that a verified config can vouch for the keys of
+/// its remotes.
+///
+/// What this test checks:
+/// 1. A verified config declaring keys for a remote config and a remote
+///    sublist is added.
+/// 2. The keys are returned along with the remotes to fetch.
+/// 3. Its image is marked as verified.
+///
+/// Why this matters:
+/// - Remotes with a key are only trusted if their signature is valid, so
+///   losing a key would silently downgrade a signed remote to unverified.
+#[test]
+fn verified_config_keeps_remote_keys() {
+    let db = Db::new().expect("Failed to create DB");
+    db.init().expect("DB init should succeed");
+
+    db.add_config(signed_remotes_config(), None, true)
+        .expect("add_config should succeed");
+
+    assert_eq!(
+        signed_remotes_state(&db),
+        (
+            Some("config key".to_string()),
+            Some("sublist key".to_string()),
+            true
+        )
+    );
+}
+
+/// This test verifies that an unverified config cannot vouch for the keys
+/// of its remotes.
+///
+/// What this test checks:
+/// 1. An unverified config declaring keys for a remote config and a remote
+///    sublist is added.
+/// 2. The keys are dropped, so the remotes are unverified as well.
+/// 3. Its image is marked as unverified.
+///
+/// Why this matters:
+/// - A compromised unsigned config could otherwise declare its own keys for
+///   remotes it controls, and have them shown as verified.
+#[test]
+fn unverified_config_drops_remote_keys() {
+    let db = Db::new().expect("Failed to create DB");
+    db.init().expect("DB init should succeed");
+
+    db.add_config(signed_remotes_config(), None, false)
+        .expect("add_config should succeed");
+
+    assert_eq!(signed_remotes_state(&db), (None, None, false));
+}
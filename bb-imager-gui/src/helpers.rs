@@ -77,11 +77,15 @@ impl BoardImage {
         if let Some(x) = image.image_download_size {
             details.push(("Download Size", pretty_bytes(x as u64)))
         }
+        if !image.verified {
+            details.push(("Source", "Unverified (unsigned remote config)".to_string()))
+        }
 
         Self::Image {
             img: RemoteImage::new(
                 image.name.into(),
                 Box::new(image.url),
+                image.mirrors,
                 image.image_download_sha256,
                 image.extract_size as u64,
                 downloader.clone(),
@@ -168,15 +172,18 @@ impl BoardImage {
             } if !matches!(img, SelectedImage::LocalImage(_)) => match init_format {
                 config::InitFormat::Sysconf => &[config::InitFormat::Sysconf],
                 config::InitFormat::CloudInit => &[config::InitFormat::CloudInit],
+                config::InitFormat::Armbian => &[config::InitFormat::Armbian],
                 _ => &[],
             },
             BoardImage::Image {
                 init_format,
                 flasher,
                 ..
-            } if *flasher == config::Flasher::SdCard => {
-                &[config::InitFormat::Sysconf, config::InitFormat::CloudInit]
-            }
+            } if *flasher == config::Flasher::SdCard => &[
+                config::InitFormat::Sysconf,
+                config::InitFormat::CloudInit,
+                config::InitFormat::Armbian,
+            ],
             BoardImage::Image { .. } => &[],
         }
     }
@@ -240,6 +247,26 @@ pub(crate) fn system_keymap() -> &'static str {
     (*SYSTEM_KEYMAP).unwrap_or("us")
 }
 
+/// Locale to pre-fill the customization page with, e.g. `en_US.UTF-8`.
+pub(crate) fn system_locale() -> String {
+    static SYSTEM_LOCALE: LazyLock<Option<String>> = LazyLock::new(|| {
+        let lang = whoami::lang_prefs().ok()?.message_langs().next()?;
+        let lang_str = lang.to_string();
+
+        let base = lang_str.split(['.', '@']).next().unwrap_or(&lang_str);
+        let base = base.replace('-', "_");
+        if base.is_empty() || base == "C" || base == "POSIX" {
+            return None;
+        }
+
+        Some(format!("{base}.UTF-8"))
+    });
+
+    SYSTEM_LOCALE
+        .clone()
+        .unwrap_or_else(|| "en_US.UTF-8".to_string())
+}
+
 /// Username to pre-fill the customization page with.
 ///
 /// Falls back to "beagle" rather than an empty name: an empty username is not a
@@ -258,6 +285,7 @@ pub(crate) fn default_user() -> &'static str {
 pub(crate) struct RemoteImage {
     name: Box<str>,
     url: Box<url::Url>,
+    mirrors: Vec<url::Url>,
     extract_sha256: [u8; 32],
     extract_size: u64,
     downloader: bb_downloader::Downloader,
@@ -267,6 +295,7 @@ impl RemoteImage {
     pub(crate) fn new(
         name: Box<str>,
         url: Box<url::Url>,
+        mirrors: Vec<url::Url>,
         extract_sha256: [u8; 32],
         extract_size: u64,
         downloader: bb_downloader::Downloader,
@@ -274,6 +303,7 @@ impl RemoteImage {
         Self {
             name,
             url,
+            mirrors,
             extract_sha256,
             extract_size,
             downloader,
@@ -303,8 +333,9 @@ impl RemoteImage {
             let sha = self.extract_sha256;
 
             let t: tokio::task::JoinHandle<io::Result<()>> = rt.spawn(async move {
+                let urls = std::iter::once(*self.url).chain(self.mirrors);
                 self.downloader
-                    .download_to_stream(*self.url, sha, tx_stream)
+                    .download_to_stream_with_mirrors(urls, sha, tx_stream)
                     .await
                     .map_err(|e| {
                         let msg = format!("Error while downloading Os Image: {e}");
@@ -442,17 +473,20 @@ pub(crate) async fn flash(
             },
             customization,
             Destination::LocalFile(f),
-        ) if flasher == config::Flasher::SdCard => tokio::task::spawn_blocking(move || {
-            bb_flasher::sd::Flasher::with_file_dest(
-                img.into_image_fn(),
-                bmap.map(|x| x.into_fn()),
-                f,
-                customization.sd_customization(),
-            )
-            .flash(Some(chan), Some(cancel_sync))
-        })
-        .await
-        .unwrap(),
+        ) if flasher == config::Flasher::SdCard => {
+            let customization = customization.sd_customization()?;
+            tokio::task::spawn_blocking(move || {
+                bb_flasher::sd::Flasher::with_file_dest(
+                    img.into_image_fn(),
+                    bmap.map(|x| x.into_fn()),
+                    f,
+                    customization,
+                )
+                .flash(Some(chan), Some(cancel_sync))
+            })
+            .await
+            .unwrap()
+        }
         #[cfg(feature = "sd")]
         (
             BoardImage::Image {
@@ -460,17 +494,20 @@ pub(crate) async fn flash(
             },
             customization,
             Destination::SdCard(t),
-        ) if flasher == config::Flasher::SdCard => tokio::task::spawn_blocking(move || {
-            bb_flasher::sd::Flasher::new(
-                img.into_image_fn(),
-                bmap.map(|x| x.into_fn()),
-                t,
-                customization.sd_customization(),
-            )
-            .flash(Some(chan), Some(cancel_sync))
-        })
-        .await
-        .unwrap(),
+        ) if flasher == config::Flasher::SdCard => {
+            let customization = customization.sd_customization()?;
+            tokio::task::spawn_blocking(move || {
+                bb_flasher::sd::Flasher::new(
+                    img.into_image_fn(),
+                    bmap.map(|x| x.into_fn()),
+                    t,
+                    customization,
+                )
+                .flash(Some(chan), Some(cancel_sync))
+            })
+            .await
+            .unwrap()
+        }
         #[cfg(feature = "sd")]
         (BoardImage::Image { img, flasher, .. }, _, Destination::SdCard(t))
             if flasher == config::Flasher::SdCardBootfs =>
@@ -680,6 +717,7 @@ pub(crate) enum FlashingCustomization {
     NoneSd,
     LinuxSdSysconfig(crate::persistance::SdSysconfCustomization),
     LinuxSdCloudInit(crate::persistance::SdSysconfCustomization),
+    LinuxSdArmbian(crate::persistance::SdSysconfCustomization),
     Bcf,
     Msp430,
     Zepto,
@@ -710,6 +748,15 @@ impl FlashingCustomization {
                         .unwrap_or_default(),
                 )
             }
+            config::Flasher::SdCard if img.init_format() == config::InitFormat::Armbian => {
+                Self::LinuxSdArmbian(
+                    app_config
+                        .sd_customization
+                        .as_ref()
+                        .map(|x| x.sysconf_customization().cloned().unwrap_or_default())
+                        .unwrap_or_default(),
+                )
+            }
             config::Flasher::SdCard | config::Flasher::SdCardBootfs => Self::NoneSd,
             config::Flasher::BeagleConnectFreedom => Self::Bcf,
             config::Flasher::Msp430Usb => Self::Msp430,
@@ -726,6 +773,9 @@ impl FlashingCustomization {
             Self::LinuxSdCloudInit(_) => {
                 *self = Self::LinuxSdCloudInit(Default::default());
             }
+            Self::LinuxSdArmbian(_) => {
+                *self = Self::LinuxSdArmbian(Default::default());
+            }
             _ => {}
         }
     }
@@ -742,6 +792,16 @@ impl FlashingCustomization {
                 ans.into()
             }
             Self::LinuxSdCloudInit(x) => sd_modifications_common(x).into(),
+            Self::LinuxSdArmbian(x) => {
+                let mut ans = sd_modifications_common(x);
+                // Armbian has no keymap or SSH key setting; the locale takes its place.
+                ans.retain(|x| !matches!(*x, "Keymap configured" | "SSH Key configured"));
+                if x.locale.is_some() {
+                    ans.push("Locale configured");
+                }
+
+                ans.into()
+            }
             // Nothing is written for these, so there is nothing to report.
             Self::NoneSd | Self::Msp430 | Self::Bcf | Self::Zepto => Box::default(),
         }
@@ -751,18 +811,48 @@ impl FlashingCustomization {
         match self {
             FlashingCustomization::LinuxSdSysconfig(sd_customization)
             | FlashingCustomization::LinuxSdCloudInit(sd_customization) => {
-                sd_customization.validate_user()
+                sd_customization.errors(false).is_empty()
+            }
+            FlashingCustomization::LinuxSdArmbian(sd_customization) => {
+                sd_customization.errors(true).is_empty()
             }
             _ => true,
         }
     }
 
+    /// Save current customization as a named profile.
     #[cfg(feature = "sd")]
-    fn sd_customization(self) -> bb_flasher::sd::FlashingSdLinuxConfig {
+    pub(crate) fn to_profile(&self, name: &str) -> Option<bb_flasher::sd::Profile> {
+        use bb_flasher::sd::profile::InitFormat;
+
         match self {
-            FlashingCustomization::LinuxSdSysconfig(c) => c.sysconfig(),
-            FlashingCustomization::LinuxSdCloudInit(c) => c.cloudinit(),
-            FlashingCustomization::NoneSd => bb_flasher::sd::FlashingSdLinuxConfig::none(),
+            Self::LinuxSdSysconfig(c) => Some(c.to_profile(name, InitFormat::Sysconf)),
+            Self::LinuxSdCloudInit(c) => Some(c.to_profile(name, InitFormat::CloudInit)),
+            Self::LinuxSdArmbian(c) => Some(c.to_profile(name, InitFormat::Armbian)),
+            _ => None,
+        }
+    }
+
+    /// Replace current customization with values from a profile. The init format is decided by
+    /// the image, so it is not changed.
+    #[cfg(feature = "sd")]
+    pub(crate) fn with_profile(self, profile: &bb_flasher::sd::Profile) -> Self {
+        let c = crate::persistance::SdSysconfCustomization::from_profile(profile);
+        match self {
+            Self::LinuxSdSysconfig(_) => Self::LinuxSdSysconfig(c),
+            Self::LinuxSdCloudInit(_) => Self::LinuxSdCloudInit(c),
+            Self::LinuxSdArmbian(_) => Self::LinuxSdArmbian(c),
+            _ => self,
+        }
+    }
+
+    #[cfg(feature = "sd")]
+    fn sd_customization(self) -> anyhow::Result<bb_flasher::sd::FlashingSdLinuxConfig> {
+        match self {
+            FlashingCustomization::LinuxSdSysconfig(c) => c.sysconfig().map_err(Into::into),
+            FlashingCustomization::LinuxSdCloudInit(c) => Ok(c.cloudinit()),
+            FlashingCustomization::LinuxSdArmbian(c) => c.armbian().map_err(Into::into),
+            FlashingCustomization::NoneSd => Ok(bb_flasher::sd::FlashingSdLinuxConfig::none()),
             FlashingCustomization::Bcf
             | FlashingCustomization::Msp430
             | FlashingCustomization::Zepto => unreachable!(),
@@ -815,6 +905,15 @@ pub(crate) fn project_dirs() -> Option<directories::ProjectDirs> {
     )
 }
 
+/// Directory of saved customization profiles.
+#[cfg(feature = "sd")]
+pub(crate) fn profile_store() -> Option<bb_flasher::sd::ProfileStore> {
+    let dirs = project_dirs()?;
+    Some(bb_flasher::sd::ProfileStore::new(
+        dirs.config_local_dir().join("profiles"),
+    ))
+}
+
 pub(crate) fn log_file_path() -> PathBuf {
     let dirs = project_dirs().unwrap();
     dirs.cache_dir().with_file_name(format!(
@@ -853,7 +952,8 @@ pub(crate) fn no_customization(
     match flasher {
         config::Flasher::SdCard
             if img.init_format() == config::InitFormat::Sysconf
-                || img.init_format() == config::InitFormat::CloudInit =>
+                || img.init_format() == config::InitFormat::CloudInit
+                || img.init_format() == config::InitFormat::Armbian =>
         {
             None
         }
@@ -1025,19 +1125,45 @@ pub(crate) fn fetch_images(
     iced::Task::batch(tasks)
 }
 
+/// Fetch a remote config or sublist. If the source has a trusted key, its detached signature must
+/// be valid. Returns whether the source was verified.
+pub(crate) async fn fetch_remote_json<T: serde::de::DeserializeOwned>(
+    downloader: &bb_downloader::Downloader,
+    source: crate::db::RemoteSource,
+) -> io::Result<(T, bool)> {
+    let Some(key) = source.public_key else {
+        tracing::warn!("No trusted key for {}. Marking as unverified", source.url);
+        let json = downloader.download_json(source.url).await?;
+        return Ok((json, false));
+    };
+
+    let signature_url = bb_config::signature::signature_url(&source.url);
+    let data = downloader.download_revalidate(source.url).await?;
+    let signature = downloader.download_revalidate(signature_url).await?;
+    let data = std::fs::read(data)?;
+    let signature = std::fs::read_to_string(signature)?;
+
+    bb_config::signature::verify(&data, &signature, &key)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    let json = serde_json::from_slice(&data).map_err(io::Error::other)?;
+    Ok((json, true))
+}
+
 pub(crate) fn fetch_remote_subitems(
-    items: impl IntoIterator<Item = (i64, Url)>,
+    items: impl IntoIterator<Item = crate::db::RemoteSource>,
     downloader: bb_downloader::Downloader,
 ) -> iced::Task<BBImagerMessage> {
-    let temp = items.into_iter().map(move |(id, url)| {
-        let url_clone = url.clone();
+    let temp = items.into_iter().map(move |source| {
+        let (id, url) = (source.id, source.url.clone());
         let dl = downloader.clone();
         iced::Task::perform(
-            async move { dl.download_json_no_cache(url_clone).await },
+            async move { fetch_remote_json(&dl, source).await },
             move |x| match x {
-                Ok(json) => BBImagerMessage::ResolveRemoteSubitemItem {
+                Ok((json, verified)) => BBImagerMessage::ResolveRemoteSubitemItem {
                     item: json,
                     target: id,
+                    verified,
                 },
                 Err(e) => {
                     tracing::error!("Failed to get remote item {}: {e}", url.as_str());
@@ -1192,6 +1318,20 @@ mod tests {
         );
     }
 
+    /// Armbian has no keymap or SSH key, so values carried over from the shared
+    /// config must not be reported, while the Armbian-only locale is.
+    #[test]
+    fn armbian_modifications_report_locale_not_keymap() {
+        let c = SdSysconfCustomization::default()
+            .update_keymap(Some("us".into()))
+            .update_ssh(Some("ssh-ed25519 AAAA".into()))
+            .update_locale(Some("en_US.UTF-8".into()));
+        assert_eq!(
+            FlashingCustomization::LinuxSdArmbian(c).modifications(),
+            ["Locale configured"].into()
+        );
+    }
+
     /// A remote SD image with the given init format.
     ///
     /// Remote specifically: a local image always reports `InitFormat::None`, so
@@ -1207,6 +1347,7 @@ mod tests {
                 description: "test".to_string(),
                 icon: std::sync::Arc::new(url::Url::parse("https://example.com/icon.png").unwrap()),
                 url: url::Url::parse("https://example.com/os.img.xz").unwrap(),
+                mirrors: Vec::new(),
                 image_download_size: None,
                 image_download_sha256: [0u8; 32],
                 extract_size: 0,
@@ -1215,6 +1356,7 @@ mod tests {
                 bmap: None,
                 info_text: None,
                 support: None,
+                verified: true,
             },
             config::Flasher::SdCard,
             downloader,
@@ -1225,11 +1367,15 @@ mod tests {
     /// writes an empty config, so the user silently loses hostname, user, wifi
     /// and SSH key.
     ///
-    /// The two formats are checked by separate guards, so it is possible to fix
+    /// The formats are checked by separate guards, so it is possible to fix
     /// or break one without touching the other.
     #[test]
     fn customizable_init_formats_reach_the_customization_page() {
-        for format in [config::InitFormat::Sysconf, config::InitFormat::CloudInit] {
+        for format in [
+            config::InitFormat::Sysconf,
+            config::InitFormat::CloudInit,
+            config::InitFormat::Armbian,
+        ] {
             let img = remote_sd_image(format);
             assert!(
                 no_customization(config::Flasher::SdCard, &img).is_none(),
@@ -1238,20 +1384,18 @@ mod tests {
         }
     }
 
-    /// The counterpart: formats we cannot write skip the page rather than
-    /// showing one that would do nothing.
+    /// The counterpart: images without an init format skip the page rather
+    /// than showing one that would do nothing.
     #[test]
     fn unwritable_init_formats_skip_the_customization_page() {
-        for format in [config::InitFormat::None, config::InitFormat::Armbian] {
-            let img = remote_sd_image(format);
-            assert!(
-                matches!(
-                    no_customization(config::Flasher::SdCard, &img),
-                    Some(FlashingCustomization::NoneSd)
-                ),
-                "{format:?} images have no customization we can apply"
-            );
-        }
+        let img = remote_sd_image(config::InitFormat::None);
+        assert!(
+            matches!(
+                no_customization(config::Flasher::SdCard, &img),
+                Some(FlashingCustomization::NoneSd)
+            ),
+            "images without init format have no customization we can apply"
+        );
     }
 
     #[test]
@@ -106,7 +106,7 @@ impl BBImager {
     fn new() -> (Self, Task<BBImagerMessage>) {
         let app_config = persistance::GuiConfiguration::load().unwrap_or_default();
 
-        let downloader = bb_downloader::Downloader::new(
+        let mut downloader = bb_downloader::Downloader::builder(
             directories::ProjectDirs::from(
                 PACKAGE_QUALIFIER.0,
                 PACKAGE_QUALIFIER.1,
@@ -115,8 +115,14 @@ impl BBImager {
             .unwrap()
             .cache_dir()
             .to_path_buf(),
-        )
-        .unwrap();
+        );
+        if let Some(x) = app_config.max_cache_size {
+            downloader = downloader.max_cache_size(x);
+        }
+        if let Some(x) = &app_config.local_mirror {
+            downloader = downloader.local_mirror(x);
+        }
+        let downloader = downloader.build().unwrap();
 
         let db = crate::db::Db::new().unwrap();
 
@@ -430,6 +436,10 @@ impl BBImager {
                         customization,
                         has_customization,
                     },
+                    #[cfg(feature = "sd")]
+                    profiles: Box::default(),
+                    #[cfg(feature = "sd")]
+                    profile_name: String::new(),
                 };
 
                 if has_customization {
@@ -460,11 +470,14 @@ impl BBImager {
                     self.scroll_reset(),
                 ])
             }
+            #[cfg(feature = "sd")]
+            Self::Customize(inner) => Task::batch([inner.refresh_profiles(), self.scroll_reset()]),
             Self::Review(inner) => match &inner.ctx.customization {
-                // Both variants are backed by the same `sysconf` slot, matching how
+                // All variants are backed by the same `sysconf` slot, matching how
                 // `FlashingCustomization::new` loads them.
                 helpers::FlashingCustomization::LinuxSdSysconfig(c)
-                | helpers::FlashingCustomization::LinuxSdCloudInit(c) => {
+                | helpers::FlashingCustomization::LinuxSdCloudInit(c)
+                | helpers::FlashingCustomization::LinuxSdArmbian(c) => {
                     let mut temp = inner
                         .common
                         .app_config
@@ -16,10 +16,15 @@ pub(crate) enum BBImagerMessage {
     Null,
 
     /// Config related options
-    ExtendConfig((i64, bb_config::Config)),
+    ExtendConfig {
+        id: i64,
+        config: bb_config::Config,
+        verified: bool,
+    },
     ResolveRemoteSubitemItem {
         item: Box<[bb_config::config::OsListItem]>,
         target: i64,
+        verified: bool,
     },
 
     /// A new version of application is available
@@ -46,6 +51,17 @@ pub(crate) enum BBImagerMessage {
     // Customization Page
     UpdateFlashConfig(crate::helpers::FlashingCustomization),
     ResetFlashingConfig,
+    /// Names of saved customization profiles
+    #[cfg(feature = "sd")]
+    ProfilesUpdated(Box<[Box<str>]>),
+    /// Load a saved customization profile by name
+    #[cfg(feature = "sd")]
+    SelectProfile(Box<str>),
+    #[cfg(feature = "sd")]
+    UpdateProfileName(String),
+    /// Save current customization as a profile
+    #[cfg(feature = "sd")]
+    SaveProfile,
 
     // Review Page
     FlashStart,
@@ -239,16 +255,23 @@ pub(crate) fn update(state: &mut BBImager, message: BBImagerMessage) -> Task<BBI
             });
             return helpers::fetch_images(&common.downloader, iter);
         }
-        BBImagerMessage::ExtendConfig((u, c)) => {
+        BBImagerMessage::ExtendConfig {
+            id: u,
+            config: c,
+            verified,
+        } => {
             tracing::debug!("Update Config: {:#?}", c);
 
             let db = state.common().db.clone();
-            let db_task = Task::perform(blocking_future(move || db.add_config(c, Some(u))), |x| {
-                if let Err(e) = x {
-                    tracing::error!("Failed to merge config {e}");
-                }
-                BBImagerMessage::Null
-            });
+            let db_task = Task::perform(
+                blocking_future(move || db.add_config(c, Some(u), verified)),
+                |x| {
+                    if let Err(e) = x {
+                        tracing::error!("Failed to merge config {e}");
+                    }
+                    BBImagerMessage::Null
+                },
+            );
 
             let tail_tasks = match state {
                 // If we are in ChooseBoard page, update the board list
@@ -277,7 +300,11 @@ pub(crate) fn update(state: &mut BBImager, message: BBImagerMessage) -> Task<BBI
             // We want fetch board images to run after the config has been added
             return db_task.chain(tail_tasks);
         }
-        BBImagerMessage::ResolveRemoteSubitemItem { item, target } => {
+        BBImagerMessage::ResolveRemoteSubitemItem {
+            item,
+            target,
+            verified,
+        } => {
             let db = state.common().db.clone();
             let tail = match &state {
                 BBImager::ChooseOs(inner) => Task::batch([
@@ -290,7 +317,8 @@ pub(crate) fn update(state: &mut BBImager, message: BBImagerMessage) -> Task<BBI
             };
 
             return Task::future(blocking_future(move || {
-                db.os_remote_sublist_resolve(target, &item).unwrap();
+                db.os_remote_sublist_resolve(target, &item, verified)
+                    .unwrap();
                 BBImagerMessage::Null
             }))
             .chain(tail);
@@ -360,6 +388,30 @@ pub(crate) fn update(state: &mut BBImager, message: BBImagerMessage) -> Task<BBI
             }
             _ => panic!("Unexpected message"),
         },
+        #[cfg(feature = "sd")]
+        BBImagerMessage::ProfilesUpdated(x) => {
+            // Profiles can finish loading after moving to a different page.
+            if let BBImager::Customize(inner) = state {
+                inner.profiles = x;
+            }
+        }
+        #[cfg(feature = "sd")]
+        BBImagerMessage::SelectProfile(x) => match state {
+            BBImager::Customize(inner) => return inner.load_profile(x),
+            _ => panic!("Unexpected message"),
+        },
+        #[cfg(feature = "sd")]
+        BBImagerMessage::UpdateProfileName(x) => match state {
+            BBImager::Customize(inner) => {
+                inner.profile_name = x;
+            }
+            _ => panic!("Unexpected message"),
+        },
+        #[cfg(feature = "sd")]
+        BBImagerMessage::SaveProfile => match state {
+            BBImager::Customize(inner) => return inner.save_profile(),
+            _ => panic!("Unexpected message"),
+        },
         BBImagerMessage::FlashCancel => {
             let mut msg = "Flashing cancelled by user";
 
@@ -528,15 +580,17 @@ pub(crate) fn update(state: &mut BBImager, message: BBImagerMessage) -> Task<BBI
 
             let config_fetch_task = Task::future(blocking_future(move || {
                 let configs = db.remote_configs().unwrap();
-                let tasks = configs.into_iter().map(move |(i, u)| {
+                let tasks = configs.into_iter().map(move |source| {
                     let dc = downloader.clone();
+                    let id = source.id;
                     Task::perform(
-                        async move {
-                            let res = dc.download_json_no_cache(u).await?;
-                            Ok((i, res))
-                        },
-                        |x: std::io::Result<(i64, bb_config::config::Config)>| match x {
-                            Ok(y) => BBImagerMessage::ExtendConfig(y),
+                        async move { helpers::fetch_remote_json(&dc, source).await },
+                        move |x: std::io::Result<(bb_config::Config, bool)>| match x {
+                            Ok((config, verified)) => BBImagerMessage::ExtendConfig {
+                                id,
+                                config,
+                                verified,
+                            },
                             Err(e) => {
                                 tracing::error!("Failed to fetch config: {e}");
                                 BBImagerMessage::Null
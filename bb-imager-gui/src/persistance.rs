@@ -10,6 +10,14 @@ use serde::{Deserialize, Serialize};
 pub(crate) struct GuiConfiguration {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) sd_customization: Option<SdCustomization>,
+    /// Maximum size of the download cache in bytes. Once exceeded, the least recently used images
+    /// are evicted. The cache is not limited unless set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) max_cache_size: Option<u64>,
+    /// Directory to serve downloads from, if mirrored there. See
+    /// [`bb_downloader::DownloaderBuilder::local_mirror`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) local_mirror: Option<PathBuf>,
 }
 
 impl GuiConfiguration {
@@ -63,6 +71,9 @@ impl SdCustomization {
     }
 
     pub(crate) fn update_sysconfig(&mut self, t: SdSysconfCustomization) {
+        // Only the hash of the password is kept around for reuse.
+        #[cfg(feature = "sd")]
+        let t = t.hash_password();
         self.sysconf = Some(t)
     }
 }
@@ -76,6 +87,8 @@ pub(crate) struct SdSysconfCustomization {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) keymap: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) locale: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) user: Option<SdCustomizationUser>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) wifi: Option<SdCustomizationWifi>,
@@ -91,6 +104,7 @@ impl Default for SdSysconfCustomization {
             hostname: None,
             timezone: None,
             keymap: None,
+            locale: None,
             user: None,
             wifi: None,
             ssh: None,
@@ -119,6 +133,11 @@ impl SdSysconfCustomization {
         self
     }
 
+    pub(crate) fn update_locale(mut self, t: Option<String>) -> Self {
+        self.locale = t;
+        self
+    }
+
     pub(crate) fn update_user(mut self, t: Option<SdCustomizationUser>) -> Self {
         self.user = t;
         self
@@ -139,20 +158,131 @@ impl SdSysconfCustomization {
         self
     }
 
-    pub(crate) fn validate_user(&self) -> bool {
-        match &self.user {
-            Some(x) => x.validate_username(),
-            None => true,
+    /// Check all configured fields. A saved password hash is not checked again, unless
+    /// `plaintext_password` is set since the target (sysconf or Armbian) does not support hashes.
+    pub(crate) fn errors(
+        &self,
+        plaintext_password: bool,
+    ) -> bb_flasher::validate::ValidationErrors {
+        bb_flasher::validate::Inputs {
+            hostname: self.hostname.as_deref(),
+            timezone: None,
+            keymap: self.keymap.as_deref(),
+            username: self.user.as_ref().map(|x| x.username.as_str()),
+            password: self
+                .user
+                .as_ref()
+                .filter(|x| {
+                    plaintext_password || x.password_hash.is_none() || !x.password.is_empty()
+                })
+                .map(|x| x.password.as_str()),
+            ssid: self.wifi.as_ref().map(|x| x.ssid.as_str()),
+            wifi_passphrase: self.wifi.as_ref().map(|x| x.password.as_str()),
+            ssh_key: self.ssh.as_deref(),
+        }
+        .errors()
+    }
+
+    #[cfg(feature = "sd")]
+    fn hash_password(mut self) -> Self {
+        self.user = self.user.map(SdCustomizationUser::hash_password);
+        self
+    }
+
+    /// Convert to a profile for `init_format`. Options not supported by `init_format` are left out,
+    /// and only the hash of the password is saved. sysconf and Armbian do not support hashes, so the
+    /// password is kept in plaintext for them.
+    #[cfg(feature = "sd")]
+    pub(crate) fn to_profile(
+        &self,
+        name: &str,
+        init_format: bb_flasher::sd::profile::InitFormat,
+    ) -> bb_flasher::sd::Profile {
+        use bb_flasher::sd::profile::InitFormat;
+
+        let temp = if init_format == InitFormat::CloudInit {
+            self.clone().hash_password()
+        } else {
+            self.clone()
+        };
+
+        bb_flasher::sd::Profile {
+            name: Some(name.into()),
+            init_format,
+            hostname: temp.hostname.map(Into::into),
+            timezone: temp.timezone.map(|x| x.to_string().into()),
+            keymap: temp
+                .keymap
+                .filter(|_| init_format != InitFormat::Armbian)
+                .map(Into::into),
+            locale: temp
+                .locale
+                .filter(|_| init_format == InitFormat::Armbian)
+                .map(Into::into),
+            user: temp.user.map(|x| bb_flasher::sd::profile::User {
+                name: x.username.as_str().into(),
+                password: x.password(),
+            }),
+            ssh_key: temp
+                .ssh
+                .filter(|_| init_format != InitFormat::Armbian)
+                .map(Into::into),
+            usb_enable_dhcp: init_format == InitFormat::Sysconf
+                && temp.usb_enable_dhcp == Some(true),
+            network: bb_flasher::sd::sysconf::Network {
+                wifis: temp
+                    .wifi
+                    .map(|x| bb_flasher::sd::sysconf::Wifi::psk(x.ssid.into(), x.password.into()))
+                    .into_iter()
+                    .collect(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Load options from a profile. Options that cannot be shown in the GUI (e.g. additional
+    /// Wi-Fi networks) are ignored.
+    #[cfg(feature = "sd")]
+    pub(crate) fn from_profile(profile: &bb_flasher::sd::Profile) -> Self {
+        Self {
+            hostname: profile.hostname.as_deref().map(Into::into),
+            timezone: profile.timezone.as_deref().and_then(|x| x.parse().ok()),
+            keymap: profile.keymap.as_deref().map(Into::into),
+            locale: profile.locale.as_deref().map(Into::into),
+            user: profile.user.as_ref().map(|x| {
+                let mut user = SdCustomizationUser::new(x.name.to_string(), String::new());
+                match &x.password {
+                    bb_flasher::sd::Password::Plain(p) => user.password = p.to_string(),
+                    bb_flasher::sd::Password::Hashed(h) => user.password_hash = Some(h.to_string()),
+                }
+                user
+            }),
+            wifi: profile
+                .network
+                .wifis
+                .iter()
+                .find_map(|x| match &x.security {
+                    bb_flasher::sd::sysconf::WifiSecurity::Psk(p) => Some(SdCustomizationWifi {
+                        ssid: x.ssid.to_string(),
+                        password: p.to_string(),
+                    }),
+                    _ => None,
+                }),
+            ssh: profile.ssh_key.as_deref().map(Into::into),
+            usb_enable_dhcp: Some(profile.usb_enable_dhcp),
         }
     }
 
     #[cfg(feature = "sd")]
-    pub(crate) fn sysconfig(self) -> bb_flasher::sd::FlashingSdLinuxConfig {
+    pub(crate) fn sysconfig(
+        self,
+    ) -> Result<bb_flasher::sd::FlashingSdLinuxConfig, bb_flasher::sd::SysconfError> {
         bb_flasher::sd::FlashingSdLinuxConfig::sysconfig(
             self.hostname.map(Into::into),
             self.timezone.map(|x| x.to_string()).map(Into::into),
             self.keymap.map(Into::into),
-            self.user.map(|x| (x.username.into(), x.password.into())),
+            self.user.map(|x| (x.username.clone().into(), x.password())),
             self.wifi.map(|x| (x.ssid.into(), x.password.into())),
             self.ssh.map(Into::into),
             self.usb_enable_dhcp,
@@ -165,22 +295,68 @@ impl SdSysconfCustomization {
             self.hostname.map(Into::into),
             self.timezone.map(|x| x.to_string()).map(Into::into),
             self.keymap.map(Into::into),
-            self.user.map(|x| (x.username.into(), x.password.into())),
+            self.user.map(|x| (x.username.clone().into(), x.password())),
             self.wifi.map(|x| (x.ssid.into(), x.password.into())),
             self.ssh.map(Into::into),
         )
     }
+
+    #[cfg(feature = "sd")]
+    pub(crate) fn armbian(
+        self,
+    ) -> Result<bb_flasher::sd::FlashingSdLinuxConfig, bb_flasher::sd::ArmbianError> {
+        bb_flasher::sd::FlashingSdLinuxConfig::armbian(
+            self.hostname.map(Into::into),
+            self.timezone.map(|x| x.to_string()).map(Into::into),
+            self.locale.map(Into::into),
+            self.user.map(|x| (x.username.clone().into(), x.password())),
+            self.wifi.map(|x| (x.ssid.into(), x.password.into())),
+        )
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct SdCustomizationUser {
     pub(crate) username: String,
+    /// Plaintext password entered in the current session. Never persisted; only read to migrate
+    /// configurations saved by older versions.
+    #[serde(default, skip_serializing)]
     pub(crate) password: String,
+    /// SHA-512 crypt hash of a previously entered password.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) password_hash: Option<String>,
 }
 
 impl SdCustomizationUser {
     pub(crate) const fn new(username: String, password: String) -> Self {
-        Self { username, password }
+        Self {
+            username,
+            password,
+            password_hash: None,
+        }
+    }
+
+    /// Password to write to the image. A newly entered password takes precedence over the saved
+    /// hash.
+    #[cfg(feature = "sd")]
+    fn password(&self) -> bb_flasher::sd::Password {
+        match &self.password_hash {
+            Some(x) if self.password.is_empty() => {
+                bb_flasher::sd::Password::Hashed(x.as_str().into())
+            }
+            _ => bb_flasher::sd::Password::Plain(self.password.as_str().into()),
+        }
+    }
+
+    #[cfg(feature = "sd")]
+    fn hash_password(mut self) -> Self {
+        if !self.password.is_empty() {
+            if let bb_flasher::sd::Password::Hashed(x) = self.password().hash() {
+                self.password_hash = Some(x.into());
+            }
+            self.password.clear();
+        }
+        self
     }
 
     pub(crate) fn update_username(mut self, t: String) -> Self {
@@ -190,12 +366,9 @@ impl SdCustomizationUser {
 
     pub(crate) fn update_password(mut self, t: String) -> Self {
         self.password = t;
+        self.password_hash = None;
         self
     }
-
-    pub(crate) fn validate_username(&self) -> bool {
-        self.username != "root"
-    }
 }
 
 impl Default for SdCustomizationUser {
@@ -226,12 +399,6 @@ impl SdCustomizationWifi {
 mod tests {
     use super::*;
 
-    #[test]
-    fn sd_user_validate_rejects_root() {
-        assert!(!SdCustomizationUser::new("root".into(), "pw".into()).validate_username());
-        assert!(SdCustomizationUser::new("beagle".into(), "pw".into()).validate_username());
-    }
-
     #[test]
     fn sd_user_default_has_empty_password() {
         assert!(SdCustomizationUser::default().password.is_empty());
@@ -256,16 +423,54 @@ mod tests {
     }
 
     #[test]
-    fn sysconf_validate_user_follows_inner_user() {
+    fn sysconf_errors_follow_inner_user() {
+        use bb_flasher::validate::Field;
+
         // No user configured is always valid.
-        assert!(SdSysconfCustomization::default().validate_user());
+        assert!(SdSysconfCustomization::default().errors(false).is_empty());
         // A configured non-root user is valid; root is not.
         let ok = SdSysconfCustomization::default()
             .update_user(Some(SdCustomizationUser::new("beagle".into(), "pw".into())));
-        assert!(ok.validate_user());
+        assert!(ok.errors(false).is_empty());
         let bad = SdSysconfCustomization::default()
             .update_user(Some(SdCustomizationUser::new("root".into(), "pw".into())));
-        assert!(!bad.validate_user());
+        assert!(bad.errors(false).get(Field::Username).is_some());
+
+        // Empty password is only fine if a hash was saved earlier.
+        let mut user = SdCustomizationUser::new("beagle".into(), String::new());
+        let cfg = SdSysconfCustomization::default().update_user(Some(user.clone()));
+        assert!(cfg.errors(false).get(Field::Password).is_some());
+        user.password_hash = Some("$6$salt$hash".into());
+        let cfg = cfg.update_user(Some(user));
+        assert!(cfg.errors(false).is_empty());
+        // sysconf and Armbian need the password to be entered again.
+        assert!(cfg.errors(true).get(Field::Password).is_some());
+    }
+
+    #[test]
+    fn sysconf_errors_cover_all_fields() {
+        use bb_flasher::validate::Field;
+
+        let cfg = SdSysconfCustomization::default()
+            .update_hostname(Some("bad_host".into()))
+            .update_keymap(Some("xx".into()))
+            .update_ssh(Some("not a key".into()))
+            .update_wifi(Some(
+                SdCustomizationWifi::default()
+                    .update_ssid("a$b".into())
+                    .update_password("short".into()),
+            ));
+        let errors = cfg.errors(false);
+
+        for field in [
+            Field::Hostname,
+            Field::Keymap,
+            Field::SshKey,
+            Field::Ssid,
+            Field::WifiPassphrase,
+        ] {
+            assert!(errors.get(field).is_some(), "{field} not reported");
+        }
     }
 
     #[test]
@@ -365,7 +570,106 @@ mod tests {
                     .update_ssid("net".into())
                     .update_password("pw".into()),
             ));
-        let _ = base.clone().sysconfig();
+        base.clone().sysconfig().unwrap();
         let _ = base.cloudinit();
     }
+
+    #[test]
+    fn sd_user_password_is_never_serialized() {
+        let user = SdCustomizationUser::new("beagle".into(), "secret".into());
+        let json = serde_json::to_string(&user).unwrap();
+        assert!(!json.contains("secret"), "{json}");
+    }
+
+    #[test]
+    fn sd_user_loads_legacy_plaintext_password() {
+        let user: SdCustomizationUser =
+            serde_json::from_str(r#"{"username":"beagle","password":"secret"}"#).unwrap();
+        assert_eq!(user.password, "secret");
+        assert_eq!(user.password_hash, None);
+    }
+
+    #[test]
+    fn sd_user_update_password_discards_saved_hash() {
+        let mut user = SdCustomizationUser::new("beagle".into(), String::new());
+        user.password_hash = Some("$6$salt$hash".into());
+        let user = user.update_password("new".into());
+        assert_eq!(user.password_hash, None);
+    }
+
+    #[cfg(feature = "sd")]
+    #[test]
+    fn sd_customization_persists_only_password_hash() {
+        let mut sd = SdCustomization::default();
+        sd.update_sysconfig(
+            SdSysconfCustomization::default()
+                .update_user(Some(SdCustomizationUser::new("beagle".into(), "pw".into()))),
+        );
+
+        let user = sd.sysconf_customization().unwrap().user.clone().unwrap();
+        assert!(user.password.is_empty());
+        let hash = user.password_hash.clone().unwrap();
+        assert!(hash.starts_with("$6$"), "{hash}");
+
+        // The saved hash is reused as-is until a new password is entered.
+        assert_eq!(
+            user.password(),
+            bb_flasher::sd::Password::Hashed(hash.into())
+        );
+        assert_eq!(
+            user.update_password("new".into()).password(),
+            bb_flasher::sd::Password::Plain("new".into())
+        );
+    }
+
+    #[cfg(feature = "sd")]
+    #[test]
+    fn sysconf_profile_round_trip() {
+        use bb_flasher::sd::profile::InitFormat;
+
+        let cfg = SdSysconfCustomization::default()
+            .update_hostname(Some("beagle".into()))
+            .update_timezone(Some("UTC".parse().unwrap()))
+            .update_keymap(Some("us".into()))
+            .update_locale(Some("en_US.UTF-8".into()))
+            .update_wifi(Some(SdCustomizationWifi {
+                ssid: "net".into(),
+                password: "password".into(),
+            }))
+            .update_user(Some(SdCustomizationUser::new("beagle".into(), "pw".into())));
+
+        let profile = cfg.to_profile("lab", InitFormat::CloudInit);
+        assert_eq!(profile.name.as_deref(), Some("lab"));
+        assert_eq!(profile.locale, None);
+        assert!(matches!(
+            profile.user.as_ref().unwrap().password,
+            bb_flasher::sd::Password::Hashed(_)
+        ));
+        profile.validate().unwrap();
+
+        let back = SdSysconfCustomization::from_profile(&profile);
+        assert_eq!(back.hostname.as_deref(), Some("beagle"));
+        assert_eq!(back.timezone, Some(chrono_tz::Tz::UTC));
+        assert_eq!(back.keymap.as_deref(), Some("us"));
+        assert_eq!(back.wifi.map(|x| x.ssid), Some("net".into()));
+        let user = back.user.unwrap();
+        assert!(user.password.is_empty());
+        assert!(user.password_hash.is_some());
+
+        let sysconf = cfg.to_profile("lab", InitFormat::Sysconf);
+        assert_eq!(
+            sysconf.user.as_ref().unwrap().password,
+            bb_flasher::sd::Password::Plain("pw".into())
+        );
+        sysconf.validate().unwrap();
+
+        let armbian = cfg.to_profile("lab", InitFormat::Armbian);
+        assert_eq!(armbian.keymap, None);
+        assert_eq!(armbian.locale.as_deref(), Some("en_US.UTF-8"));
+        assert_eq!(
+            armbian.user.as_ref().unwrap().password,
+            bb_flasher::sd::Password::Plain("pw".into())
+        );
+        armbian.validate().unwrap();
+    }
 }
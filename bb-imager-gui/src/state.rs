@@ -291,6 +291,12 @@ impl FlashingContext {
 pub(crate) struct CustomizeState {
     pub(crate) common: BBImagerCommon,
     pub(crate) ctx: FlashingContext,
+    /// Names of saved customization profiles.
+    #[cfg(feature = "sd")]
+    pub(crate) profiles: Box<[Box<str>]>,
+    /// Name of the selected profile. Used when saving the current customization.
+    #[cfg(feature = "sd")]
+    pub(crate) profile_name: String,
 }
 
 impl CustomizeState {
@@ -303,6 +309,64 @@ impl CustomizeState {
             BBImagerMessage::Null
         }))
     }
+
+    #[cfg(feature = "sd")]
+    pub(crate) fn refresh_profiles(&self) -> Task<BBImagerMessage> {
+        Task::perform(
+            blocking_future(|| {
+                let Some(store) = helpers::profile_store() else {
+                    return Box::default();
+                };
+                match store.list() {
+                    Ok(x) => x.into(),
+                    Err(e) => {
+                        tracing::error!("Failed to list profiles: {e}");
+                        Box::default()
+                    }
+                }
+            }),
+            BBImagerMessage::ProfilesUpdated,
+        )
+    }
+
+    #[cfg(feature = "sd")]
+    pub(crate) fn load_profile(&mut self, name: Box<str>) -> Task<BBImagerMessage> {
+        self.profile_name = name.to_string();
+
+        let customization = self.ctx.customization.clone();
+        Task::perform(
+            blocking_future(move || {
+                let store = helpers::profile_store()?;
+                store
+                    .load(&name)
+                    .inspect_err(|e| tracing::error!("Failed to load profile {name}: {e}"))
+                    .ok()
+            }),
+            move |x| match x {
+                Some(p) => {
+                    BBImagerMessage::UpdateFlashConfig(customization.clone().with_profile(&p))
+                }
+                None => BBImagerMessage::Null,
+            },
+        )
+    }
+
+    #[cfg(feature = "sd")]
+    pub(crate) fn save_profile(&self) -> Task<BBImagerMessage> {
+        let Some(profile) = self.ctx.customization.to_profile(self.profile_name.trim()) else {
+            return Task::none();
+        };
+
+        Task::future(blocking_future(move || {
+            if let Some(store) = helpers::profile_store()
+                && let Err(e) = store.save(&profile)
+            {
+                tracing::error!("Failed to save profile: {e}");
+            }
+            BBImagerMessage::Null
+        }))
+        .chain(self.refresh_profiles())
+    }
 }
 
 #[derive(Debug)]
@@ -1,3 +1,4 @@
+use bb_flasher::validate::Field;
 use iced::{
     Element,
     widget::{self, text},
@@ -35,6 +36,7 @@ fn customization_pane<'a>(state: &'a crate::state::CustomizeState) -> Element<'a
     match &state.ctx.customization {
         FlashingCustomization::LinuxSdSysconfig(inner) => linux_sd_card_sysconfig(state, inner),
         FlashingCustomization::LinuxSdCloudInit(inner) => linux_sd_card_cloudinit(state, inner),
+        FlashingCustomization::LinuxSdArmbian(inner) => linux_sd_card_armbian(state, inner),
         _ => panic!("No customization"),
     }
 }
@@ -45,6 +47,22 @@ fn linux_sd_card_common<'a>(
     wrap: impl Fn(persistance::SdSysconfCustomization) -> FlashingCustomization + Copy + 'static,
 ) -> widget::Column<'a, BBImagerMessage> {
     let mut col = widget::column([]);
+    let armbian = matches!(
+        state.ctx.customization,
+        FlashingCustomization::LinuxSdArmbian(_)
+    );
+    // Only cloud-init supports password hashes.
+    let plaintext_password = !matches!(
+        state.ctx.customization,
+        FlashingCustomization::LinuxSdCloudInit(_)
+    );
+    let errors = config.errors(plaintext_password);
+    let invalid = |field| errors.get(field).is_some();
+
+    #[cfg(feature = "sd")]
+    {
+        col = col.extend([profile_picker(state), widget::rule::horizontal(2).into()]);
+    }
 
     // Username and Password
     col = col.push(
@@ -68,12 +86,18 @@ fn linux_sd_card_common<'a>(
                             .update_user(Some(usr.clone().update_username(inp))),
                     )
                 },
-                !usr.validate_username(),
+                invalid(Field::Username),
             )
             .into(),
             input_with_label(
                 "Password",
-                "password",
+                // The saved password is only kept as a hash, so it cannot be shown. sysconf and
+                // Armbian need it in plaintext, so it has to be entered again.
+                if usr.password_hash.is_some() && !plaintext_password {
+                    "(saved)"
+                } else {
+                    "password"
+                },
                 &usr.password,
                 move |inp| {
                     wrap(
@@ -82,7 +106,7 @@ fn linux_sd_card_common<'a>(
                             .update_user(Some(usr.clone().update_password(inp))),
                     )
                 },
-                false,
+                invalid(Field::Password),
             )
             .into(),
         ])
@@ -112,7 +136,7 @@ fn linux_sd_card_common<'a>(
                             .update_wifi(Some(wifi.clone().update_ssid(inp))),
                     )
                 },
-                false,
+                invalid(Field::Ssid),
             )
             .into(),
             input_with_label(
@@ -126,7 +150,7 @@ fn linux_sd_card_common<'a>(
                             .update_wifi(Some(wifi.clone().update_password(inp))),
                     )
                 },
-                false,
+                invalid(Field::WifiPassphrase),
             )
             .into(),
         ])
@@ -176,6 +200,7 @@ fn linux_sd_card_common<'a>(
                         config.clone().update_hostname(Some(inp)),
                     ))
                 })
+                .style(input_style(invalid(Field::Hostname)))
                 .width(INPUT_WIDTH)
                 .into(),
         )),
@@ -184,7 +209,11 @@ fn linux_sd_card_common<'a>(
 
     col = col.push(widget::rule::horizontal(2));
 
-    // Keymap
+    // Keymap and SSH key. Not supported by Armbian, which gets a locale instead.
+    if armbian {
+        return col;
+    }
+
     let toggle = widget::toggler(config.keymap.is_some())
         .label("Set Keymap")
         .on_toggle(move |t| {
@@ -224,19 +253,59 @@ fn linux_sd_card_common<'a>(
 
     col = col.push(widget::rule::horizontal(2));
 
+    linux_sd_card_ssh(col, config, wrap, invalid(Field::SshKey))
+}
+
+/// Load a saved profile, or save the current customization as one.
+#[cfg(feature = "sd")]
+fn profile_picker<'a>(state: &'a crate::state::CustomizeState) -> Element<'a, BBImagerMessage> {
+    let selected = state
+        .profiles
+        .iter()
+        .find(|x| x.as_ref() == state.profile_name.trim());
+
+    element_with_element(
+        widget::pick_list(
+            state.profiles.as_ref(),
+            selected,
+            BBImagerMessage::SelectProfile,
+        )
+        .placeholder("Load Profile")
+        .into(),
+        widget::row![
+            widget::text_input("profile name", &state.profile_name)
+                .on_input(BBImagerMessage::UpdateProfileName)
+                .width(INPUT_WIDTH),
+            widget::button("SAVE").on_press_maybe(
+                (!state.profile_name.trim().is_empty()).then_some(BBImagerMessage::SaveProfile)
+            ),
+        ]
+        .spacing(8)
+        .align_y(iced::Alignment::Center)
+        .into(),
+    )
+    .into()
+}
+
+fn linux_sd_card_ssh<'a>(
+    col: widget::Column<'a, BBImagerMessage>,
+    config: &'a persistance::SdSysconfCustomization,
+    wrap: impl Fn(persistance::SdSysconfCustomization) -> FlashingCustomization + Copy + 'static,
+    invalid_key: bool,
+) -> widget::Column<'a, BBImagerMessage> {
     // SSH Key
     col.extend([
         text("SSH authorization public key").into(),
         widget::center(
-            widget::text_input("authorized key", config.ssh.as_deref().unwrap_or("")).on_input(
-                move |x| {
+            widget::text_input("authorized key", config.ssh.as_deref().unwrap_or(""))
+                .on_input(move |x| {
                     BBImagerMessage::UpdateFlashConfig(wrap(
                         config
                             .clone()
                             .update_ssh(if x.is_empty() { None } else { Some(x) }),
                     ))
-                },
-            ),
+                })
+                .style(input_style(invalid_key)),
         )
         .padding(iced::Padding::ZERO.horizontal(16))
         .into(),
@@ -251,6 +320,44 @@ fn linux_sd_card_cloudinit<'a>(
     detail_pane(col, &state.common.scroll_id)
 }
 
+fn linux_sd_card_armbian<'a>(
+    state: &'a crate::state::CustomizeState,
+    config: &'a persistance::SdSysconfCustomization,
+) -> Element<'a, BBImagerMessage> {
+    let mut col = linux_sd_card_common(state, config, FlashingCustomization::LinuxSdArmbian);
+
+    col = col.push(widget::rule::horizontal(2));
+    // Locale
+    let toggle = widget::toggler(config.locale.is_some())
+        .label("Set Locale")
+        .on_toggle(move |t| {
+            let locale = if t {
+                Some(helpers::system_locale())
+            } else {
+                None
+            };
+            BBImagerMessage::UpdateFlashConfig(FlashingCustomization::LinuxSdArmbian(
+                config.clone().update_locale(locale),
+            ))
+        });
+    col = match config.locale.as_ref() {
+        Some(locale) => col.push(element_with_element(
+            toggle.into(),
+            widget::text_input("en_US.UTF-8", locale)
+                .on_input(move |inp| {
+                    BBImagerMessage::UpdateFlashConfig(FlashingCustomization::LinuxSdArmbian(
+                        config.clone().update_locale(Some(inp)),
+                    ))
+                })
+                .width(INPUT_WIDTH)
+                .into(),
+        )),
+        None => col.push(toggle),
+    };
+
+    detail_pane(col, &state.common.scroll_id)
+}
+
 fn linux_sd_card_sysconfig<'a>(
     state: &'a crate::state::CustomizeState,
     config: &'a persistance::SdSysconfCustomization,
@@ -286,17 +393,24 @@ where
         label,
         widget::text_input(placeholder, val)
             .on_input(move |inp| BBImagerMessage::UpdateFlashConfig(update_config_cb(inp)))
-            .style(move |theme, status| {
-                let mut t = widget::text_input::default(theme, status);
-
-                if invalid_val {
-                    t.border = t.border.color(theme.palette().danger);
-                    t
-                } else {
-                    t
-                }
-            })
+            .style(input_style(invalid_val))
             .width(INPUT_WIDTH)
             .into(),
     )
 }
+
+/// Text input style with the border highlighted if the value is invalid.
+fn input_style(
+    invalid_val: bool,
+) -> impl Fn(&iced::Theme, widget::text_input::Status) -> widget::text_input::Style {
+    move |theme, status| {
+        let mut t = widget::text_input::default(theme, status);
+
+        if invalid_val {
+            t.border = t.border.color(theme.palette().danger);
+            t
+        } else {
+            t
+        }
+    }
+}